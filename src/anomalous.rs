@@ -0,0 +1,124 @@
+use rug::{ops::RemRounding, Integer};
+
+use crate::{ec_discrete_log, Curve, Error, Point};
+
+/// Lifts `curve` to a curve over `Z/p**2` with coefficient `a_lift` that passes through `point`.
+///
+/// The coefficient `b` of the lifted curve is chosen so that `point` itself (seen as a pair of integers) lies on it.
+fn lift_curve(curve: &Curve, a_lift: &Integer, point: (&Integer, &Integer)) -> Curve {
+    let p2 = Integer::from(curve.p().square_ref());
+    let (x, y) = point;
+    let b_lift =
+        (Integer::from(y.square_ref()) - (Integer::from(x.square_ref()) + a_lift) * x).rem_euc(&p2);
+    Curve::new(p2, a_lift.clone(), b_lift)
+}
+
+/// Lifts the point `(x, y)` of `curve` to `lifted` (defined over `Z/p**2`) with Hensel's lemma on `y`.
+fn lift_point(curve: &Curve, lifted: &Curve, x: &Integer, y: &Integer) -> (Integer, Integer) {
+    let p = curve.p();
+    let p2 = lifted.p();
+    let rhs = (Integer::from(x.square_ref()) + lifted.a()) * x + lifted.b();
+    let delta = Integer::from(&rhs - y.square_ref()).rem_euc(p2) / p;
+    let correction = delta * Integer::from(y * 2).invert(p).unwrap() % p;
+    (x.clone(), (correction * p + y) % p2)
+}
+
+/// Returns the p-adic elliptic logarithm of `p * point` divided by `p`, modulo `p`, where `point` is a point of
+/// `lifted` (over `Z/p**2`) whose reduction has order `p`.
+///
+/// `p * point` lies in the kernel of the reduction, where the formal group parameter `-x/y` is computed from the
+/// slope of the last addition `(p - 1) * point + point`.
+fn p_adic_log(p: &Integer, lifted: &Curve, (x, y): (&Integer, &Integer)) -> Integer {
+    let p2 = lifted.p();
+    let point = Point::Affine(x.clone(), y.clone());
+    let (x1, y1) = match lifted.mul(&point, &Integer::from(p - 1)) {
+        Point::Affine(x1, y1) => (x1, y1),
+        Point::Infinity => unreachable!("(p - 1) * point does not reduce to infinity"),
+    };
+    // x - x1 is divisible by p, and y - y1 = 2*y (mod p) is invertible
+    let dx = Integer::from(x - &x1).rem_euc(p2) / p;
+    let dy = Integer::from(y - &y1).rem_euc(p2);
+    dx * dy.invert(p).unwrap() % p
+}
+
+/// Compute the elliptic curve discrete logarithm of `q` in base `p` on an anomalous `curve` (smallest non-negative
+/// integer `k` where `k*p = q`) with Smart's attack.
+///
+/// A curve is anomalous when it has exactly `p` points (see `Curve::is_anomalous`). The points are lifted to the
+/// p-adic numbers, where the p-adic elliptic logarithm maps `p*P` and `p*Q` to `F_p+`, and the discrete logarithm is
+/// then a single modular division: the computation runs in polynomial time.
+///
+/// If `p` does not have order `p` (the field characteristic), `ec_discrete_log` is used instead.
+pub fn ec_discrete_log_smart(curve: &Curve, p: &Point, q: &Point) -> Result<Integer, Error> {
+    if !curve.contains(p) || !curve.contains(q) {
+        return Err(Error::PointNotOnCurve);
+    }
+    let modulus = curve.p();
+    let (px, py) = match p {
+        Point::Affine(x, y) if curve.mul(p, modulus) == Point::Infinity => (x, y),
+        _ => return ec_discrete_log(curve, p, q, None),
+    };
+    let (qx, qy) = match q {
+        Point::Infinity => return Ok(Integer::ZERO),
+        Point::Affine(x, y) => (x, y),
+    };
+
+    // The attack fails on the canonical lift of the curve, whose p-adic logarithms vanish: another lift of `a` is
+    // tried in that case
+    let mut a_lift = curve.a().clone();
+    loop {
+        let lifted = lift_curve(curve, &a_lift, (px, py));
+        let p_log = p_adic_log(modulus, &lifted, (px, py));
+        if p_log != 0 {
+            let (qx, qy) = lift_point(curve, &lifted, qx, qy);
+            let q_log = p_adic_log(modulus, &lifted, (&qx, &qy));
+            let k = q_log * p_log.invert(modulus).unwrap() % modulus;
+            return if curve.mul(p, &k) == *q {
+                Ok(k)
+            } else {
+                Err(Error::LogDoesNotExist)
+            };
+        }
+        a_lift += modulus;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use super::*;
+
+    #[test]
+    fn smart_attack() {
+        // y^2 = x^3 + 32 over p = (1 + 3v^2) / 4, a twist of trace 1 of the j = 0 curves (exactly p points)
+        let curve = Curve::new(
+            Integer::from_str("255211775190703851000955237173238443091").unwrap(),
+            0.into(),
+            32.into(),
+        );
+        assert!(curve.is_anomalous());
+        let p = curve
+            .point(
+                1.into(),
+                Integer::from_str("17239946369424902473577213543680726305").unwrap(),
+            )
+            .unwrap();
+        let q = curve
+            .point(
+                Integer::from_str("15051538988963227109383639747462634468").unwrap(),
+                Integer::from_str("132025982064244624066350648445798833478").unwrap(),
+            )
+            .unwrap();
+        let k = Integer::from_str("40125655066622386354123033417875897284").unwrap();
+        assert_eq!(ec_discrete_log_smart(&curve, &p, &q).unwrap(), k);
+        assert_eq!(ec_discrete_log(&curve, &p, &q, None).unwrap(), k);
+        assert_eq!(ec_discrete_log_smart(&curve, &p, &p).unwrap(), 1);
+        assert_eq!(
+            ec_discrete_log_smart(&curve, &p, &Point::Infinity).unwrap(),
+            0
+        );
+
+        assert!(!Curve::new(97.into(), 2.into(), 3.into()).is_anomalous());
+    }
+}
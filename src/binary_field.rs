@@ -0,0 +1,763 @@
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+
+use rug::{ops::RemRounding, rand::RandState, Integer};
+
+use crate::{
+    discrete_log_group, element_order, pohlig_hellman::pohlig_hellman_with_factors, shanks_steps,
+    utils::factor_power_minus_one, Error, Factorization, Group,
+};
+
+/// Largest degree of the factor base of `discrete_log_binary_index_calculus`.
+const MAX_FACTOR_BASE_DEGREE: usize = 20;
+
+/// Largest number of consecutive tries to find a relation in `discrete_log_binary_index_calculus`.
+const MAX_RELATION_TRIES: u64 = 1 << 24;
+
+/// Number of relations collected beyond the number of unknowns before solving the system of relations.
+const RELATION_MARGIN: usize = 16;
+
+/// Number of times the system of relations is solved, with more relations each time, before giving up.
+const SOLVE_ATTEMPTS: usize = 4;
+
+/// Returns the degree of the non-zero binary polynomial `a`.
+fn degree(a: &Integer) -> usize {
+    a.significant_bits() as usize - 1
+}
+
+/// Returns the product of the binary polynomials `a` and `b` (carry-less multiplication).
+fn clmul(a: &Integer, b: &Integer) -> Integer {
+    let (a, b) = if a.count_ones() < b.count_ones() {
+        (a, b)
+    } else {
+        (b, a)
+    };
+    let mut result = Integer::ZERO;
+    let mut bit = a.find_one(0);
+    while let Some(i) = bit {
+        result ^= Integer::from(b << i);
+        bit = a.find_one(i + 1);
+    }
+    result
+}
+
+/// Returns the quotient and the remainder of the division of the binary polynomial `a` by `b`.
+fn div_rem(a: &Integer, b: &Integer) -> (Integer, Integer) {
+    let db = degree(b);
+    let mut quotient = Integer::ZERO;
+    let mut remainder = a.clone();
+    while remainder != 0 && degree(&remainder) >= db {
+        let shift = (degree(&remainder) - db) as u32;
+        quotient.toggle_bit(shift);
+        remainder ^= Integer::from(b << shift);
+    }
+    (quotient, remainder)
+}
+
+/// Returns the greatest common divisor of the binary polynomials `a` and `b`.
+fn gcd(a: &Integer, b: &Integer) -> Integer {
+    let (mut a, mut b) = (a.clone(), b.clone());
+    while b != 0 {
+        let r = div_rem(&a, &b).1;
+        a = b;
+        b = r;
+    }
+    a
+}
+
+/// The binary field `F_{2^m}`, represented as `F_2[x]/(f(x))` for an irreducible polynomial `f` of degree `m`, usually
+/// a trinomial or a pentanomial.
+///
+/// Elements are non-negative integers whose bit `i` is the coefficient of `x**i` (bit-packed polynomials of degree
+/// less than `m`). The multiplicative group `F_{2^m}*` implements `Group` (see also `discrete_log_binary`).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BinaryField {
+    m: usize,
+    /// Exponents of the modulus below `m`, in decreasing order.
+    terms: Vec<usize>,
+    modulus: Integer,
+}
+
+impl BinaryField {
+    /// Creates the field `F_{2^m}`, `m` being at least 1.
+    ///
+    /// The modulus is the irreducible trinomial `x**m + x**k + 1` with the smallest `k`, or the irreducible pentanomial
+    /// `x**m + x**k3 + x**k2 + x**k1 + 1` with the smallest `(k3, k2, k1)` if there is no such trinomial.
+    pub fn new(m: usize) -> Self {
+        if m == 1 {
+            return Self::from_terms(1, vec![0]);
+        }
+        let trinomials = (1..m).map(|k| vec![k, 0]);
+        let pentanomials = (3..m)
+            .flat_map(|k3| (2..k3).flat_map(move |k2| (1..k2).map(move |k1| vec![k3, k2, k1, 0])));
+        trinomials
+            .chain(pentanomials)
+            .map(|terms| Self::from_terms(m, terms))
+            .find(Self::is_irreducible)
+            .unwrap()
+    }
+
+    /// Creates the field `F_2[x]/(f(x))`, `f` being given by the exponents of its non-zero terms (for instance
+    /// `[163, 7, 6, 3, 0]` for `x**163 + x**7 + x**6 + x**3 + 1`).
+    ///
+    /// Returns `Error::ReducibleModulus` if `f` is not irreducible.
+    pub fn with_modulus(exponents: &[usize]) -> Result<Self, Error> {
+        let mut exponents = exponents.to_vec();
+        exponents.sort_unstable_by(|a, b| b.cmp(a));
+        exponents.dedup();
+        let field = match exponents.split_first() {
+            Some((&m, terms)) if m > 0 => Self::from_terms(m, terms.to_vec()),
+            _ => return Err(Error::ReducibleModulus),
+        };
+        if field.is_irreducible() {
+            Ok(field)
+        } else {
+            Err(Error::ReducibleModulus)
+        }
+    }
+
+    fn from_terms(m: usize, terms: Vec<usize>) -> Self {
+        let mut modulus = Integer::from(1) << m as u32;
+        for t in &terms {
+            modulus.toggle_bit(*t as u32);
+        }
+        Self { m, terms, modulus }
+    }
+
+    /// Returns `true` if the modulus is irreducible (Rabin's test).
+    fn is_irreducible(&self) -> bool {
+        if !self.modulus.get_bit(0) {
+            return self.m == 1 && self.modulus == 2;
+        }
+        // x**(2**i) for i = 0..=m
+        let mut frobenius = vec![self.reduce(&Integer::from(2))];
+        for i in 0..self.m {
+            frobenius.push(self.square(&frobenius[i]));
+        }
+        if frobenius[self.m] != frobenius[0] {
+            return false;
+        }
+
+        let m = self.m;
+        (2..=m)
+            .filter(|q| m.is_multiple_of(*q) && (2..*q).all(|d| !q.is_multiple_of(d)))
+            .all(|q| gcd(&(Integer::from(&frobenius[m / q] ^ 2u32)), &self.modulus) == 1)
+    }
+
+    /// Returns the degree `m` of the field over `F_2`.
+    pub fn m(&self) -> usize {
+        self.m
+    }
+
+    /// Returns the exponents of the non-zero terms of the modulus, in decreasing order.
+    pub fn modulus(&self) -> Vec<usize> {
+        let mut exponents = vec![self.m];
+        exponents.extend(&self.terms);
+        exponents
+    }
+
+    /// Returns the number of elements of the field, `2**m`.
+    pub fn size(&self) -> Integer {
+        Integer::from(1) << self.m as u32
+    }
+
+    /// Returns the element represented by the polynomial `a` (reduced modulo the modulus of the field).
+    ///
+    /// `a` must be non-negative.
+    pub fn element(&self, a: &Integer) -> Integer {
+        self.reduce(a)
+    }
+
+    /// Reduces the binary polynomial `a` modulo the sparse modulus, using `x**m = sum(x**t)`.
+    fn reduce(&self, a: &Integer) -> Integer {
+        let mut a = a.clone();
+        while a.significant_bits() as usize > self.m {
+            let high = Integer::from(&a >> self.m as u32);
+            a.keep_bits_mut(self.m as u32);
+            for t in &self.terms {
+                a ^= Integer::from(&high << *t as u32);
+            }
+        }
+        a
+    }
+
+    /// Returns a random element of the field.
+    pub fn random(&self, rand_state: &mut RandState<'_>) -> Integer {
+        self.size().random_below(rand_state)
+    }
+
+    /// Returns `a + b` (which is also `a - b`).
+    pub fn add(&self, a: &Integer, b: &Integer) -> Integer {
+        Integer::from(a ^ b)
+    }
+
+    /// Returns `a * b`.
+    pub fn mul(&self, a: &Integer, b: &Integer) -> Integer {
+        self.reduce(&clmul(a, b))
+    }
+
+    /// Returns `a**2`.
+    ///
+    /// Squaring is linear over `F_2`: the bits of `a` are spread out before the reduction.
+    pub fn square(&self, a: &Integer) -> Integer {
+        let mut result = Integer::ZERO;
+        let mut bit = a.find_one(0);
+        while let Some(i) = bit {
+            result.set_bit(2 * i, true);
+            bit = a.find_one(i + 1);
+        }
+        self.reduce(&result)
+    }
+
+    /// Returns the inverse of `a`, `None` if `a` is zero (extended Euclidean algorithm).
+    pub fn invert(&self, a: &Integer) -> Option<Integer> {
+        let mut u = self.reduce(a);
+        if u == 0 {
+            return None;
+        }
+        let mut v = self.modulus.clone();
+        let (mut g1, mut g2) = (Integer::from(1), Integer::ZERO);
+        while u != 1 {
+            if degree(&u) < degree(&v) {
+                std::mem::swap(&mut u, &mut v);
+                std::mem::swap(&mut g1, &mut g2);
+            }
+            let shift = (degree(&u) - degree(&v)) as u32;
+            u ^= Integer::from(&v << shift);
+            g1 ^= Integer::from(&g2 << shift);
+        }
+        Some(self.reduce(&g1))
+    }
+
+    /// Returns the prime factorization of the order `2**m - 1` of the multiplicative group.
+    pub fn multiplicative_order_factors(&self) -> Factorization {
+        factor_power_minus_one(&2.into(), self.m)
+    }
+
+    /// Writes `c` as a quotient `u/v` of two polynomials of degree about `m/2`, with the extended Euclidean algorithm
+    /// stopped halfway.
+    fn rational_reconstruction(&self, c: &Integer) -> (Integer, Integer) {
+        let (mut r0, mut r1) = (self.modulus.clone(), c.clone());
+        let (mut t0, mut t1) = (Integer::ZERO, Integer::from(1));
+        while degree(&r1) >= self.m.div_ceil(2) {
+            let (q, r) = div_rem(&r0, &r1);
+            let t = t0 ^ clmul(&q, &t1);
+            (r0, r1) = (r1, r);
+            (t0, t1) = (t1, t);
+        }
+        (r1, t1)
+    }
+}
+
+impl Group for BinaryField {
+    type Element = Integer;
+
+    fn identity(&self) -> Integer {
+        Integer::from(1)
+    }
+
+    fn op(&self, a: &Integer, b: &Integer) -> Integer {
+        self.mul(a, b)
+    }
+
+    /// Returns the inverse of `a` in the field.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `a` is zero.
+    fn inverse(&self, a: &Integer) -> Integer {
+        self.invert(a).expect("element is not invertible")
+    }
+
+    fn encode(&self, a: &Integer) -> Integer {
+        a.clone()
+    }
+
+    fn pow(&self, a: &Integer, k: &Integer) -> Integer {
+        let base = if *k < 0 {
+            self.inverse(a)
+        } else {
+            self.reduce(a)
+        };
+        let mut result = Integer::from(1);
+        for i in (0..k.significant_bits()).rev() {
+            result = self.square(&result);
+            if k.get_bit(i) {
+                result = self.mul(&result, &base);
+            }
+        }
+        result
+    }
+}
+
+/// Returns the irreducible binary polynomials of degree at most `max_degree`, by increasing degree.
+fn irreducible_polynomials(max_degree: usize) -> Vec<Integer> {
+    let mut irreducibles: Vec<Integer> = Vec::new();
+    for d in 1..=max_degree {
+        for low in 0u64..(1 << d) {
+            let f = Integer::from(low) + (Integer::from(1) << d as u32);
+            if irreducibles
+                .iter()
+                .take_while(|g| 2 * degree(g) <= d)
+                .all(|g| div_rem(&f, g).1 != 0)
+            {
+                irreducibles.push(f);
+            }
+        }
+    }
+    irreducibles
+}
+
+/// Factors the binary polynomial `h` over the factor base, returning the exponents of the factors, or `None` if `h`
+/// has an irreducible factor of degree greater than `max_degree`.
+fn factor_smooth(
+    mut h: Integer,
+    factor_base: &[Integer],
+    index: &HashMap<Integer, usize>,
+    max_degree: usize,
+) -> Option<Vec<(usize, u32)>> {
+    let mut factors = Vec::new();
+    for (i, g) in factor_base.iter().enumerate() {
+        if h == 1 || 2 * degree(g) > degree(&h) {
+            break;
+        }
+        let mut e = 0;
+        loop {
+            let (q, r) = div_rem(&h, g);
+            if r != 0 {
+                break;
+            }
+            h = q;
+            e += 1;
+        }
+        if e > 0 {
+            factors.push((i, e));
+        }
+    }
+    if h != 1 {
+        // `h` has no factor of degree at most half its degree: it is irreducible
+        if degree(&h) > max_degree {
+            return None;
+        }
+        factors.push((index[&h], 1));
+    }
+    Some(factors)
+}
+
+/// Returns the expected number of tries to find a relation in `F_{2^m}` with a factor base of degree `b`.
+///
+/// The two halves of a relation have degree about `m/2`, and a random polynomial of degree `n` has all its factors of
+/// degree at most `b` with a probability of about `u**-u`, with `u = n/b`.
+fn expected_tries(m: usize, b: usize) -> f64 {
+    let u = (m.div_ceil(2) as f64 / b as f64).max(1.0);
+    u.powf(2.0 * u)
+}
+
+/// Returns the degree bound of the factor base minimizing the expected cost of the relation search in `F_{2^m}`.
+fn factor_base_degree(m: usize) -> usize {
+    (1..=MAX_FACTOR_BASE_DEGREE.min(m))
+        .min_by(|&b1, &b2| {
+            let cost = |b: usize| {
+                let size = 2f64.powi(b as i32 + 1) / b as f64;
+                size * size * expected_tries(m, b)
+            };
+            cost(b1).total_cmp(&cost(b2))
+        })
+        .unwrap()
+}
+
+/// Sparse linear relation `sum(e_i * L_i) = rhs` between the logarithms `L_i` of the factor base, modulo the order.
+#[derive(Debug, Clone)]
+struct SparseRow {
+    /// Non-zero coefficients `(i, e_i)`, by increasing `i`.
+    entries: Vec<(usize, Integer)>,
+    rhs: Integer,
+}
+
+impl SparseRow {
+    /// Returns the coefficient of the unknown `i`.
+    fn coefficient(&self, i: usize) -> Integer {
+        self.entries
+            .binary_search_by_key(&i, |(j, _)| *j)
+            .map_or(Integer::ZERO, |k| self.entries[k].1.clone())
+    }
+
+    /// Returns `self - c * other` modulo `modulus`.
+    fn sub_mul(&self, c: &Integer, other: &SparseRow, modulus: &Integer) -> SparseRow {
+        let mut entries = Vec::with_capacity(self.entries.len() + other.entries.len());
+        let (mut s, mut o) = (
+            self.entries.iter().peekable(),
+            other.entries.iter().peekable(),
+        );
+        loop {
+            let (i, e) = match (s.peek(), o.peek()) {
+                (Some((i, e)), Some((j, _))) if i < j => {
+                    s.next();
+                    (*i, e.clone())
+                }
+                (Some((i, e)), Some((j, f))) if i == j => {
+                    s.next();
+                    o.next();
+                    (*i, (e - Integer::from(c * f)).rem_euc(modulus))
+                }
+                (_, Some((j, f))) => {
+                    o.next();
+                    (*j, (-Integer::from(c * f)).rem_euc(modulus))
+                }
+                (Some((i, e)), None) => {
+                    s.next();
+                    (*i, e.clone())
+                }
+                (None, None) => break,
+            };
+            if e != 0 {
+                entries.push((i, e));
+            }
+        }
+        let rhs = (&self.rhs - Integer::from(c * &other.rhs)).rem_euc(modulus);
+        SparseRow { entries, rhs }
+    }
+}
+
+/// Rows containing each unknown of a sparse system, with the unknowns not eliminated yet ordered by weight (number of
+/// rows containing them).
+struct ColumnIndex {
+    rows: Vec<BTreeSet<usize>>,
+    by_weight: BTreeSet<(usize, usize)>,
+}
+
+impl ColumnIndex {
+    fn new(columns: usize) -> Self {
+        Self {
+            rows: vec![BTreeSet::new(); columns],
+            by_weight: BTreeSet::new(),
+        }
+    }
+
+    fn update(&mut self, i: usize, change: impl FnOnce(&mut BTreeSet<usize>)) {
+        self.by_weight.remove(&(self.rows[i].len(), i));
+        change(&mut self.rows[i]);
+        if !self.rows[i].is_empty() {
+            self.by_weight.insert((self.rows[i].len(), i));
+        }
+    }
+
+    fn add_row(&mut self, r: usize, row: &SparseRow) {
+        for &(i, _) in &row.entries {
+            self.update(i, |rows| {
+                rows.insert(r);
+            });
+        }
+    }
+
+    fn remove_row(&mut self, r: usize, row: &SparseRow) {
+        for &(i, _) in &row.entries {
+            self.update(i, |rows| {
+                rows.remove(&r);
+            });
+        }
+    }
+
+    /// Removes the unknown of smallest non-zero weight, returning it with the rows containing it.
+    fn pop_lightest(&mut self) -> Option<(usize, BTreeSet<usize>)> {
+        let (_, i) = self.by_weight.pop_first()?;
+        Some((i, std::mem::take(&mut self.rows[i])))
+    }
+}
+
+/// Solves the sparse linear system `rows` in `columns` unknowns modulo the prime `modulus` with structured Gaussian
+/// elimination, returning a solution whose undetermined unknowns are 0 (the equations which are inconsistent with the
+/// others are ignored).
+///
+/// Each pivot is taken in the unknown appearing in the fewest rows, and in the shortest of them, which keeps the
+/// fill-in low: the unknowns appearing in a single row (such as most of the factor base of the largest degree) are
+/// eliminated without any fill-in, as are the rows made of a single unknown, before the elimination of the dense core.
+fn solve_sparse(mut rows: Vec<SparseRow>, columns: usize, modulus: &Integer) -> Vec<Integer> {
+    let mut index = ColumnIndex::new(columns);
+    for (r, row) in rows.iter().enumerate() {
+        index.add_row(r, row);
+    }
+
+    let mut pivots = Vec::new();
+    while let Some((i, containing)) = index.pop_lightest() {
+        let r = *containing
+            .iter()
+            .min_by_key(|&&r| rows[r].entries.len())
+            .unwrap();
+        index.remove_row(r, &rows[r]);
+        let inverse = rows[r].coefficient(i).invert(modulus).unwrap();
+        let pivot = SparseRow {
+            entries: rows[r]
+                .entries
+                .iter()
+                .map(|(j, e)| (*j, Integer::from(e * &inverse) % modulus))
+                .collect(),
+            rhs: Integer::from(&rows[r].rhs * &inverse) % modulus,
+        };
+        for &s in containing.iter().filter(|&&s| s != r) {
+            index.remove_row(s, &rows[s]);
+            rows[s] = rows[s].sub_mul(&rows[s].coefficient(i), &pivot, modulus);
+            index.add_row(s, &rows[s]);
+        }
+        rows[r] = pivot;
+        pivots.push((i, r));
+    }
+
+    // The row of each pivot only contains unknowns eliminated after it
+    let mut solution = vec![Integer::ZERO; columns];
+    for &(i, r) in pivots.iter().rev() {
+        let mut value = rows[r].rhs.clone();
+        for (j, e) in rows[r].entries.iter().filter(|(j, _)| *j != i) {
+            value -= Integer::from(e * &solution[*j]);
+        }
+        solution[i] = value.rem_euc(modulus);
+    }
+    solution
+}
+
+/// Index Calculus algorithm for computing the discrete logarithm of `a` in base `b` in the binary field `field`
+/// (smallest non-negative integer `x` where `b**x = a` in `F_{2^m}`).
+///
+/// `order` must be the order of `b`, prime and not dividing `(2**m - 1) / order`. The factor base is made of the
+/// irreducible polynomials of small degree, and the relations are found with random powers of `b` written as quotients
+/// of two polynomials of degree `m/2` (rational reconstruction), both of which must factor over the factor base (this
+/// is the basic `L(1/2)` index calculus: the `L(1/3)` relation generation of Coppersmith's algorithm is not
+/// implemented). The sparse system of relations is solved with structured Gaussian elimination once there are a few
+/// more relations than unknowns. It is much faster than the generic algorithms for large fields, and might fail to find
+/// a solution for small orders.
+pub fn discrete_log_binary_index_calculus(
+    field: &BinaryField,
+    a: &Integer,
+    b: &Integer,
+    order: &Integer,
+) -> Result<Integer, Error> {
+    let a = field.reduce(a);
+    let b = field.reduce(b);
+    if a == 0 || b == 0 {
+        return Err(Error::LogDoesNotExist);
+    }
+
+    let max_degree = factor_base_degree(field.m());
+    let factor_base = irreducible_polynomials(max_degree);
+    let index = factor_base
+        .iter()
+        .enumerate()
+        .map(|(i, g)| (g.clone(), i))
+        .collect::<HashMap<_, _>>();
+    let lf = factor_base.len();
+
+    // Exponents (numerator minus denominator) of a quotient u/v = c, c being b**x
+    let relation = |c: &Integer, x: &Integer| -> Option<SparseRow> {
+        let (u, v) = field.rational_reconstruction(c);
+        let mut exponents = BTreeMap::new();
+        for (i, e) in factor_smooth(u, &factor_base, &index, max_degree)? {
+            *exponents.entry(i).or_insert(0i64) += e as i64;
+        }
+        for (i, e) in factor_smooth(v, &factor_base, &index, max_degree)? {
+            *exponents.entry(i).or_insert(0i64) -= e as i64;
+        }
+        Some(SparseRow {
+            entries: exponents
+                .into_iter()
+                .map(|(i, e)| (i, Integer::from(e).rem_euc(order)))
+                .filter(|(_, e)| *e != 0)
+                .collect(),
+            rhs: x.clone().rem_euc(order),
+        })
+    };
+
+    // Expected number of tries to find a relation, with a large margin
+    let max_tries = (8.0 * expected_tries(field.m(), max_degree))
+        .clamp(1024.0, MAX_RELATION_TRIES as f64) as u64;
+
+    // First, find a relation for a: a * b**x = u/v
+    let mut relation_a = None;
+    let mut abx = a.clone();
+    for x in 0..max_tries {
+        if abx == 1 {
+            return Ok((order - Integer::from(x)).rem_euc(order));
+        }
+        if let Some(row) = relation(&abx, &Integer::from(x)) {
+            relation_a = Some(row);
+            break;
+        }
+        abx = field.mul(&abx, &b);
+    }
+    let relation_a = relation_a.ok_or(Error::LogDoesNotExist)?;
+
+    // Then, collect relations b**x = u/v for random x until there are a few more than unknowns, and solve them
+    let mut seen = vec![false; lf];
+    for &(i, _) in &relation_a.entries {
+        seen[i] = true;
+    }
+    let mut unknowns = relation_a.entries.len();
+    let mut rows = Vec::new();
+    let mut margin = RELATION_MARGIN;
+    let mut rand_state = RandState::new();
+    let step = Integer::from(order - 1u32).random_below(&mut rand_state) + 1u32;
+    let b_step = field.pow(&b, &step);
+    let mut x = step.clone();
+    let mut bx = b_step.clone();
+    let mut failures = 0;
+    for _ in 0..SOLVE_ATTEMPTS {
+        while rows.len() < unknowns + margin {
+            if failures == max_tries {
+                return Err(Error::LogDoesNotExist);
+            }
+            let row = relation(&bx, &x);
+            bx = field.mul(&bx, &b_step);
+            x = (x + &step) % order;
+            let Some(row) = row else {
+                failures += 1;
+                continue;
+            };
+            failures = 0;
+            for &(i, _) in &row.entries {
+                if !seen[i] {
+                    seen[i] = true;
+                    unknowns += 1;
+                }
+            }
+            rows.push(row);
+        }
+
+        // log(a) = sum(e_i * L_i) - x, which is right once the relation of a is spanned by the others
+        let logs = solve_sparse(rows.clone(), lf, order);
+        let log_a = relation_a
+            .entries
+            .iter()
+            .map(|(i, e)| Integer::from(e * &logs[*i]))
+            .sum::<Integer>()
+            - &relation_a.rhs;
+        let log_a = log_a.rem_euc(order);
+        if field.pow(&b, &log_a) == a {
+            return Ok(log_a);
+        }
+        margin += rows.len() / 4;
+    }
+
+    Err(Error::LogDoesNotExist)
+}
+
+/// Compute the discrete logarithm of `a` in base `b` in the binary field `field` (smallest non-negative integer `x`
+/// where `b**x = a` in `F_{2^m}`).
+///
+/// The order `2**m - 1` of `F_{2^m}*` is factored to compute the order of `b`, then the logarithm is computed with the
+/// Pohlig-Hellman algorithm. Its prime order sub-problems are solved with `discrete_log_binary_index_calculus` when
+/// they are too large for the generic algorithms, and with `discrete_log_group` otherwise.
+pub fn discrete_log_binary(
+    field: &BinaryField,
+    a: &Integer,
+    b: &Integer,
+) -> Result<Integer, Error> {
+    let a = field.reduce(a);
+    let b = field.reduce(b);
+    if a == 0 || b == 0 {
+        return Err(Error::LogDoesNotExist);
+    }
+    let group_factors = field.multiplicative_order_factors();
+    let order = element_order(field, &b, &group_factors);
+    let mut order_factors = Factorization::new();
+    for (q, _, primality) in group_factors.iter_with_primality() {
+        let e = order.clone().remove_factor(q).1 as usize;
+        order_factors.insert(q.clone(), e, primality);
+    }
+
+    let x = pohlig_hellman_with_factors(field, &a, &b, &order, &order_factors, |aj, bj, pi| {
+        // Index calculus needs the subgroup of order `pi` to be the whole `pi`-part of the group
+        if *pi >= shanks_steps::MAX_ORDER && group_factors.exponent(pi) == 1 {
+            discrete_log_binary_index_calculus(field, aj, bj, pi)
+                .or_else(|_| discrete_log_group(field, aj, bj, pi))
+        } else {
+            discrete_log_group(field, aj, bj, pi)
+        }
+    })?;
+    if field.pow(&b, &x) == a {
+        Ok(x)
+    } else {
+        Err(Error::LogDoesNotExist)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn binary_field() {
+        // AES field: x^8 + x^4 + x^3 + x + 1
+        let field = BinaryField::with_modulus(&[8, 4, 3, 1, 0]).unwrap();
+        assert_eq!(field.mul(&0x53.into(), &0xca.into()), 1);
+        assert_eq!(field.invert(&0x53.into()).unwrap(), 0xca);
+        assert_eq!(
+            field.square(&0x80.into()),
+            field.mul(&0x80.into(), &0x80.into())
+        );
+        assert_eq!(field.add(&0x57.into(), &0x83.into()), 0xd4);
+        assert_eq!(field.mul(&0x57.into(), &0x83.into()), 0xc1);
+        assert_eq!(
+            BinaryField::with_modulus(&[8, 4, 0]),
+            Err(Error::ReducibleModulus)
+        );
+
+        // x^163 + x^7 + x^6 + x^3 + 1 (NIST B-163)
+        assert!(BinaryField::with_modulus(&[163, 7, 6, 3, 0]).is_ok());
+        // There is no irreducible trinomial of degree 8
+        assert_eq!(BinaryField::new(8).modulus().len(), 5);
+        assert_eq!(BinaryField::new(127).modulus(), vec![127, 1, 0]);
+
+        let field = BinaryField::new(89);
+        let mut rand_state = RandState::new();
+        for _ in 0..10 {
+            let a = field.random(&mut rand_state);
+            let b = field.random(&mut rand_state);
+            let ab = field.mul(&a, &b);
+            assert_eq!(field.mul(&ab, &field.inverse(&b)), a);
+            assert!(field.pow(&a, &(field.size() - 1u32)) == 1 || a == 0);
+        }
+    }
+
+    #[test]
+    fn sparse_system() {
+        // L0 + 2 L1 = 3, L1 = 5, 3 L0 + L2 = 1 and L0 + 3 L1 + L2 = 2 modulo 7, L3 being undetermined
+        let row = |entries: &[(usize, u32)], rhs: u32| SparseRow {
+            entries: entries.iter().map(|&(i, e)| (i, e.into())).collect(),
+            rhs: rhs.into(),
+        };
+        let rows = vec![
+            row(&[(0, 1), (1, 2)], 3),
+            row(&[(1, 1)], 5),
+            row(&[(0, 3), (2, 1)], 1),
+            row(&[(0, 1), (1, 3), (2, 1)], 2),
+        ];
+        assert_eq!(solve_sparse(rows, 4, &7.into()), [0, 5, 1, 0]);
+    }
+
+    #[test]
+    fn binary_index_calculus() {
+        // 2^41 - 1 = 13367 * 164511353
+        let field = BinaryField::new(41);
+        let order = Integer::from(164511353);
+        let b = field.pow(&2.into(), &13367.into());
+        assert_eq!(field.pow(&b, &order), 1);
+        let a = field.pow(&b, &123456789.into());
+        assert_eq!(
+            discrete_log_binary_index_calculus(&field, &a, &b, &order).unwrap(),
+            123456789
+        );
+    }
+
+    #[test]
+    fn discrete_log_binary_() {
+        let field = BinaryField::new(60);
+        let mut rand_state = RandState::new();
+        let b = field.random(&mut rand_state);
+        let a = field.pow(&b, &987654321987654321u64.into());
+        let x = discrete_log_binary(&field, &a, &b).unwrap();
+        assert_eq!(field.pow(&b, &x), a);
+
+        assert_eq!(
+            discrete_log_binary(&field, &0.into(), &b),
+            Err(Error::LogDoesNotExist)
+        );
+    }
+}
@@ -0,0 +1,573 @@
+use std::collections::HashMap;
+
+use rug::{
+    ops::{Pow, RemRounding},
+    rand::RandState,
+    Integer,
+};
+
+use crate::{
+    class_polynomial::{hilbert_class_polynomial, reduced_forms},
+    factorize::{
+        ecm, is_prime, perfect_power, pollard_pm1, pollard_rho_brent, trial_division, ECM_BOUND,
+        ECM_CURVES, PM1_BOUND, RHO_ITERATIONS, TRIAL_DIVISION_BOUND,
+    },
+    poly::Poly,
+    utils::sqrt_mod,
+    Curve, Error, Point,
+};
+
+/// Bases of the deterministic Miller-Rabin test of `PrimalityCertificate::Small` (the first 12 primes, which have no
+/// strong pseudoprime below `3.3 * 10**24`).
+const MILLER_RABIN_BASES: [u32; 12] = [2, 3, 5, 7, 11, 13, 17, 19, 23, 29, 31, 37];
+/// Number of bits of the largest primes certified with `PrimalityCertificate::Small`.
+const SMALL_BITS: u32 = 64;
+/// Number of bases tried for each prime factor of `n - 1` to find a Pocklington witness.
+const POCKLINGTON_WITNESSES: u32 = 100;
+/// Number of random curves tried for each candidate order of the ECPP step.
+const ECPP_CURVES: usize = 32;
+/// Largest `-D` of the discriminants `D` used by the ECPP step.
+const ECPP_MAX_DISCRIMINANT: u32 = 3000;
+/// Largest class number (degree of the Hilbert class polynomial) of the discriminants used by the ECPP step.
+const ECPP_MAX_CLASS_NUMBER: usize = 16;
+/// Number of candidate orders collected by the ECPP step before trying them, smallest prime factor `q` first.
+const ECPP_CANDIDATES: usize = 8;
+
+/// Certificate of the primality of an integer `n`, checked by `verify` without any probabilistic test.
+///
+/// Certificates are produced by `prove_prime` and are recursive: the primality of `n` is reduced to the primality of
+/// smaller primes, themselves certified, down to `Small` ones.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PrimalityCertificate {
+    /// `n < 2**64`, checked with a deterministic Miller-Rabin test.
+    Small(Integer),
+    /// Pocklington's `n - 1` certificate (Lucas' test when `n - 1` is fully factored).
+    ///
+    /// Each certified prime `q` dividing `n - 1` comes with a witness `a` such that `a**(n-1) = 1 (mod n)` and
+    /// `gcd(a**((n-1)/q) - 1, n) = 1`. The part `F` of `n - 1` made of these primes must satisfy `F**2 > n`.
+    Pocklington {
+        /// Certified prime.
+        n: Integer,
+        /// Certificates of prime factors of `n - 1`, with their witnesses.
+        factors: Vec<(PrimalityCertificate, Integer)>,
+    },
+    /// Elliptic curve certificate (Goldwasser-Kilian, with the curves of Atkin-Morain's CM method).
+    ///
+    /// `point` is a point of the curve `y**2 = x**3 + a*x + b` over `Z/nZ` such that `(order/q) * point` is finite and
+    /// `order * point` is the point at infinity, where `q` is a certified prime dividing `order` with
+    /// `q > (n**(1/4) + 1)**2`.
+    Ecpp {
+        /// Certified prime.
+        n: Integer,
+        /// Coefficient `a` of the curve.
+        a: Integer,
+        /// Coefficient `b` of the curve.
+        b: Integer,
+        /// Point of the curve.
+        point: Point,
+        /// Multiple of the order of `point`.
+        order: Integer,
+        /// Certificate of the prime factor `q` of `order`.
+        factor: Box<PrimalityCertificate>,
+    },
+}
+
+impl PrimalityCertificate {
+    /// Returns the certified prime.
+    pub fn n(&self) -> &Integer {
+        match self {
+            Self::Small(n) | Self::Pocklington { n, .. } | Self::Ecpp { n, .. } => n,
+        }
+    }
+
+    /// Returns `true` if the certificate (and recursively the certificates it contains) is valid, which proves that
+    /// `n()` is prime.
+    pub fn verify(&self) -> bool {
+        match self {
+            Self::Small(n) => verify_small(n),
+            Self::Pocklington { n, factors } => verify_pocklington(n, factors),
+            Self::Ecpp {
+                n,
+                a,
+                b,
+                point,
+                order,
+                factor,
+            } => verify_ecpp(n, a, b, point, order, factor),
+        }
+    }
+}
+
+fn verify_small(n: &Integer) -> bool {
+    if *n < 2 || n.significant_bits() > SMALL_BITS {
+        return false;
+    }
+    if MILLER_RABIN_BASES.iter().any(|base| *n == *base) {
+        return true;
+    }
+    if n.is_even() {
+        return false;
+    }
+    let n1 = Integer::from(n - 1);
+    let s = n1.find_one(0).unwrap();
+    let d = Integer::from(&n1 >> s);
+    MILLER_RABIN_BASES.iter().all(|base| {
+        let mut x = Integer::from(*base).pow_mod(&d, n).unwrap();
+        if x == 1 || x == n1 {
+            return true;
+        }
+        for _ in 1..s {
+            x = x.square() % n;
+            if x == n1 {
+                return true;
+            }
+        }
+        false
+    })
+}
+
+fn verify_pocklington(n: &Integer, factors: &[(PrimalityCertificate, Integer)]) -> bool {
+    if *n < 3 {
+        return false;
+    }
+    let n1 = Integer::from(n - 1);
+    let mut f = Integer::from(1);
+    for (i, (certificate, witness)) in factors.iter().enumerate() {
+        let q = certificate.n();
+        if factors[..i].iter().any(|(other, _)| other.n() == q)
+            || !n1.is_divisible(q)
+            || !certificate.verify()
+        {
+            return false;
+        }
+        let mut rest = n1.clone();
+        let e = rest.remove_factor_mut(q);
+        f *= q.clone().pow(e);
+
+        if Integer::from(witness.pow_mod_ref(&n1, n).unwrap()) != 1 {
+            return false;
+        }
+        let w = Integer::from(witness.pow_mod_ref(&Integer::from(&n1 / q), n).unwrap());
+        if (w - 1u32).gcd(n) != 1 {
+            return false;
+        }
+    }
+    Integer::from(f.square_ref()) > *n
+}
+
+fn verify_ecpp(
+    n: &Integer,
+    a: &Integer,
+    b: &Integer,
+    point: &Point,
+    order: &Integer,
+    factor: &PrimalityCertificate,
+) -> bool {
+    if *n < 5 || Integer::from(n.gcd_ref(&6.into())) != 1 {
+        return false;
+    }
+    let discriminant = Integer::from(4) * Integer::from(a.pow_mod_ref(&3.into(), n).unwrap())
+        + Integer::from(27) * Integer::from(b.square_ref());
+    if discriminant.gcd(n) != 1 {
+        return false;
+    }
+    let Point::Affine(x, y) = point else {
+        return false;
+    };
+    let rhs = (Integer::from(x.square_ref()) + a) * x + b - Integer::from(y.square_ref());
+    if !rhs.is_divisible(n) {
+        return false;
+    }
+
+    let q = factor.n();
+    if *q <= ecpp_bound(n) || !order.is_divisible(q) || !factor.verify() {
+        return false;
+    }
+    let point = Point::Affine(x.clone().rem_euc(n), y.clone().rem_euc(n));
+    match ec_mul(n, a, &point, &Integer::from(order / q)) {
+        Some(Point::Infinity) | None => false,
+        Some(cofactor_point) => ec_mul(n, a, &cofactor_point, q) == Some(Point::Infinity),
+    }
+}
+
+/// Returns `(floor(n**(1/4)) + 2)**2`, an integer greater than `(n**(1/4) + 1)**2`.
+fn ecpp_bound(n: &Integer) -> Integer {
+    (n.clone().root(4) + 2u32).square()
+}
+
+/// Adds two points of the curve `y**2 = x**3 + a*x + b` over `Z/nZ`, `None` if a denominator is not invertible modulo
+/// `n` (which proves that `n` is composite).
+fn ec_add(n: &Integer, a: &Integer, p1: &Point, p2: &Point) -> Option<Point> {
+    let (Point::Affine(x1, y1), Point::Affine(x2, y2)) = (p1, p2) else {
+        return Some(if *p1 == Point::Infinity { p2 } else { p1 }.clone());
+    };
+    let lambda = if x1 == x2 {
+        if Integer::from(y1 + y2).is_divisible(n) {
+            return Some(Point::Infinity);
+        }
+        if y1 != y2 {
+            return None;
+        }
+        let num = Integer::from(3) * Integer::from(x1.square_ref()) + a;
+        num * Integer::from(2 * y1).invert(n).ok()?
+    } else {
+        Integer::from(y2 - y1) * Integer::from(x2 - x1).invert(n).ok()?
+    } % n;
+    let x3 = (Integer::from(lambda.square_ref()) - x1 - x2).rem_euc(n);
+    let y3 = (lambda * Integer::from(x1 - &x3) - y1).rem_euc(n);
+    Some(Point::Affine(x3, y3))
+}
+
+/// Computes `k * point` on the curve `y**2 = x**3 + a*x + b` over `Z/nZ` (`k >= 0`), `None` if a denominator is not
+/// invertible modulo `n`.
+fn ec_mul(n: &Integer, a: &Integer, point: &Point, k: &Integer) -> Option<Point> {
+    let mut result = Point::Infinity;
+    for i in (0..k.significant_bits()).rev() {
+        result = ec_add(n, a, &result, &result)?;
+        if k.get_bit(i) {
+            result = ec_add(n, a, &result, point)?;
+        }
+    }
+    Some(result)
+}
+
+/// Proves the primality of `n` and returns its certificate.
+///
+/// Primes below `2**64` get a `Small` certificate. Larger ones are certified with Pocklington's theorem when enough of
+/// `n - 1` is factored by trial division, Pollard's rho and p - 1 algorithms and ECM, otherwise with an elliptic curve
+/// of complex multiplication (Atkin-Morain). The prime factors used by these certificates are proven recursively.
+///
+/// The ECPP step looks for orders `n + 1 - t` with a large probable prime factor `q` among the curves of complex
+/// multiplication by the discriminants `D` with `-D <= 3000` and a class number at most 16, whose j-invariants are the
+/// roots of the Hilbert class polynomials. It descends to the candidate with the smallest `q`, falling back to the
+/// next ones if `q` cannot be proven, which certifies primes of 512 bits in a few seconds.
+///
+/// Returns `Error::PrimalityNotProven` if `n` is not prime or if no certificate was found.
+pub fn prove_prime(n: &Integer) -> Result<PrimalityCertificate, Error> {
+    Prover::new().prove(n).ok_or(Error::PrimalityNotProven)
+}
+
+/// State shared by the recursive proofs of `prove_prime`.
+struct Prover {
+    rand_state: RandState<'static>,
+    /// Discriminants used by the ECPP step (as `-D`), computed on first use.
+    discriminants: Option<Vec<u32>>,
+    /// Hilbert class polynomials of the discriminants already used.
+    class_polynomials: HashMap<u32, Vec<Integer>>,
+}
+
+impl Prover {
+    fn new() -> Self {
+        Self {
+            rand_state: RandState::new(),
+            discriminants: None,
+            class_polynomials: HashMap::new(),
+        }
+    }
+
+    fn prove(&mut self, n: &Integer) -> Option<PrimalityCertificate> {
+        if !is_prime(n) {
+            return None;
+        }
+        if n.significant_bits() <= SMALL_BITS {
+            return Some(PrimalityCertificate::Small(n.clone()));
+        }
+        self.pocklington(n).or_else(|| self.ecpp(n))
+    }
+
+    fn pocklington(&mut self, n: &Integer) -> Option<PrimalityCertificate> {
+        let n1 = Integer::from(n - 1);
+        let mut f = Integer::from(1);
+        let mut chosen = Vec::new();
+        for q in partial_factor(&n1) {
+            let mut rest = n1.clone();
+            let e = rest.remove_factor_mut(&q);
+            f *= q.clone().pow(e);
+            chosen.push(q);
+            if Integer::from(f.square_ref()) > *n {
+                break;
+            }
+        }
+        if Integer::from(f.square_ref()) <= *n {
+            return None;
+        }
+
+        let mut factors = Vec::new();
+        for q in chosen {
+            let exponent = Integer::from(&n1 / &q);
+            let witness = (2..2 + POCKLINGTON_WITNESSES)
+                .map(Integer::from)
+                .find(|a| {
+                    let w = Integer::from(a.pow_mod_ref(&exponent, n).unwrap());
+                    (w - 1u32).gcd(n) == 1
+                })?;
+            factors.push((self.prove(&q)?, witness));
+        }
+        Some(PrimalityCertificate::Pocklington {
+            n: n.clone(),
+            factors,
+        })
+    }
+
+    fn ecpp(&mut self, n: &Integer) -> Option<PrimalityCertificate> {
+        let bound = ecpp_bound(n);
+        let discriminants = self
+            .discriminants
+            .get_or_insert_with(ecpp_discriminants)
+            .clone();
+        let mut candidates = Vec::new();
+        for d in discriminants {
+            if candidates.len() >= ECPP_CANDIDATES {
+                break;
+            }
+            if Integer::from(-i64::from(d)).rem_euc(n).legendre(n) != 1 {
+                continue;
+            }
+            let Some((u, v)) = cornacchia(n, d) else {
+                continue;
+            };
+            for trace in cm_traces(d, &u, &v) {
+                let order = Integer::from(n + 1) - trace;
+                let (_, q) = trial_division(&order, TRIAL_DIVISION_BOUND);
+                if q > bound && q < *n && is_prime(&q) {
+                    candidates.push((q, order, d));
+                }
+            }
+        }
+        candidates.sort();
+
+        for (q, order, d) in candidates {
+            let Some(j) = self.j_invariant(n, d) else {
+                continue;
+            };
+            let Some((a, b, point)) = cm_curve(n, d, &j, &order, &q, &mut self.rand_state) else {
+                continue;
+            };
+            let Some(factor) = self.prove(&q) else {
+                continue;
+            };
+            return Some(PrimalityCertificate::Ecpp {
+                n: n.clone(),
+                a,
+                b,
+                point,
+                order,
+                factor: Box::new(factor),
+            });
+        }
+        None
+    }
+
+    /// Returns a root modulo `n` of the Hilbert class polynomial of discriminant `-d`, the j-invariant of a curve with
+    /// complex multiplication by the quadratic order of discriminant `-d`.
+    fn j_invariant(&mut self, n: &Integer, d: u32) -> Option<Integer> {
+        let coeffs = self
+            .class_polynomials
+            .entry(d)
+            .or_insert_with(|| hilbert_class_polynomial(d.into()));
+        Poly::from_coeffs(coeffs.clone(), n).root(n, &mut self.rand_state)
+    }
+}
+
+/// Returns the discriminants `D` (as `-D`) with `-D <= ECPP_MAX_DISCRIMINANT` and a class number at most
+/// `ECPP_MAX_CLASS_NUMBER`, by increasing class number.
+fn ecpp_discriminants() -> Vec<u32> {
+    let mut discriminants = (3..=ECPP_MAX_DISCRIMINANT)
+        .filter(|d| matches!(d % 4, 0 | 3))
+        .map(|d| (reduced_forms(d.into()).len(), d))
+        .filter(|&(h, _)| h <= ECPP_MAX_CLASS_NUMBER)
+        .collect::<Vec<_>>();
+    discriminants.sort();
+    discriminants.into_iter().map(|(_, d)| d).collect()
+}
+
+/// Returns the distinct prime factors of `n` found by trial division, Pollard's rho and p - 1 algorithms and ECM, in
+/// increasing order (the factors which resist these methods are left out).
+fn partial_factor(n: &Integer) -> Vec<Integer> {
+    let (small_factors, cofactor) = trial_division(n, TRIAL_DIVISION_BOUND);
+    let mut primes = small_factors
+        .into_iter()
+        .map(|(q, _)| q)
+        .collect::<Vec<_>>();
+    let mut composites = vec![cofactor];
+    while let Some(c) = composites.pop() {
+        if c == 1 {
+            continue;
+        }
+        if is_prime(&c) {
+            primes.push(c);
+        } else if let Some((root, _)) = perfect_power(&c) {
+            composites.push(root);
+        } else if let Some(d) = pollard_rho_brent(&c, RHO_ITERATIONS)
+            .or_else(|| pollard_pm1(&c, PM1_BOUND))
+            .or_else(|| ecm(&c, ECM_BOUND, ECM_CURVES))
+        {
+            composites.push(Integer::from(&c / &d));
+            composites.push(d);
+        }
+    }
+    primes.sort();
+    primes.dedup();
+    primes
+}
+
+/// Solves `u**2 + d*v**2 = 4*n` for the prime `n` (modified Cornacchia algorithm), `d` being `-D` for a discriminant
+/// `D`.
+fn cornacchia(n: &Integer, d: u32) -> Option<(Integer, Integer)> {
+    let mut x0 = sqrt_mod(&Integer::from(-i64::from(d)).rem_euc(n), n)?;
+    if x0.is_odd() != (d % 2 == 1) {
+        x0 = Integer::from(n - &x0);
+    }
+    let n4 = Integer::from(n << 2);
+    let bound = n4.clone().sqrt();
+    let (mut r0, mut r1) = (Integer::from(n << 1), x0);
+    while r1 > bound {
+        let r2 = Integer::from(&r0 % &r1);
+        r0 = r1;
+        r1 = r2;
+    }
+    let c = n4 - Integer::from(r1.square_ref());
+    if !c.is_divisible_u(d) {
+        return None;
+    }
+    let c = c / d;
+    c.is_perfect_square().then(|| (r1, c.sqrt()))
+}
+
+/// Returns the possible traces of Frobenius of the curves over `F_n` with complex multiplication by discriminant `-d`,
+/// given `u**2 + d*v**2 = 4*n`.
+fn cm_traces(d: u32, u: &Integer, v: &Integer) -> Vec<Integer> {
+    let mut traces = vec![u.clone()];
+    match d {
+        3 => {
+            traces.push((u + 3 * v.clone()) / 2u32);
+            traces.push((u - 3 * v.clone()) / 2u32);
+        }
+        4 => traces.push(Integer::from(2 * v)),
+        _ => {}
+    }
+    let negated = traces.iter().map(|t| Integer::from(-t)).collect::<Vec<_>>();
+    traces.extend(negated);
+    traces
+}
+
+/// Looks for a curve with j-invariant `j` (a random twist of it) over `F_n` and a point `P` such that
+/// `(order/q) * P` is finite and `order * P` is the point at infinity.
+fn cm_curve(
+    n: &Integer,
+    d: u32,
+    j: &Integer,
+    order: &Integer,
+    q: &Integer,
+    rand_state: &mut RandState<'_>,
+) -> Option<(Integer, Integer, Point)> {
+    // y^2 = x^3 + 3*k*x + 2*k has j-invariant 1728 * k / (k + 1) = j
+    let k = match d {
+        3 | 4 => Integer::new(),
+        _ => Integer::from(1728 - j).invert(n).ok()? * j % n,
+    };
+    if k == 0 && d != 3 && d != 4 {
+        return None;
+    }
+    let cofactor = Integer::from(order / q);
+    for _ in 0..ECPP_CURVES {
+        let c = Integer::from(n - 1).random_below(rand_state) + 1;
+        let (a, b) = match d {
+            3 => (Integer::new(), c),
+            4 => (c, Integer::new()),
+            _ => {
+                let c2 = Integer::from(c.square_ref());
+                (3 * Integer::from(&k * &c2), 2 * (&k * c2 * c))
+            }
+        };
+        let curve = Curve::new(n.clone(), a, b);
+        let point = curve.random_point(rand_state);
+        let cofactor_point = ec_mul(n, curve.a(), &point, &cofactor)?;
+        if cofactor_point != Point::Infinity
+            && ec_mul(n, curve.a(), &cofactor_point, q)? == Point::Infinity
+        {
+            return Some((curve.a().clone(), curve.b().clone(), point));
+        }
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use super::*;
+
+    #[test]
+    fn prove_prime_() {
+        let small = prove_prime(&1000003.into()).unwrap();
+        assert_eq!(small, PrimalityCertificate::Small(1000003.into()));
+        assert!(small.verify());
+        assert!(!PrimalityCertificate::Small(1000001.into()).verify());
+        assert_eq!(prove_prime(&1000001.into()), Err(Error::PrimalityNotProven));
+
+        // 2^127 - 1 and a 160-bit prime
+        for n in [
+            (Integer::from(1) << 127) - 1,
+            Integer::from_str("1461501637330902918203684832716283019655932542983").unwrap(),
+        ] {
+            let certificate = prove_prime(&n).unwrap();
+            assert_eq!(*certificate.n(), n);
+            assert!(certificate.verify());
+        }
+    }
+
+    #[test]
+    fn prove_prime_512_bits() {
+        let mut rand_state = RandState::new();
+        for _ in 0..2 {
+            let n = (Integer::from(Integer::random_bits(511, &mut rand_state))
+                + (Integer::from(1) << 511u32))
+                .next_prime();
+            let certificate = prove_prime(&n).unwrap();
+            assert_eq!(*certificate.n(), n);
+            assert!(certificate.verify());
+        }
+    }
+
+    #[test]
+    fn pocklington() {
+        let n = (Integer::from(1) << 127) - 1;
+        let Some(PrimalityCertificate::Pocklington { n, mut factors }) =
+            Prover::new().pocklington(&n)
+        else {
+            panic!("no Pocklington certificate");
+        };
+        assert!(verify_pocklington(&n, &factors));
+        factors[0].1 = Integer::from(1);
+        assert!(!verify_pocklington(&n, &factors));
+        assert!(!verify_pocklington(&Integer::from(&n + 2), &factors));
+    }
+
+    #[test]
+    fn ecpp() {
+        let n = Integer::from_str("340282366920938463463374607431768211297").unwrap();
+        let certificate = Prover::new().ecpp(&n).unwrap();
+        assert!(certificate.verify());
+        let PrimalityCertificate::Ecpp {
+            n,
+            a,
+            b,
+            point,
+            order,
+            factor,
+        } = certificate
+        else {
+            panic!("not an ECPP certificate");
+        };
+        let tampered = PrimalityCertificate::Ecpp {
+            n,
+            a,
+            b,
+            point,
+            order: order + 1,
+            factor,
+        };
+        assert!(!tampered.verify());
+    }
+}
@@ -0,0 +1,268 @@
+use rug::Integer;
+
+/// Returns the reduced primitive binary quadratic forms `(a, b, c)` of discriminant `b**2 - 4*a*c = -d` (`d > 0`,
+/// `-d` being 0 or 1 modulo 4).
+///
+/// A form is reduced when `|b| <= a <= c`, with `b >= 0` if `|b| = a` or `a = c`. There is one reduced form in each
+/// class, so that their number is the class number `h(-d)`.
+pub(crate) fn reduced_forms(d: u64) -> Vec<(u64, i64, u64)> {
+    let mut forms = Vec::new();
+    let mut a = 1u64;
+    while 3 * a * a <= d {
+        for b in 1 - a as i64..=a as i64 {
+            let b2d = b.unsigned_abs().pow(2) + d;
+            if !b2d.is_multiple_of(4 * a) {
+                continue;
+            }
+            let c = b2d / (4 * a);
+            if c < a || (c == a && b < 0) {
+                continue;
+            }
+            if gcd(gcd(a, b.unsigned_abs()), c) == 1 {
+                forms.push((a, b, c));
+            }
+        }
+        a += 1;
+    }
+    forms
+}
+
+fn gcd(a: u64, b: u64) -> u64 {
+    if b == 0 {
+        a
+    } else {
+        gcd(b, a % b)
+    }
+}
+
+/// Returns the Hilbert class polynomial `H_D` of the discriminant `D = -d`, lowest degree first.
+///
+/// `H_D` is the monic polynomial whose roots are the j-invariants `j((-b + sqrt(D)) / (2a))` of the reduced forms
+/// `(a, b, c)` of discriminant `D`. These are evaluated with enough precision for the integer coefficients of `H_D` to
+/// be recovered by rounding, `j` being computed from the Dedekind eta function.
+pub(crate) fn hilbert_class_polynomial(d: u64) -> Vec<Integer> {
+    let forms = reduced_forms(d);
+
+    // |j(tau)| is about exp(pi * sqrt(d) / a), so that the coefficients have less than `bits` bits
+    let log2_j =
+        |a: u64| (std::f64::consts::PI * (d as f64).sqrt() / a as f64) / std::f64::consts::LN_2;
+    let bits = forms.iter().map(|&(a, _, _)| log2_j(a) + 1.0).sum::<f64>() + forms.len() as f64;
+    let fixed = Fixed {
+        prec: 2 * (bits as u32 + log2_j(1) as u32) + 128,
+    };
+
+    let pi = fixed.pi();
+    let sqrt_d = (Integer::from(d) << (2 * fixed.prec)).sqrt();
+    let pi_sqrt_d = Integer::from(&pi * &sqrt_d) >> fixed.prec;
+
+    let mut poly = vec![Complex::from(fixed.one())];
+    for (a, b, _) in forms {
+        // q = exp(2 i pi tau) = exp(-pi (sqrt(d) + i b) / a)
+        let q = fixed.exp(&Complex {
+            re: -Integer::from(&pi_sqrt_d / a),
+            im: -Integer::from(&pi * b) / a,
+        });
+        let j = fixed.j_invariant(&q);
+
+        // poly *= (X - j)
+        let mut product = vec![Complex::default(); poly.len() + 1];
+        for (i, c) in poly.iter().enumerate() {
+            product[i + 1] = product[i + 1].add(c);
+            product[i] = product[i].sub(&fixed.mul(c, &j));
+        }
+        poly = product;
+    }
+
+    let half = Integer::from(1) << (fixed.prec - 1);
+    poly.into_iter()
+        .map(|c| (c.re + &half) >> fixed.prec)
+        .collect()
+}
+
+/// Complex number in fixed-point representation (see `Fixed`).
+#[derive(Debug, Clone, Default)]
+struct Complex {
+    re: Integer,
+    im: Integer,
+}
+
+impl Complex {
+    fn from(re: Integer) -> Self {
+        Self {
+            re,
+            im: Integer::new(),
+        }
+    }
+
+    fn add(&self, other: &Self) -> Self {
+        Self {
+            re: Integer::from(&self.re + &other.re),
+            im: Integer::from(&self.im + &other.im),
+        }
+    }
+
+    fn sub(&self, other: &Self) -> Self {
+        Self {
+            re: Integer::from(&self.re - &other.re),
+            im: Integer::from(&self.im - &other.im),
+        }
+    }
+
+    /// Whether this is below the precision (the shifts rounding down, small negative values end up at -1).
+    fn is_negligible(&self) -> bool {
+        self.re.significant_bits() <= 1 && self.im.significant_bits() <= 1
+    }
+}
+
+/// Fixed-point arithmetic, a real number `x` being represented by the integer `x * 2**prec`.
+struct Fixed {
+    prec: u32,
+}
+
+impl Fixed {
+    fn one(&self) -> Integer {
+        Integer::from(1) << self.prec
+    }
+
+    fn mul(&self, a: &Complex, b: &Complex) -> Complex {
+        let re = Integer::from(&a.re * &b.re) - Integer::from(&a.im * &b.im);
+        let im = Integer::from(&a.re * &b.im) + Integer::from(&a.im * &b.re);
+        Complex {
+            re: re >> self.prec,
+            im: im >> self.prec,
+        }
+    }
+
+    fn div(&self, a: &Complex, b: &Complex) -> Complex {
+        let norm = Integer::from(b.re.square_ref()) + Integer::from(b.im.square_ref());
+        let re = Integer::from(&a.re * &b.re) + Integer::from(&a.im * &b.im);
+        let im = Integer::from(&a.im * &b.re) - Integer::from(&a.re * &b.im);
+        Complex {
+            re: (re << self.prec) / &norm,
+            im: (im << self.prec) / norm,
+        }
+    }
+
+    fn pow(&self, a: &Complex, e: u32) -> Complex {
+        let mut result = Complex::from(self.one());
+        for i in (0..u32::BITS - e.leading_zeros()).rev() {
+            result = self.mul(&result, &result);
+            if e & (1 << i) != 0 {
+                result = self.mul(&result, a);
+            }
+        }
+        result
+    }
+
+    /// Returns `arctan(1/k)`.
+    fn arctan_inv(&self, k: u32) -> Integer {
+        let k2 = k * k;
+        let mut power = self.one() / k;
+        let mut sum = power.clone();
+        let mut i = 1u32;
+        while power != 0 {
+            power /= k2;
+            let term = Integer::from(&power / (2 * i + 1));
+            if i % 2 == 1 {
+                sum -= term;
+            } else {
+                sum += term;
+            }
+            i += 1;
+        }
+        sum
+    }
+
+    /// Returns `pi` (Machin's formula).
+    fn pi(&self) -> Integer {
+        16 * self.arctan_inv(5) - 4 * self.arctan_inv(239)
+    }
+
+    /// Returns `exp(z)`, computed with the Taylor series of `exp(z / 2**s)` squared `s` times.
+    fn exp(&self, z: &Complex) -> Complex {
+        let magnitude =
+            Integer::from(z.re.abs_ref()).max(Integer::from(z.im.abs_ref())) >> self.prec;
+        let s = magnitude.significant_bits() + 2;
+        let w = Complex {
+            re: Integer::from(&z.re >> s),
+            im: Integer::from(&z.im >> s),
+        };
+
+        let mut sum = Complex::from(self.one());
+        let mut term = sum.clone();
+        let mut k = 1u32;
+        while !term.is_negligible() {
+            term = self.mul(&term, &w);
+            term.re /= k;
+            term.im /= k;
+            sum = sum.add(&term);
+            k += 1;
+        }
+        for _ in 0..s {
+            sum = self.mul(&sum, &sum);
+        }
+        sum
+    }
+
+    /// Returns `prod(1 - q**n)`, the Dedekind eta function without its factor `q**(1/24)`, with Euler's pentagonal
+    /// number theorem: `sum((-1)**n * q**(n * (3n - 1) / 2))` over the integers `n`.
+    fn eta(&self, q: &Complex) -> Complex {
+        let mut sum = Complex::from(self.one());
+        let mut n = 1u32;
+        loop {
+            let t1 = self.pow(q, n * (3 * n - 1) / 2);
+            let t2 = self.pow(q, n * (3 * n + 1) / 2);
+            if t1.is_negligible() {
+                return sum;
+            }
+            let terms = t1.add(&t2);
+            sum = if n % 2 == 1 {
+                sum.sub(&terms)
+            } else {
+                sum.add(&terms)
+            };
+            n += 1;
+        }
+    }
+
+    /// Returns `j(tau)` for `q = exp(2 i pi tau)`: `j = (256 x + 1)**3 / x`, where `x = Delta(2 tau) / Delta(tau)
+    /// = q * (eta(q**2) / eta(q))**24`.
+    fn j_invariant(&self, q: &Complex) -> Complex {
+        let q2 = self.mul(q, q);
+        let ratio = self.div(&self.eta(&q2), &self.eta(q));
+        let x = self.mul(q, &self.pow(&ratio, 24));
+        let mut y = x.clone();
+        y.re *= 256;
+        y.im *= 256;
+        y.re += self.one();
+        self.div(&self.pow(&y, 3), &x)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn hilbert_class_polynomial_() {
+        assert_eq!(reduced_forms(3), [(1, 1, 1)]);
+        assert_eq!(reduced_forms(20), [(1, 0, 5), (2, 2, 3)]);
+        assert_eq!(reduced_forms(23).len(), 3);
+
+        assert_eq!(hilbert_class_polynomial(4), [-1728, 1]);
+        assert_eq!(
+            hilbert_class_polynomial(163),
+            [Integer::from(262537412640768000u64), 1.into()]
+        );
+        assert_eq!(hilbert_class_polynomial(15), [-121287375, 191025, 1]);
+        assert_eq!(
+            hilbert_class_polynomial(23),
+            [
+                Integer::from(12771880859375u64),
+                Integer::from(-5151296875i64),
+                3491750.into(),
+                1.into()
+            ]
+        );
+    }
+}
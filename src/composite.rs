@@ -0,0 +1,138 @@
+use rug::{ops::Pow, Integer};
+
+use crate::{
+    discrete_log_solution_with_order, factorize::factor, n_order::n_order_with_factors,
+    prime_power::prime_power_log, DlogSolution, Error, Factorization,
+};
+
+/// Report of a discrete logarithm computed modulo each prime power factor of a composite modulus, returned by
+/// `discrete_log_composite`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CompositeDlog {
+    /// Solutions modulo `n`.
+    pub solution: DlogSolution,
+    /// Prime power factors `p**e` of `n` with the solutions modulo them, in increasing order of `p`.
+    pub factors: Vec<(Integer, DlogSolution)>,
+}
+
+/// Compute the discrete logarithm of `a` in base `b` modulo `n` (smallest non-negative integer `x` where `b**x = a (mod n)`)
+/// by splitting `n` into its prime power factors.
+///
+/// The problem is solved independently modulo each `p**e` dividing `n`, which gives `x` modulo the order of `b` modulo
+/// `p**e`, and these congruences are combined with the Chinese remainder theorem (their moduli are usually not
+/// coprime, as they all divide `lambda(n)`). `n_factors` must be the prime factorization of `n`
+/// (`Error::IncompleteFactorization` if a factor is composite), and `b` must be a unit modulo `n`.
+pub fn discrete_log_composite(
+    n: &Integer,
+    a: &Integer,
+    b: &Integer,
+    n_factors: &Factorization,
+) -> Result<CompositeDlog, Error> {
+    if *n < 1 {
+        return Err(Error::InvalidModulus);
+    }
+    if n_factors.value() != *n {
+        return Err(Error::InvalidFactorization);
+    }
+    if !n_factors.is_complete() {
+        return Err(Error::IncompleteFactorization);
+    }
+    if Integer::from(b.gcd_ref(n)) != 1 {
+        return Err(Error::NotRelativelyPrime);
+    }
+
+    let mut solution = DlogSolution::new(Integer::new(), Integer::from(1));
+    let mut factors = Vec::new();
+    for (p, e) in n_factors.iter() {
+        let pe = p.clone().pow(e as u32);
+        let local = prime_power_solution(p, e, &Integer::from(a % &pe), &Integer::from(b % &pe))?;
+        solution = solution.intersect(&local).ok_or(Error::LogDoesNotExist)?;
+        factors.push((pe, local));
+    }
+    Ok(CompositeDlog { solution, factors })
+}
+
+/// Compute all the discrete logarithms of `a` in base `b` modulo the prime power `p**e` dividing the modulus, with the
+/// known prime `p`: only `p - 1` is factored, to compute the order of `b`.
+fn prime_power_solution(
+    p: &Integer,
+    e: usize,
+    a: &Integer,
+    b: &Integer,
+) -> Result<DlogSolution, Error> {
+    if p.is_odd() {
+        return prime_power_log(p, e as u32, a, b, &factor(&(p.clone() - 1)));
+    }
+    // The order of b modulo 2**e divides 2**(e-1)
+    let pe = Integer::from(1) << e as u32;
+    let order = n_order_with_factors(b, &pe, &Factorization::from_iter([(p.clone(), e)]))?;
+    discrete_log_solution_with_order(&pe, a, b, &order)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use super::*;
+    use crate::{discrete_log_pohlig_hellman, factorize::factor, Primality};
+
+    #[test]
+    fn composite() {
+        // 2^x = 8 (mod 15): x = 1 (mod 2) modulo 3 and x = 3 (mod 4) modulo 5
+        let report =
+            discrete_log_composite(&15.into(), &8.into(), &2.into(), &factor(&15.into())).unwrap();
+        assert_eq!(report.solution, DlogSolution::new(3.into(), 4.into()));
+        assert_eq!(
+            report.factors,
+            [
+                (3.into(), DlogSolution::new(1.into(), 2.into())),
+                (5.into(), DlogSolution::new(3.into(), 4.into()))
+            ]
+        );
+        // 2^x = 4 (mod 15): x = 0 (mod 2) modulo 3 and x = 2 (mod 4) modulo 5
+        assert_eq!(
+            discrete_log_composite(&15.into(), &4.into(), &2.into(), &factor(&15.into()))
+                .unwrap()
+                .solution
+                .x0,
+            2
+        );
+        // 2^x = 7 (mod 15): x = 0 (mod 2) modulo 3 but x = 1 (mod 4) modulo 5
+        assert_eq!(
+            discrete_log_composite(&15.into(), &7.into(), &2.into(), &factor(&15.into())),
+            Err(Error::LogDoesNotExist)
+        );
+        assert_eq!(
+            discrete_log_composite(&15.into(), &7.into(), &2.into(), &factor(&45.into())),
+            Err(Error::InvalidFactorization)
+        );
+        let mut incomplete = Factorization::new();
+        incomplete.insert(15.into(), 1, Primality::Composite);
+        assert_eq!(
+            discrete_log_composite(&15.into(), &8.into(), &2.into(), &incomplete),
+            Err(Error::IncompleteFactorization)
+        );
+
+        // 6048 = 2^5 * 3^3 * 7: smallest exponents and periods found by brute force
+        let n = Integer::from(6048);
+        let mut powers = vec![Integer::from(1)];
+        while powers.len() == 1 || *powers.last().unwrap() != 1 {
+            powers.push(Integer::from(powers.last().unwrap() * 5) % &n);
+        }
+        let period = Integer::from(powers.len() - 1);
+        for (x, power) in powers.iter().enumerate().take(powers.len() - 1) {
+            let report = discrete_log_composite(&n, power, &5.into(), &factor(&n)).unwrap();
+            assert_eq!(report.solution, DlogSolution::new(x.into(), period.clone()));
+        }
+
+        let n = Integer::from_str("1000000000000000003").unwrap() * 1000003 * 49;
+        let x = Integer::from_str("12345678901234567").unwrap();
+        let a = Integer::from(3).pow_mod(&x, &n).unwrap();
+        let report = discrete_log_composite(&n, &a, &3.into(), &factor(&n)).unwrap();
+        assert_eq!(report.factors.len(), 3);
+        assert_eq!(
+            report.solution.x0,
+            discrete_log_pohlig_hellman(&n, &a, &3.into(), None).unwrap()
+        );
+    }
+}
@@ -0,0 +1,134 @@
+//! Chinese remainder theorem: solution of systems of congruences `x = r_i (mod m_i)`, whose moduli need not be
+//! pairwise coprime.
+
+use rug::{ops::RemRounding, Integer};
+
+/// Error of the Chinese remainder theorem functions.
+#[derive(thiserror::Error, Debug, Clone, PartialEq, Eq)]
+pub enum CrtError {
+    /// The congruences have no common solution
+    #[error("Congruences are inconsistent")]
+    Inconsistent,
+    /// The numbers of residues and moduli differ
+    #[error("Residues and moduli have different lengths")]
+    LengthMismatch,
+    /// A modulus is not positive
+    #[error("Modulus is not positive")]
+    InvalidModulus,
+}
+
+/// Solves `x = r1 (mod m1)` and `x = r2 (mod m2)`, returning `(x, lcm(m1, m2))` with `0 <= x < lcm(m1, m2)`.
+///
+/// The moduli need not be coprime: the congruences are consistent if `r1 = r2 (mod gcd(m1, m2))`, and
+/// `CrtError::Inconsistent` is returned otherwise.
+pub fn crt_pair(
+    r1: &Integer,
+    m1: &Integer,
+    r2: &Integer,
+    m2: &Integer,
+) -> Result<(Integer, Integer), CrtError> {
+    if *m1 < 1 || *m2 < 1 {
+        return Err(CrtError::InvalidModulus);
+    }
+    let (g, s, _) = m1.clone().extended_gcd(m2.clone(), Integer::new());
+    let diff = Integer::from(r2 - r1);
+    if !diff.is_divisible(&g) {
+        return Err(CrtError::Inconsistent);
+    }
+    let lcm = Integer::from(m1 / &g) * m2;
+    // x = r1 + m1 * s * (r2 - r1) / g, with s * m1 = g (mod m2)
+    let x = (r1 + m1 * s * (diff / g)).rem_euc(&lcm);
+    Ok((x, lcm))
+}
+
+/// Solves the system `x = residues[i] (mod moduli[i])`, returning `(x, m)` where `m` is the least common multiple of
+/// the moduli and `0 <= x < m` (the empty system gives `(0, 1)`).
+///
+/// The moduli need not be pairwise coprime (see `crt_pair`). Returns `CrtError::LengthMismatch` if the slices have
+/// different lengths, `CrtError::InvalidModulus` if a modulus is not positive and `CrtError::Inconsistent` if the
+/// system has no solution.
+pub fn crt(residues: &[Integer], moduli: &[Integer]) -> Result<(Integer, Integer), CrtError> {
+    if residues.len() != moduli.len() {
+        return Err(CrtError::LengthMismatch);
+    }
+    if moduli.iter().any(|m| *m < 1) {
+        return Err(CrtError::InvalidModulus);
+    }
+    residues
+        .iter()
+        .zip(moduli)
+        .try_fold((Integer::new(), Integer::from(1)), |(x, m), (r, mi)| {
+            crt_pair(&x, &m, r, mi)
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn chinese_remainder_theorem() {
+        assert_eq!(
+            crt(
+                &[3.into(), 5.into(), 7.into()],
+                &[2.into(), 3.into(), 1.into()]
+            ),
+            Ok((5.into(), 6.into()))
+        );
+        assert_eq!(
+            crt(
+                &[1.into(), 4.into(), 6.into()],
+                &[3.into(), 5.into(), 7.into()]
+            ),
+            Ok((34.into(), 105.into()))
+        );
+        assert_eq!(
+            crt(
+                &[1.into(), 4.into(), 6.into()],
+                &[1.into(), 2.into(), 0.into()]
+            ),
+            Err(CrtError::InvalidModulus)
+        );
+        assert_eq!(
+            crt(
+                &[2.into(), 5.into(), 7.into()],
+                &[6.into(), 9.into(), 15.into()]
+            ),
+            Err(CrtError::Inconsistent)
+        );
+    }
+
+    #[test]
+    fn crt_() {
+        let ints = |v: &[i64]| v.iter().map(|&x| Integer::from(x)).collect::<Vec<_>>();
+        assert_eq!(
+            crt(&ints(&[2, 3, 2]), &ints(&[3, 5, 7])),
+            Ok((23.into(), 105.into()))
+        );
+        assert_eq!(
+            crt(&ints(&[3, 7]), &ints(&[10, 12])),
+            Ok((43.into(), 60.into()))
+        );
+        assert_eq!(
+            crt(&ints(&[-1, 5]), &ints(&[4, 6])),
+            Ok((11.into(), 12.into()))
+        );
+        assert_eq!(crt(&[], &[]), Ok((0.into(), 1.into())));
+        assert_eq!(
+            crt(&ints(&[3, 6]), &ints(&[10, 12])),
+            Err(CrtError::Inconsistent)
+        );
+        assert_eq!(
+            crt(&ints(&[3]), &ints(&[10, 12])),
+            Err(CrtError::LengthMismatch)
+        );
+        assert_eq!(
+            crt(&ints(&[3, 1]), &ints(&[10, 0])),
+            Err(CrtError::InvalidModulus)
+        );
+        assert_eq!(
+            crt_pair(&5.into(), &8.into(), &1.into(), &12.into()),
+            Ok((13.into(), 24.into()))
+        );
+    }
+}
@@ -0,0 +1,573 @@
+use rug::{ops::Pow, ops::RemRounding, rand::RandState, Integer};
+
+use crate::{
+    discrete_log_pollard_rho_negation, discrete_log_shanks_steps_group,
+    discrete_log_trial_mul_group, ec_discrete_log_singular, ec_discrete_log_smart,
+    ec_embedding_degree, ec_group_order, ec_group_order_with_options, factorize::factor,
+    order_from_multiple, pairing::mov, pohlig_hellman::pohlig_hellman_with_factors, shanks_steps,
+    utils::sqrt_mod, Error, Group, MovReduction, NegationMap, SolverOptions, MAX_EMBEDDING_DEGREE,
+};
+
+/// A point of an elliptic curve, in affine coordinates.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum Point {
+    /// The point at infinity (identity of the group of points).
+    Infinity,
+    /// A finite point `(x, y)`.
+    Affine(Integer, Integer),
+}
+
+/// An elliptic curve in short Weierstrass form `y**2 = x**3 + a*x + b` over the prime field `F_p`.
+///
+/// The modulus `p` must be a prime greater than 3.
+/// The group of points of the curve implements `Group`, so every generic solver of this crate can be used on it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Curve {
+    p: Integer,
+    a: Integer,
+    b: Integer,
+}
+
+/// A point in Jacobian coordinates `(X, Y, Z)`, representing the affine point `(X/Z**2, Y/Z**3)`.
+struct Jacobian {
+    x: Integer,
+    y: Integer,
+    z: Integer,
+}
+
+impl Curve {
+    /// Creates the curve `y**2 = x**3 + a*x + b` over `F_p`.
+    pub fn new(p: Integer, a: Integer, b: Integer) -> Self {
+        let a = a.rem_euc(&p);
+        let b = b.rem_euc(&p);
+        Self { p, a, b }
+    }
+
+    /// Returns the characteristic `p` of the base field.
+    pub fn p(&self) -> &Integer {
+        &self.p
+    }
+
+    /// Returns the coefficient `a` of the curve.
+    pub fn a(&self) -> &Integer {
+        &self.a
+    }
+
+    /// Returns the coefficient `b` of the curve.
+    pub fn b(&self) -> &Integer {
+        &self.b
+    }
+
+    /// Returns the discriminant `-16 * (4*a**3 + 27*b**2)` of the curve, modulo `p`.
+    pub fn discriminant(&self) -> Integer {
+        let d = Integer::from(4) * Integer::from(self.a.square_ref()) * &self.a
+            + Integer::from(27) * Integer::from(self.b.square_ref());
+        (Integer::from(-16) * d).rem_euc(&self.p)
+    }
+
+    /// Returns `true` if the discriminant of the curve is zero (the cubic has a node or a cusp).
+    pub fn is_singular(&self) -> bool {
+        self.discriminant() == 0
+    }
+
+    /// Returns `true` if the curve is anomalous (it has exactly `p` points, i.e. its trace of Frobenius is one).
+    ///
+    /// Discrete logarithms on anomalous curves are easy to compute with `ec_discrete_log_smart`. Above 5, a single
+    /// point is checked: by Hasse's bound, a curve with a point of order `p` has exactly `p` points.
+    pub fn is_anomalous(&self) -> bool {
+        if self.is_singular() {
+            return false;
+        }
+        if self.p < 7 {
+            return matches!(ec_group_order(self), Ok(n) if n == self.p);
+        }
+        let point = (0..).find_map(|x| self.lift_x(&Integer::from(x))).unwrap();
+        self.mul(&point, &self.p) == Point::Infinity
+    }
+
+    /// Creates the point `(x, y)`, checking that it lies on the curve.
+    pub fn point(&self, x: Integer, y: Integer) -> Result<Point, Error> {
+        let point = Point::Affine(x.rem_euc(&self.p), y.rem_euc(&self.p));
+        if self.contains(&point) {
+            Ok(point)
+        } else {
+            Err(Error::PointNotOnCurve)
+        }
+    }
+
+    /// Returns the point with abscissa `x` and the smallest ordinate, `None` if there is no such point.
+    pub fn lift_x(&self, x: &Integer) -> Option<Point> {
+        let x = x.clone().rem_euc(&self.p);
+        let rhs = (Integer::from(x.square_ref()) + &self.a) * &x + &self.b;
+        let y = sqrt_mod(&rhs, &self.p)?;
+        let y = y.clone().min(Integer::from(&self.p - &y) % &self.p);
+        Some(Point::Affine(x, y))
+    }
+
+    /// Returns a random finite point of the curve.
+    pub fn random_point(&self, rand_state: &mut RandState<'_>) -> Point {
+        loop {
+            let x = self.p.clone().random_below(rand_state);
+            if let Some(point) = self.lift_x(&x) {
+                return if rand_state.bits(1) == 1 {
+                    self.neg(&point)
+                } else {
+                    point
+                };
+            }
+        }
+    }
+
+    /// Returns the quadratic twist `y**2 = x**3 + a*d**2*x + b*d**3` of the curve, `d` being the smallest quadratic
+    /// non-residue modulo `p`.
+    ///
+    /// The orders of a curve and of its twist add up to `2*p + 2`.
+    pub fn quadratic_twist(&self) -> Curve {
+        let mut d = Integer::from(2);
+        while d.legendre(&self.p) != -1 {
+            d += 1;
+        }
+        let d2 = Integer::from(d.square_ref());
+        let d3 = Integer::from(&d2 * &d);
+        Curve::new(
+            self.p.clone(),
+            Integer::from(&self.a * &d2) % &self.p,
+            Integer::from(&self.b * &d3) % &self.p,
+        )
+    }
+
+    /// Returns `true` if `point` lies on the curve.
+    pub fn contains(&self, point: &Point) -> bool {
+        match point {
+            Point::Infinity => true,
+            Point::Affine(x, y) => {
+                if *x < 0 || *x >= self.p || *y < 0 || *y >= self.p {
+                    return false;
+                }
+                let rhs = (x.clone().square() + &self.a) * x + &self.b;
+                (y.clone().square() - rhs).is_divisible(&self.p)
+            }
+        }
+    }
+
+    /// Returns the opposite of `point`.
+    pub fn neg(&self, point: &Point) -> Point {
+        match point {
+            Point::Infinity => Point::Infinity,
+            Point::Affine(x, y) => Point::Affine(x.clone(), (-y.clone()).rem_euc(&self.p)),
+        }
+    }
+
+    /// Returns the sum of `p1` and `p2`.
+    pub fn add(&self, p1: &Point, p2: &Point) -> Point {
+        let (x1, y1, x2, y2) = match (p1, p2) {
+            (Point::Infinity, _) => return p2.clone(),
+            (_, Point::Infinity) => return p1.clone(),
+            (Point::Affine(x1, y1), Point::Affine(x2, y2)) => (x1, y1, x2, y2),
+        };
+
+        let lambda = if x1 == x2 {
+            if Integer::from(y1 + y2).is_divisible(&self.p) {
+                return Point::Infinity;
+            }
+            let num = Integer::from(3) * x1.clone().square() + &self.a;
+            let den = Integer::from(2) * y1;
+            num * den.invert(&self.p).unwrap()
+        } else {
+            let num = Integer::from(y2 - y1);
+            let den = Integer::from(x2 - x1);
+            num * den.invert(&self.p).unwrap()
+        } % &self.p;
+
+        let x3 = (lambda.clone().square() - x1 - x2).rem_euc(&self.p);
+        let y3 = (lambda * Integer::from(x1 - &x3) - y1).rem_euc(&self.p);
+        Point::Affine(x3, y3)
+    }
+
+    /// Returns `2 * point`.
+    pub fn double(&self, point: &Point) -> Point {
+        self.add(point, point)
+    }
+
+    /// Returns the scalar multiplication `k * point`.
+    ///
+    /// The computation is done in Jacobian coordinates, with a single inversion at the end.
+    pub fn mul(&self, point: &Point, k: &Integer) -> Point {
+        let (x, y) = match point {
+            Point::Infinity => return Point::Infinity,
+            Point::Affine(x, y) => (x, y),
+        };
+        let y = if *k < 0 {
+            (-y.clone()).rem_euc(&self.p)
+        } else {
+            y.clone()
+        };
+        let k = k.clone().abs();
+
+        let mut result: Option<Jacobian> = None;
+        for i in (0..k.significant_bits()).rev() {
+            result = result.and_then(|r| self.jacobian_double(&r));
+            if k.get_bit(i) {
+                result = match result {
+                    None => Some(Jacobian {
+                        x: x.clone(),
+                        y: y.clone(),
+                        z: Integer::from(1),
+                    }),
+                    Some(r) => self.jacobian_add_affine(&r, x, &y),
+                };
+            }
+        }
+
+        match result {
+            None => Point::Infinity,
+            Some(r) => self.to_affine(&r),
+        }
+    }
+
+    /// Doubles a point in Jacobian coordinates, `None` being the point at infinity.
+    fn jacobian_double(&self, point: &Jacobian) -> Option<Jacobian> {
+        if point.y == 0 {
+            return None;
+        }
+        let p = &self.p;
+        let yy = Integer::from(point.y.square_ref()) % p;
+        let zz = Integer::from(point.z.square_ref()) % p;
+        let s = Integer::from(4) * &point.x * &yy % p;
+        let m =
+            (Integer::from(3) * Integer::from(point.x.square_ref()) + &self.a * zz.square()) % p;
+        let x3 = (Integer::from(m.square_ref()) - Integer::from(2) * &s).rem_euc(p);
+        let y3 = (m * Integer::from(&s - &x3) - Integer::from(8) * yy.square()).rem_euc(p);
+        let z3 = Integer::from(2) * &point.y * &point.z % p;
+        Some(Jacobian {
+            x: x3,
+            y: y3,
+            z: z3,
+        })
+    }
+
+    /// Adds the affine point `(x2, y2)` to a point in Jacobian coordinates, `None` being the point at infinity.
+    fn jacobian_add_affine(
+        &self,
+        point: &Jacobian,
+        x2: &Integer,
+        y2: &Integer,
+    ) -> Option<Jacobian> {
+        let p = &self.p;
+        let z1z1 = Integer::from(point.z.square_ref()) % p;
+        let u2 = Integer::from(x2 * &z1z1) % p;
+        let s2 = Integer::from(y2 * &point.z) * &z1z1 % p;
+        let h = (u2 - &point.x).rem_euc(p);
+        let r = (s2 - &point.y).rem_euc(p);
+        if h == 0 {
+            return if r == 0 {
+                self.jacobian_double(point)
+            } else {
+                None
+            };
+        }
+        let hh = Integer::from(h.square_ref()) % p;
+        let hhh = Integer::from(&h * &hh) % p;
+        let v = Integer::from(&point.x * &hh) % p;
+        let x3 = (Integer::from(r.square_ref()) - &hhh - Integer::from(2) * &v).rem_euc(p);
+        let y3 = (r * Integer::from(&v - &x3) - Integer::from(&point.y * &hhh)).rem_euc(p);
+        let z3 = Integer::from(&point.z * &h) % p;
+        Some(Jacobian {
+            x: x3,
+            y: y3,
+            z: z3,
+        })
+    }
+
+    /// Converts a point in Jacobian coordinates to affine coordinates.
+    fn to_affine(&self, point: &Jacobian) -> Point {
+        let p = &self.p;
+        let z_inv = point.z.clone().invert(p).unwrap();
+        let z_inv2 = Integer::from(z_inv.square_ref()) % p;
+        let x = Integer::from(&point.x * &z_inv2) % p;
+        let y = Integer::from(&point.y * &z_inv2) * z_inv % p;
+        Point::Affine(x, y)
+    }
+}
+
+impl Group for Curve {
+    type Element = Point;
+
+    fn identity(&self) -> Point {
+        Point::Infinity
+    }
+
+    fn op(&self, a: &Point, b: &Point) -> Point {
+        self.add(a, b)
+    }
+
+    fn inverse(&self, a: &Point) -> Point {
+        self.neg(a)
+    }
+
+    fn encode(&self, a: &Point) -> Integer {
+        match a {
+            Point::Infinity => Integer::ZERO,
+            Point::Affine(x, y) => Integer::from(y * &self.p) + x + 1,
+        }
+    }
+
+    fn pow(&self, a: &Point, k: &Integer) -> Point {
+        self.mul(a, k)
+    }
+}
+
+impl NegationMap for Curve {
+    /// Returns the point with the smallest `y` coordinate among `point` and `-point`.
+    fn canonical(&self, point: &Point) -> (Point, bool) {
+        match point {
+            Point::Affine(x, y) if Integer::from(y * 2) > self.p => {
+                (Point::Affine(x.clone(), Integer::from(&self.p - y)), true)
+            }
+            _ => (point.clone(), false),
+        }
+    }
+}
+
+/// Method used to compute an elliptic curve discrete logarithm, reported by `ec_discrete_log_report`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum EcDlogMethod {
+    /// The curve is singular and the logarithm was computed by `ec_discrete_log_singular`.
+    Singular,
+    /// The base point has order `p` (anomalous curve) and the logarithm was computed by `ec_discrete_log_smart`.
+    Smart,
+    /// The logarithm was computed with Pohlig-Hellman over the order of the base point.
+    PohligHellman {
+        /// MOV / Frey-Rück reductions of the prime order sub-problems transferred to `F_{p^k}*` (see
+        /// `ec_discrete_log_mov`), each with the logarithm of its sub-problem.
+        mov: Vec<MovReduction>,
+    },
+}
+
+/// Report of an elliptic curve discrete logarithm, returned by `ec_discrete_log_report`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EcDlog {
+    /// Discrete logarithm of `q` in base `p`.
+    pub log: Integer,
+    /// How the logarithm was computed.
+    pub method: EcDlogMethod,
+}
+
+/// Compute the elliptic curve discrete logarithm of `q` in base `p` (smallest non-negative integer `k` where `k*p = q` on `curve`).
+///
+/// If the order of the point `p` is known, it can be passed as `order` to speed up the computation (otherwise the
+/// group order is computed with `ec_group_order`). See `ec_discrete_log_report`.
+pub fn ec_discrete_log(
+    curve: &Curve,
+    p: &Point,
+    q: &Point,
+    order: Option<&Integer>,
+) -> Result<Integer, Error> {
+    ec_discrete_log_report(curve, p, q, order).map(|report| report.log)
+}
+
+/// Compute the elliptic curve discrete logarithm of `q` in base `p` (smallest non-negative integer `k` where `k*p = q` on `curve`).
+///
+/// The solver is configured with `options` (see `SolverOptions`): the group order is computed with
+/// `ec_group_order_with_options` when `order` is not given, and when the primality of the factors of the order must be
+/// proven, the order of `p` is made exact with the certified factorization, which also drives the Pohlig-Hellman
+/// decomposition. See `ec_discrete_log_report`.
+pub fn ec_discrete_log_with_options(
+    curve: &Curve,
+    p: &Point,
+    q: &Point,
+    order: Option<&Integer>,
+    options: &SolverOptions,
+) -> Result<Integer, Error> {
+    discrete_log_report_with_options(curve, p, q, order, options).map(|report| report.log)
+}
+
+/// Compute the elliptic curve discrete logarithm of `q` in base `p` (smallest non-negative integer `k` where `k*p = q` on `curve`),
+/// along with a report of how it was computed.
+///
+/// If the order of the point `p` is known, it can be passed as `order` to speed up the computation (otherwise the
+/// group order is computed with `ec_group_order`). The algorithm uses Pohlig-Hellman over the factorization of `order`,
+/// with trial multiplication, baby-step giant-step or Pollard's rho with the negation map for the prime order
+/// sub-problems. Large sub-problems whose embedding degree `k` is at most `MAX_EMBEDDING_DEGREE` are transferred to
+/// `F_{p^k}*` with the Tate pairing (see `ec_discrete_log_mov`), where they are solved with `discrete_log_with_order`
+/// (and index calculus) when `k = 1` and with `discrete_log_fpk` otherwise. Singular curves are handled by
+/// `ec_discrete_log_singular`, and points of order `p` (on anomalous curves) by `ec_discrete_log_smart`.
+pub fn ec_discrete_log_report(
+    curve: &Curve,
+    p: &Point,
+    q: &Point,
+    order: Option<&Integer>,
+) -> Result<EcDlog, Error> {
+    discrete_log_report_with_options(curve, p, q, order, &SolverOptions::default())
+}
+
+/// Compute the elliptic curve discrete logarithm of `q` in base `p` with a report, the solver being configured with
+/// `options` (see `ec_discrete_log_with_options`).
+fn discrete_log_report_with_options(
+    curve: &Curve,
+    p: &Point,
+    q: &Point,
+    order: Option<&Integer>,
+    options: &SolverOptions,
+) -> Result<EcDlog, Error> {
+    if !curve.contains(p) || !curve.contains(q) {
+        return Err(Error::PointNotOnCurve);
+    }
+    if curve.is_singular() {
+        return Ok(EcDlog {
+            log: ec_discrete_log_singular(curve, p, q)?,
+            method: EcDlogMethod::Singular,
+        });
+    }
+    if *p != Point::Infinity && curve.mul(p, curve.p()) == Point::Infinity {
+        return Ok(EcDlog {
+            log: ec_discrete_log_smart(curve, p, q)?,
+            method: EcDlogMethod::Smart,
+        });
+    }
+    let (order, order_factors) = match order {
+        Some(order) if !options.require_proven_primality => (order.clone(), factor(order)),
+        Some(order) => order_from_multiple(curve, p, factor(order), options)?,
+        None => {
+            let group_order = ec_group_order_with_options(curve, options)?;
+            order_from_multiple(curve, p, factor(&group_order), options)?
+        }
+    };
+
+    let mut reductions = Vec::new();
+    let k = pohlig_hellman_with_factors(curve, q, p, &order, &order_factors, |qj, pj, pi| {
+        if *pi < 1000 {
+            return discrete_log_trial_mul_group(curve, qj, pj, pi);
+        } else if *pi < shanks_steps::MAX_ORDER {
+            return discrete_log_shanks_steps_group(curve, qj, pj, pi);
+        }
+        if let Some(embedding_degree) = ec_embedding_degree(curve, pi, MAX_EMBEDDING_DEGREE) {
+            // The pairing maps the sub-problem to F_{p^k}*, where index calculus applies when k = 1
+            if let Ok(log) = mov(curve, pj, qj, pi, embedding_degree) {
+                reductions.push(MovReduction {
+                    log: log.clone(),
+                    order: pi.clone(),
+                    embedding_degree,
+                    field_size: curve.p().clone().pow(embedding_degree as u32),
+                });
+                return Ok(log);
+            }
+        }
+        discrete_log_pollard_rho_negation(curve, qj, pj, pi)
+    })?;
+    if curve.mul(p, &k) == *q {
+        Ok(EcDlog {
+            log: k,
+            method: EcDlogMethod::PohligHellman { mov: reductions },
+        })
+    } else {
+        Err(Error::LogDoesNotExist)
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use std::str::FromStr;
+
+    use super::*;
+
+    /// Logarithm of the point `q` in base `g` of `prime_order_curve`.
+    pub(crate) const PRIME_ORDER_LOG: u64 = 131383004;
+
+    /// Returns a curve over a 32-bit field whose group has prime order, with its order and points `g` and `q`.
+    pub(crate) fn prime_order_curve() -> (Curve, Integer, Point, Point) {
+        let curve = Curve::new(4294967311u64.into(), 5.into(), 7.into());
+        let g = curve
+            .point(3871601465u64.into(), 1852154904u64.into())
+            .unwrap();
+        let q = curve
+            .point(4155227213u64.into(), 2487749464u64.into())
+            .unwrap();
+        (curve, 4294871149u64.into(), g, q)
+    }
+
+    fn toy_curve() -> Curve {
+        // y^2 = x^3 + 2x + 3 over F_97, #E = 100
+        Curve::new(97.into(), 2.into(), 3.into())
+    }
+
+    #[test]
+    fn point_arithmetic() {
+        let curve = toy_curve();
+        let p = curve.point(3.into(), 6.into()).unwrap();
+        assert_eq!(curve.point(3.into(), 7.into()), Err(Error::PointNotOnCurve));
+        assert_eq!(curve.double(&p), Point::Affine(80.into(), 10.into()));
+        assert_eq!(curve.add(&p, &curve.neg(&p)), Point::Infinity);
+        assert_eq!(curve.mul(&p, &5.into()), Point::Infinity);
+        assert_eq!(curve.mul(&p, &(-1).into()), curve.neg(&p));
+
+        let mut acc = Point::Infinity;
+        for k in 0..20 {
+            assert_eq!(curve.mul(&p, &k.into()), acc);
+            assert!(curve.contains(&acc));
+            acc = curve.add(&acc, &p);
+        }
+    }
+
+    #[test]
+    fn points() {
+        let curve = toy_curve();
+        assert_eq!(
+            curve.lift_x(&3.into()),
+            Some(Point::Affine(3.into(), 6.into()))
+        );
+        assert_eq!(curve.lift_x(&2.into()), None);
+
+        let mut rand_state = RandState::new();
+        for _ in 0..10 {
+            assert!(curve.contains(&curve.random_point(&mut rand_state)));
+        }
+
+        // The curve has 100 points, so its twist has 2 * 97 + 2 - 100 points
+        let twist = curve.quadratic_twist();
+        assert_eq!(ec_group_order(&twist).unwrap(), 96);
+    }
+
+    #[test]
+    fn singular() {
+        assert!(!toy_curve().is_singular());
+        assert!(Curve::new(97.into(), 0.into(), 0.into()).is_singular());
+        // (x - 1)^2 (x + 2) = x^3 - 3x + 2
+        assert!(Curve::new(97.into(), (-3).into(), 2.into()).is_singular());
+    }
+
+    #[test]
+    fn ec_discrete_log_() {
+        // Prime order group
+        let (curve, order, g, q) = prime_order_curve();
+        assert_eq!(curve.mul(&g, &order), Point::Infinity);
+        assert_eq!(
+            ec_discrete_log(&curve, &g, &q, Some(&order)).unwrap(),
+            PRIME_ORDER_LOG
+        );
+
+        // Smooth order group: 5^2 * 17 * 607 * 16649
+        let curve = Curve::new(4294967311u64.into(), 9.into(), 11.into());
+        let g = curve.point(815398389.into(), 845984680.into()).unwrap();
+        let q = curve
+            .point(2583482498u64.into(), 3719802455u64.into())
+            .unwrap();
+        assert_eq!(
+            ec_discrete_log(&curve, &g, &q, None).unwrap(),
+            Integer::from_str("3664843848").unwrap()
+        );
+        let options = SolverOptions {
+            require_proven_primality: true,
+            ..Default::default()
+        };
+        assert_eq!(
+            ec_discrete_log_with_options(&curve, &g, &q, None, &options).unwrap(),
+            Integer::from_str("3664843848").unwrap()
+        );
+
+        assert_eq!(
+            ec_discrete_log(&curve, &g, &Point::Affine(1.into(), 1.into()), None),
+            Err(Error::PointNotOnCurve)
+        );
+    }
+}
@@ -0,0 +1,358 @@
+use std::{
+    collections::{BTreeMap, HashMap},
+    ops::{Mul, MulAssign},
+};
+
+use rug::{ops::Pow, Integer};
+
+use crate::{factorize::is_prime, prove_prime, Error, PrimalityCertificate};
+
+/// Primality status of a factor of a `Factorization`.
+///
+/// The variants are ordered by strength of the primality claim.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Primality {
+    /// The factor is composite (it has not been factored further).
+    Composite,
+    /// The factor passed a probabilistic primality test.
+    Probable,
+    /// The factor is proven prime.
+    Proven,
+}
+
+impl Primality {
+    /// Returns the primality status of `n` established with `factorize::is_prime`.
+    ///
+    /// The Baillie-PSW test has no counterexample below `2**64`, so primes below this bound are `Proven`, and larger
+    /// ones are `Probable`.
+    pub fn of(n: &Integer) -> Self {
+        if !is_prime(n) {
+            Self::Composite
+        } else if n.significant_bits() <= 64 {
+            Self::Proven
+        } else {
+            Self::Probable
+        }
+    }
+}
+
+/// Factorization of a positive integer into powers of factors, each recorded with its primality status.
+///
+/// A factorization is complete when all its factors are (proven or probable) primes. The factors are kept in increasing
+/// order and are all greater than 1: the empty factorization is the one of 1.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Factorization {
+    factors: BTreeMap<Integer, (usize, Primality)>,
+}
+
+impl Factorization {
+    /// Creates the empty factorization (of 1).
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Multiplies the factorization by `factor**exponent`.
+    ///
+    /// If `factor` is already present, the exponents are added and the strongest primality status is kept. Factors
+    /// equal to 1 and zero exponents are ignored.
+    pub fn insert(&mut self, factor: Integer, exponent: usize, primality: Primality) {
+        if factor <= 1 || exponent == 0 {
+            return;
+        }
+        let entry = self.factors.entry(factor).or_insert((0, primality));
+        entry.0 += exponent;
+        entry.1 = entry.1.max(primality);
+    }
+
+    /// Returns the number of distinct factors.
+    pub fn len(&self) -> usize {
+        self.factors.len()
+    }
+
+    /// Returns `true` if there is no factor (factorization of 1).
+    pub fn is_empty(&self) -> bool {
+        self.factors.is_empty()
+    }
+
+    /// Returns the factors with their exponents, in increasing order.
+    pub fn iter(&self) -> impl Iterator<Item = (&Integer, usize)> + '_ {
+        self.factors.iter().map(|(q, (e, _))| (q, *e))
+    }
+
+    /// Returns the factors with their exponents and primality status, in increasing order.
+    pub fn iter_with_primality(&self) -> impl Iterator<Item = (&Integer, usize, Primality)> + '_ {
+        self.factors.iter().map(|(q, (e, s))| (q, *e, *s))
+    }
+
+    /// Returns the exponent of `factor` (0 if it is not a factor).
+    pub fn exponent(&self, factor: &Integer) -> usize {
+        self.factors.get(factor).map_or(0, |(e, _)| *e)
+    }
+
+    /// Returns the primality status of `factor`, `None` if it is not a factor.
+    pub fn primality(&self, factor: &Integer) -> Option<Primality> {
+        self.factors.get(factor).map(|(_, s)| *s)
+    }
+
+    /// Returns `true` if all the factors are (proven or probable) primes.
+    pub fn is_complete(&self) -> bool {
+        self.factors
+            .values()
+            .all(|(_, s)| *s != Primality::Composite)
+    }
+
+    /// Returns `true` if all the factors are proven primes.
+    pub fn is_proven(&self) -> bool {
+        self.factors.values().all(|(_, s)| *s == Primality::Proven)
+    }
+
+    /// Returns the factored number, the product of the factors raised to their exponents.
+    pub fn value(&self) -> Integer {
+        self.iter().map(|(q, e)| q.clone().pow(e as u32)).product()
+    }
+
+    /// Checks that this is a factorization of `n`: the product of the factors must be `n`, and the primality status of
+    /// each factor must agree with `factorize::is_prime`.
+    ///
+    /// Returns `Error::InvalidFactorization` otherwise.
+    pub fn validate(&self, n: &Integer) -> Result<(), Error> {
+        let consistent = self
+            .iter_with_primality()
+            .all(|(q, _, s)| (s == Primality::Composite) != is_prime(q));
+        if consistent && self.value() == *n {
+            Ok(())
+        } else {
+            Err(Error::InvalidFactorization)
+        }
+    }
+
+    /// Merges another factorization of the same number into this one, splitting the composite factors with the
+    /// greatest common divisors of all the known factors, so that the result is at least as fine as both.
+    ///
+    /// Returns `Error::InvalidFactorization` if the two factorizations are not of the same number.
+    pub fn merge(&mut self, other: &Factorization) -> Result<(), Error> {
+        if self.value() != other.value() {
+            return Err(Error::InvalidFactorization);
+        }
+        let status = |q: &Integer, this: &Self| {
+            other
+                .primality(q)
+                .max(this.primality(q))
+                .unwrap_or_else(|| Primality::of(q))
+        };
+
+        loop {
+            let known = self
+                .factors
+                .keys()
+                .chain(other.factors.keys())
+                .cloned()
+                .collect::<Vec<_>>();
+            let split = self
+                .iter_with_primality()
+                .filter(|(_, _, s)| *s == Primality::Composite)
+                .find_map(|(c, e, _)| {
+                    known.iter().find_map(|f| {
+                        let g = Integer::from(c.gcd_ref(f));
+                        (g != 1 && g != *c).then(|| (c.clone(), e, g))
+                    })
+                });
+            let Some((c, e, g)) = split else {
+                return Ok(());
+            };
+            self.factors.remove(&c);
+            let h = Integer::from(&c / &g);
+            let (sg, sh) = (status(&g, self), status(&h, self));
+            self.insert(g, e, sg);
+            self.insert(h, e, sh);
+        }
+    }
+
+    /// Proves the primality of the prime factors with `prove_prime`, upgrading their status to `Proven`, and returns
+    /// their certificates in increasing order of the factors.
+    ///
+    /// Returns `Error::IncompleteFactorization` if a factor is composite, and `Error::PrimalityNotProven` if a factor
+    /// could not be proven prime (the factorization is then left unchanged).
+    pub fn certify(&mut self) -> Result<Vec<PrimalityCertificate>, Error> {
+        if !self.is_complete() {
+            return Err(Error::IncompleteFactorization);
+        }
+        let certificates = self
+            .factors
+            .keys()
+            .map(prove_prime)
+            .collect::<Result<Vec<_>, _>>()?;
+        for (_, status) in self.factors.values_mut() {
+            *status = Primality::Proven;
+        }
+        Ok(certificates)
+    }
+
+    /// Returns the divisors of the factored number, in no particular order.
+    pub fn divisors(&self) -> impl Iterator<Item = Integer> + '_ {
+        let factors = self.iter().collect::<Vec<_>>();
+        let mut exponents = vec![0; factors.len()];
+        let mut done = false;
+        std::iter::from_fn(move || {
+            if done {
+                return None;
+            }
+            let divisor = factors
+                .iter()
+                .zip(&exponents)
+                .map(|((q, _), e)| (*q).clone().pow(*e as u32))
+                .product::<Integer>();
+            // Next exponents, as a mixed-radix counter
+            done = true;
+            for (i, (_, e)) in factors.iter().enumerate() {
+                if exponents[i] < *e {
+                    exponents[i] += 1;
+                    done = false;
+                    break;
+                }
+                exponents[i] = 0;
+            }
+            Some(divisor)
+        })
+    }
+
+    /// Returns Euler's totient of the factored number, the order of `(Z/nZ)*`.
+    ///
+    /// Returns `Error::IncompleteFactorization` if a factor is composite.
+    pub fn euler_phi(&self) -> Result<Integer, Error> {
+        if !self.is_complete() {
+            return Err(Error::IncompleteFactorization);
+        }
+        Ok(self
+            .iter()
+            .map(|(p, e)| p.clone().pow(e as u32 - 1) * Integer::from(p - 1))
+            .product())
+    }
+
+    /// Returns the Carmichael function of the factored number, the exponent of `(Z/nZ)*` (largest order of its
+    /// elements).
+    ///
+    /// Returns `Error::IncompleteFactorization` if a factor is composite.
+    pub fn carmichael_lambda(&self) -> Result<Integer, Error> {
+        if !self.is_complete() {
+            return Err(Error::IncompleteFactorization);
+        }
+        Ok(self.iter().fold(Integer::from(1), |lambda, (p, e)| {
+            let lambda_pe = if *p == 2 && e >= 3 {
+                Integer::from(1) << (e as u32 - 2)
+            } else {
+                p.clone().pow(e as u32 - 1) * Integer::from(p - 1)
+            };
+            lambda.lcm(&lambda_pe)
+        }))
+    }
+}
+
+impl Mul<&Factorization> for &Factorization {
+    type Output = Factorization;
+
+    /// Returns the factorization of the product of the two factored numbers.
+    fn mul(self, rhs: &Factorization) -> Factorization {
+        let mut product = self.clone();
+        product *= rhs;
+        product
+    }
+}
+
+impl MulAssign<&Factorization> for Factorization {
+    fn mul_assign(&mut self, rhs: &Factorization) {
+        for (q, e, s) in rhs.iter_with_primality() {
+            self.insert(q.clone(), e, s);
+        }
+    }
+}
+
+impl FromIterator<(Integer, usize)> for Factorization {
+    /// Collects factors with their exponents, their primality status being computed with `Primality::of`.
+    fn from_iter<T: IntoIterator<Item = (Integer, usize)>>(iter: T) -> Self {
+        let mut factorization = Self::new();
+        for (q, e) in iter {
+            let primality = Primality::of(&q);
+            factorization.insert(q, e, primality);
+        }
+        factorization
+    }
+}
+
+impl From<HashMap<Integer, usize>> for Factorization {
+    fn from(factors: HashMap<Integer, usize>) -> Self {
+        factors.into_iter().collect()
+    }
+}
+
+impl From<&Factorization> for HashMap<Integer, usize> {
+    fn from(factorization: &Factorization) -> Self {
+        factorization.iter().map(|(q, e)| (q.clone(), e)).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use super::*;
+    use crate::factorize::factor;
+
+    #[test]
+    fn factorization() {
+        let f = factor(&360.into());
+        assert_eq!(f.value(), 360);
+        assert!(f.is_proven());
+        assert_eq!(f.exponent(&2.into()), 3);
+        assert_eq!(f.exponent(&7.into()), 0);
+        assert_eq!(f.euler_phi().unwrap(), 96);
+        assert_eq!(f.carmichael_lambda().unwrap(), 12);
+        let mut divisors = f.divisors().collect::<Vec<_>>();
+        divisors.sort();
+        assert_eq!(divisors.len(), 24);
+        assert_eq!(divisors[..6], [1, 2, 3, 4, 5, 6]);
+        assert_eq!(f.validate(&360.into()), Ok(()));
+        assert_eq!(f.validate(&720.into()), Err(Error::InvalidFactorization));
+
+        let product = &f * &factor(&14.into());
+        assert_eq!(product.value(), 5040);
+        assert_eq!(product.exponent(&2.into()), 4);
+        assert_eq!(Factorization::new().value(), 1);
+
+        let big = Integer::from_str("100000000000000000039").unwrap();
+        assert_eq!(Primality::of(&big), Primality::Probable);
+        assert_eq!(factor(&big).primality(&big), Some(Primality::Probable));
+
+        let mut f = factor(&(big.clone() * 6));
+        let certificates = f.certify().unwrap();
+        assert!(f.is_proven());
+        assert_eq!(certificates.len(), 3);
+        assert!(certificates.iter().all(|c| c.verify()));
+        assert_eq!(*certificates[2].n(), big);
+    }
+
+    #[test]
+    fn merge() {
+        // 2 * 15 * 77 and 6 * 5 * 77 give 2 * 3 * 5 * 77
+        let mut f = Factorization::new();
+        f.insert(2.into(), 1, Primality::Proven);
+        f.insert(15.into(), 1, Primality::Composite);
+        f.insert(77.into(), 1, Primality::Composite);
+        let mut g = Factorization::new();
+        g.insert(6.into(), 1, Primality::Composite);
+        g.insert(5.into(), 1, Primality::Proven);
+        g.insert(77.into(), 1, Primality::Composite);
+        assert_eq!(f.euler_phi(), Err(Error::IncompleteFactorization));
+        f.merge(&g).unwrap();
+        assert_eq!(
+            f.iter().map(|(q, e)| (q.clone(), e)).collect::<Vec<_>>(),
+            vec![(2.into(), 1), (3.into(), 1), (5.into(), 1), (77.into(), 1)]
+        );
+        assert_eq!(f.primality(&77.into()), Some(Primality::Composite));
+        assert_eq!(f.validate(&2310.into()), Ok(()));
+        assert_eq!(
+            f.merge(&factor(&7.into())),
+            Err(Error::InvalidFactorization)
+        );
+    }
+}
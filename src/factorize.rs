@@ -0,0 +1,426 @@
+//! Integer factorization: trial division, Brent's variant of Pollard's rho algorithm, Pollard's p - 1 algorithm and
+//! Lenstra's elliptic curve method (ECM) and the self-initializing quadratic sieve (SIQS), with primality checks on the
+//! cofactors.
+
+use std::collections::HashMap;
+
+use primal::Primes;
+use rug::{integer::IsPrime, ops::Pow, Integer};
+
+pub use crate::siqs::siqs;
+use crate::{Factorization, Primality};
+
+/// Bound of the trial division of `factor`.
+pub const TRIAL_DIVISION_BOUND: u32 = 1 << 16;
+
+/// Number of iterations of Pollard's rho algorithm tried by `factor` before switching to the p - 1 algorithm.
+pub(crate) const RHO_ITERATIONS: u64 = 1 << 16;
+/// Smoothness bound of Pollard's p - 1 algorithm in `factor`.
+pub(crate) const PM1_BOUND: u64 = 100_000;
+/// First stage 1 bound and number of curves of ECM in `factor`, both increased at each round.
+pub(crate) const ECM_BOUND: u64 = 2_000;
+pub(crate) const ECM_CURVES: u64 = 25;
+/// Number of rounds of ECM tried by `factor` before switching to the quadratic sieve (the last one, with 100 curves
+/// of stage 1 bound 50000, finds factors of about 25 digits).
+const ECM_ROUNDS: usize = 3;
+/// Largest number of decimal digits of the numbers factored with the quadratic sieve by `factor` (ECM is used beyond).
+const SIQS_MAX_DIGITS: usize = 100;
+/// First parameter of Suyama's parametrization used by `ecm` (0, 1, 3 and 5 give degenerate curves).
+const ECM_FIRST_SIGMA: u64 = 6;
+/// Distance between two giant steps of ECM stage 2.
+const ECM_STEP: u64 = 210;
+
+/// Returns `true` if `n` is prime (Baillie-PSW test followed by Miller-Rabin rounds with random bases, for which no
+/// composite counterexample is known).
+pub fn is_prime(n: &Integer) -> bool {
+    n.is_probably_prime(30) != IsPrime::No
+}
+
+/// Divides `n` by the primes below `bound`, returning the prime factors found with their multiplicity and the remaining
+/// cofactor (which has no prime factor below `bound`).
+pub fn trial_division(n: &Integer, bound: u32) -> (Vec<(Integer, usize)>, Integer) {
+    let mut n = n.clone().abs();
+    let mut factors = Vec::new();
+    for p in Primes::all().take_while(|p| *p < bound as usize) {
+        let p = Integer::from(p);
+        if Integer::from(p.square_ref()) > n {
+            break;
+        }
+        let e = n.remove_factor_mut(&p);
+        if e > 0 {
+            factors.push((p, e as usize));
+        }
+    }
+    (factors, n)
+}
+
+/// Returns the prime factorization of `|n|` (`n` must be non-zero).
+///
+/// Small factors are removed by trial division, then the composite cofactors are split with Brent's variant of Pollard's
+/// rho algorithm, Pollard's p - 1 algorithm and Lenstra's elliptic curve method with increasing bounds. When these
+/// methods give up, numbers of up to about 100 digits are handed to the quadratic sieve. This is repeated until all the
+/// factors pass the primality test, and their primality status is set with `Primality::of`. The cofactors which resist
+/// all these methods (such as products of two primes of more than 50 digits each) are kept as `Primality::Composite`
+/// factors, so that the factorization is then not complete.
+///
+/// # Panics
+///
+/// Panics if `n` is zero.
+pub fn factor(n: &Integer) -> Factorization {
+    assert!(*n != 0, "cannot factor zero");
+    let (small_factors, cofactor) = trial_division(n, TRIAL_DIVISION_BOUND);
+    let mut factors = Factorization::new();
+    for (p, e) in small_factors {
+        factors.insert(p, e, Primality::Proven);
+    }
+
+    let mut composites = vec![(cofactor, 1)];
+    while let Some((c, e)) = composites.pop() {
+        if c == 1 {
+            continue;
+        }
+        match Primality::of(&c) {
+            Primality::Composite => {}
+            primality => {
+                factors.insert(c, e, primality);
+                continue;
+            }
+        }
+        if let Some((root, k)) = perfect_power(&c) {
+            composites.push((root, e * k));
+        } else if let Some(d) = find_factor(&c) {
+            let q = Integer::from(&c / &d);
+            composites.push((d, e));
+            composites.push((q, e));
+        } else {
+            factors.insert(c, e, Primality::Composite);
+        }
+    }
+    factors
+}
+
+/// Returns the prime factorization of `|n|` as a map from the prime factors to their multiplicity (see `factor`, whose
+/// primality statuses are dropped).
+///
+/// # Panics
+///
+/// Panics if `n` is zero.
+pub fn fast_factor(n: &Integer) -> HashMap<Integer, usize> {
+    HashMap::from(&factor(n))
+}
+
+/// Returns `(r, k)` with `r**k = n` and `k > 1`, if `n` is a perfect power.
+pub(crate) fn perfect_power(n: &Integer) -> Option<(Integer, usize)> {
+    if !n.is_perfect_power() {
+        return None;
+    }
+    (2..n.significant_bits()).find_map(|k| {
+        let r = Integer::from(n.root_ref(k));
+        (Integer::from((&r).pow(k)) == *n).then_some((r, k as usize))
+    })
+}
+
+/// Returns a non-trivial factor of the odd composite `n`, which is not a perfect power, `None` if none of the methods
+/// of `factor` finds one within its budget.
+fn find_factor(n: &Integer) -> Option<Integer> {
+    if let Some(d) = pollard_rho_brent(n, RHO_ITERATIONS) {
+        return Some(d);
+    }
+    if let Some(d) = pollard_pm1(n, PM1_BOUND) {
+        return Some(d);
+    }
+    let (mut b1, mut curves, mut sigma) = (ECM_BOUND, ECM_CURVES, ECM_FIRST_SIGMA);
+    for _ in 0..ECM_ROUNDS {
+        if let Some(d) = (sigma..sigma + curves).find_map(|s| ecm_curve(n, b1, 100 * b1, s)) {
+            return Some(d);
+        }
+        sigma += curves;
+        b1 *= 5;
+        curves *= 2;
+    }
+    if n.significant_bits() as f64 * std::f64::consts::LOG10_2 < SIQS_MAX_DIGITS as f64 {
+        return siqs(n);
+    }
+    None
+}
+
+/// Finds a non-trivial factor of the composite `n` with Brent's variant of Pollard's rho algorithm, `None` if none is
+/// found within `max_iterations` iterations of the polynomial map (factors up to about `max_iterations**2` are found).
+pub fn pollard_rho_brent(n: &Integer, max_iterations: u64) -> Option<Integer> {
+    if n.is_even() {
+        return (*n > 2).then(|| Integer::from(2));
+    }
+    const BATCH: u64 = 128;
+
+    let mut iterations = 0;
+    for c in 1u32.. {
+        let f = |y: &Integer| (Integer::from(y.square_ref()) + c) % n;
+        let mut y = Integer::from(2);
+        let mut x = y.clone();
+        let mut ys = y.clone();
+        let mut q = Integer::from(1);
+        let mut g = Integer::from(1);
+        let mut r = 1;
+        while g == 1 {
+            if iterations >= max_iterations {
+                return None;
+            }
+            x = y.clone();
+            for _ in 0..r {
+                y = f(&y);
+            }
+            let mut k = 0;
+            while k < r && g == 1 {
+                ys = y.clone();
+                for _ in 0..BATCH.min(r - k) {
+                    y = f(&y);
+                    q = q * Integer::from(&x - &y) % n;
+                }
+                g = Integer::from(q.gcd_ref(n));
+                k += BATCH;
+            }
+            iterations += 2 * r;
+            r *= 2;
+        }
+        if g == *n {
+            // The batch overshot: step back through it one iteration at a time
+            loop {
+                ys = f(&ys);
+                g = Integer::from(&x - &ys).gcd(n);
+                if g != 1 {
+                    break;
+                }
+            }
+        }
+        if g != *n {
+            return Some(g);
+        }
+        // The sequence cycled modulo all the factors at once: try another polynomial
+    }
+    None
+}
+
+/// Returns the prime powers `q**e <= bound` with the largest such `e` for each prime `q <= bound`.
+fn prime_powers(bound: u64) -> impl Iterator<Item = u64> {
+    Primes::all()
+        .map(|q| q as u64)
+        .take_while(move |q| *q <= bound)
+        .map(move |q| {
+            let mut qe = q;
+            while qe <= bound / q {
+                qe *= q;
+            }
+            qe
+        })
+}
+
+/// Finds a non-trivial factor of the composite `n` with Pollard's p - 1 algorithm, `None` if no prime factor `p` of `n`
+/// has a `bound`-powersmooth `p - 1` (or if all of them have).
+pub fn pollard_pm1(n: &Integer, bound: u64) -> Option<Integer> {
+    let mut a = Integer::from(2);
+    for qe in prime_powers(bound) {
+        a.pow_mod_mut(&Integer::from(qe), n).unwrap();
+    }
+    let g = Integer::from(&a - 1u32).gcd(n);
+    if g == 1 {
+        return None;
+    } else if g != *n {
+        return Some(g);
+    }
+
+    // All the factors were found at once: check the gcd after each prime power
+    let mut a = Integer::from(2);
+    for qe in prime_powers(bound) {
+        a.pow_mod_mut(&Integer::from(qe), n).unwrap();
+        let g = Integer::from(&a - 1u32).gcd(n);
+        if g == *n {
+            return None;
+        } else if g != 1 {
+            return Some(g);
+        }
+    }
+    None
+}
+
+/// Montgomery curve `B*y**2 = x**3 + A*x**2 + x` modulo `n`, whose points are handled in `(X : Z)` coordinates.
+struct MontgomeryCurve<'a> {
+    n: &'a Integer,
+    /// `(A + 2) / 4`
+    a24: Integer,
+}
+
+impl MontgomeryCurve<'_> {
+    fn double(&self, (x, z): &(Integer, Integer)) -> (Integer, Integer) {
+        let s = Integer::from(x + z).square() % self.n;
+        let d = Integer::from(x - z).square() % self.n;
+        let t = Integer::from(&s - &d);
+        let z2 = (Integer::from(&self.a24 * &t) + &d) * t % self.n;
+        (s * d % self.n, z2)
+    }
+
+    /// Returns `p + q`, `diff` being `p - q`.
+    fn add(
+        &self,
+        (xp, zp): &(Integer, Integer),
+        (xq, zq): &(Integer, Integer),
+        (xd, zd): &(Integer, Integer),
+    ) -> (Integer, Integer) {
+        let u = Integer::from(xp - zp) * Integer::from(xq + zq);
+        let v = Integer::from(xp + zp) * Integer::from(xq - zq);
+        let x = Integer::from(&u + &v).square() % self.n * zd % self.n;
+        let z = (u - v).square() % self.n * xd % self.n;
+        (x, z)
+    }
+
+    /// Returns `k * p` (`k > 0`) with the Montgomery ladder.
+    fn mul(&self, p: &(Integer, Integer), k: u64) -> (Integer, Integer) {
+        let mut r0 = p.clone();
+        let mut r1 = self.double(p);
+        for i in (0..63 - k.leading_zeros()).rev() {
+            if k >> i & 1 == 1 {
+                r0 = self.add(&r1, &r0, p);
+                r1 = self.double(&r1);
+            } else {
+                r1 = self.add(&r0, &r1, p);
+                r0 = self.double(&r0);
+            }
+        }
+        r0
+    }
+}
+
+/// Runs ECM with stage 1 bound `b1` and stage 2 bound `b2` on the curve given by Suyama's parametrization with
+/// `sigma`, whose group order is divisible by 12.
+fn ecm_curve(n: &Integer, b1: u64, b2: u64, sigma: u64) -> Option<Integer> {
+    let non_trivial = |g: Integer| (g != 1 && g != *n).then_some(g);
+
+    let sigma = Integer::from(sigma);
+    let u = (Integer::from(sigma.square_ref()) - 5u32) % n;
+    let v = Integer::from(&sigma * 4u32) % n;
+    let x0 = Integer::from((&u).pow(3)) % n;
+    let z0 = Integer::from((&v).pow(3)) % n;
+    let num = Integer::from(&v - &u).pow(3) * (Integer::from(&u * 3u32) + &v) % n;
+    let den = Integer::from(&x0 * &v) * 16u32 % n;
+    let a24 = match den.invert_ref(n) {
+        Some(inv) => num * Integer::from(inv) % n,
+        None => return non_trivial(den.gcd(n)),
+    };
+    let curve = MontgomeryCurve { n, a24 };
+
+    // Stage 1: multiply by all the prime powers below b1
+    let mut point = (x0, z0);
+    for qe in prime_powers(b1) {
+        point = curve.mul(&point, qe);
+    }
+    let g = Integer::from(point.1.gcd_ref(n));
+    if g == *n {
+        return None;
+    } else if g != 1 {
+        return Some(g);
+    }
+
+    // Stage 2: each prime q in (b1, b2] is written as m*D +- j with j odd and at most D/2, and q*point is the identity
+    // modulo a factor when (m*D)*point = +-j*point, that is when their X/Z coordinates match
+    let half = ECM_STEP / 2;
+    let mut baby = vec![point.clone(), curve.double(&point)];
+    let two = baby[1].clone();
+    // baby[i] = (2*i + 1) * point
+    baby[1] = curve.add(&two, &point, &point);
+    for i in 2..=(half as usize / 2) {
+        let next = curve.add(&baby[i - 1], &two, &baby[i - 2]);
+        baby.push(next);
+    }
+    let giant = curve.mul(&point, ECM_STEP);
+    let mut m = (b1 / ECM_STEP).max(2);
+    let mut previous = curve.mul(&giant, m - 1);
+    let mut current = curve.mul(&giant, m);
+    let mut acc = Integer::from(1);
+    let primes = Primes::all()
+        .map(|q| q as u64)
+        .skip_while(|q| *q <= b1.max(ECM_STEP))
+        .take_while(|q| *q <= b2);
+    for q in primes {
+        let target = (q + half) / ECM_STEP;
+        while m < target {
+            let next = curve.add(&current, &giant, &previous);
+            previous = std::mem::replace(&mut current, next);
+            m += 1;
+        }
+        let (xj, zj) = &baby[(q.abs_diff(m * ECM_STEP) / 2) as usize];
+        acc = acc * (Integer::from(&current.0 * zj) - Integer::from(xj * &current.1)) % n;
+    }
+    non_trivial(acc.gcd(n))
+}
+
+/// Finds a non-trivial factor of the composite `n` with Lenstra's elliptic curve method on `curves` curves, with stage
+/// 1 bound `b1` and stage 2 bound `100 * b1`, `None` if none is found.
+pub fn ecm(n: &Integer, b1: u64, curves: u64) -> Option<Integer> {
+    (ECM_FIRST_SIGMA..ECM_FIRST_SIGMA + curves).find_map(|sigma| ecm_curve(n, b1, 100 * b1, sigma))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn factor_() {
+        let factorization = |factors: &[(u64, usize)]| {
+            factors
+                .iter()
+                .map(|(q, e)| (Integer::from(*q), *e))
+                .collect::<Factorization>()
+        };
+        assert_eq!(factor(&1.into()), Factorization::new());
+        assert_eq!(
+            factor(&(-360).into()),
+            factorization(&[(2, 3), (3, 2), (5, 1)])
+        );
+        // The composite cofactor left by the trial division used to be returned as a prime
+        assert_eq!(
+            factor(&(Integer::from(1000003) * 1000033 * 7)),
+            factorization(&[(7, 1), (1000003, 1), (1000033, 1)])
+        );
+        assert_eq!(
+            factor(&(Integer::from(4294967311u64).pow(3) * 65537)),
+            factorization(&[(65537, 1), (4294967311, 3)])
+        );
+        // 2^67 - 1 = 193707721 * 761838257287
+        assert_eq!(
+            factor(&((Integer::from(1) << 67) - 1)),
+            factorization(&[(193707721, 1), (761838257287, 1)])
+        );
+        // Two 51-digit prime factors, too large for the quadratic sieve, resist all the methods: the cofactor is left
+        // composite
+        let p = (Integer::from(10).pow(50u32) * 3u32).next_prime();
+        let q = (Integer::from(10).pow(50u32) * 7u32).next_prime();
+        let n = Integer::from(&p * &q) * 12;
+        let factors = factor(&n);
+        assert!(!factors.is_complete());
+        assert_eq!(factors.primality(&(p * q)), Some(Primality::Composite));
+        assert_eq!(factors.value(), n);
+        assert_eq!(
+            fast_factor(&(-360).into()),
+            HashMap::from([(2.into(), 3), (3.into(), 2), (5.into(), 1)])
+        );
+    }
+
+    #[test]
+    fn factoring_methods() {
+        let n = Integer::from(193707721) * 761838257287u64;
+        assert_eq!(pollard_rho_brent(&n, 1 << 20).unwrap(), 193707721);
+        assert_eq!(pollard_rho_brent(&n, 100), None);
+
+        // 2434002108217681 - 1 = 2^4 * 3 * 5 * ... * 41 is 41-powersmooth, 2147483659 - 1 has the factor 2402107
+        let n = Integer::from(2434002108217681u64) * 2147483659u64;
+        assert_eq!(pollard_pm1(&n, 100).unwrap(), 2434002108217681u64);
+        assert_eq!(pollard_pm1(&n, 10), None);
+
+        // 1099511627791 and 1125899906842679 are primes
+        let n = Integer::from(1099511627791u64) * 1125899906842679u64;
+        let d = ecm(&n, 2000, 200).unwrap();
+        assert!(d == 1099511627791u64 || d == 1125899906842679u64);
+
+        assert!(is_prime(&1125899906842679u64.into()));
+        assert!(!is_prime(&(Integer::from(1000003) * 1000033)));
+    }
+}
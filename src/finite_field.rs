@@ -0,0 +1,496 @@
+use rug::{ops::Pow, rand::RandState, Integer};
+
+use crate::{
+    discrete_log_group, order_from_multiple,
+    pohlig_hellman::pohlig_hellman_with_factors,
+    poly::{Poly, PolyModulus},
+    utils::factor_power_minus_one,
+    Error, Factorization, Group, SolverOptions,
+};
+
+/// An element of a finite field `F_{p^k}` (see `ExtensionField`).
+///
+/// It is a polynomial of degree less than `k` with coefficients in `F_p`.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct FieldElement(Poly);
+
+impl FieldElement {
+    /// Returns the coefficient of `x**i`.
+    pub fn coefficient(&self, i: usize) -> Integer {
+        self.0.coeff(i)
+    }
+
+    /// Returns the coefficients of the element, lowest degree first, without leading zeros.
+    pub fn coefficients(&self) -> Vec<Integer> {
+        (0..self.0.degree().map_or(0, |d| d + 1))
+            .map(|i| self.0.coeff(i))
+            .collect()
+    }
+
+    /// Returns `true` if the element is zero.
+    pub fn is_zero(&self) -> bool {
+        self.0.is_zero()
+    }
+
+    /// Returns `true` if the element is one.
+    pub fn is_one(&self) -> bool {
+        self.0.is_one()
+    }
+}
+
+/// The finite field `F_{p^k}`, represented as `F_p[x]/(m(x))` for a monic irreducible polynomial `m` of degree `k`
+/// (polynomial basis).
+///
+/// The multiplicative group `F_{p^k}*` implements `Group`, so every generic solver of this crate can be used on it
+/// (see also `discrete_log_fpk`).
+#[derive(Debug, Clone)]
+pub struct ExtensionField {
+    p: Integer,
+    modulus: PolyModulus,
+    /// `x**p` modulo `m`, used to compute the Frobenius endomorphism.
+    x_p: Poly,
+}
+
+impl ExtensionField {
+    /// Creates the field `F_{p^k}`, `p` being prime and `k` at least 1.
+    ///
+    /// The modulus is the first irreducible trinomial `x**k + c1*x + c0` (`c0, c1 < p`) by increasing `c1 + c0`. When
+    /// there is none (e.g. `F_{2^5}`), it is the first irreducible `x**k + t(x)`, the tails `t` of degree less than `k`
+    /// being ordered as the integers whose base `p` digits are their coefficients (`t(p)`).
+    pub fn new(p: &Integer, k: usize) -> Self {
+        let monic = |tail: Vec<Integer>| {
+            let mut coeffs = tail;
+            coeffs.resize(k, Integer::ZERO);
+            coeffs.push(Integer::from(1));
+            Poly::from_coeffs(coeffs, p)
+        };
+
+        // Trinomials, with c0 and c1 below p (and c1 = 0 when k = 1)
+        let limit = p.to_u64().unwrap_or(u64::MAX);
+        let c1_limit = if k > 1 { limit } else { 1 };
+        let trinomials = (0..=(limit - 1).saturating_add(c1_limit - 1)).flat_map(move |s| {
+            (s.saturating_sub(limit - 1)..=s.min(c1_limit - 1)).map(move |c1| (c1, s - c1))
+        });
+        let trinomials = trinomials.map(|(c1, c0)| {
+            let mut tail = vec![Integer::from(c0)];
+            if k > 1 {
+                tail.push(Integer::from(c1));
+            }
+            monic(tail)
+        });
+
+        // Every tail of degree less than k, in increasing order
+        let tails = (0u64..).map_while(|t| {
+            let mut t = Integer::from(t);
+            let mut tail = Vec::new();
+            while t != 0 && tail.len() <= k {
+                tail.push(Integer::from(&t % p));
+                t /= p;
+            }
+            (tail.len() <= k).then(|| monic(tail))
+        });
+
+        let modulus = trinomials
+            .chain(tails)
+            .find(|m| is_irreducible(m, p))
+            .expect("there are irreducible polynomials of every degree");
+        Self::from_poly(p, &modulus)
+    }
+
+    /// Creates the field `F_p[x]/(m(x))`, `m` being given by its coefficients (lowest degree first).
+    ///
+    /// Returns `Error::ReducibleModulus` if `m` is not an irreducible polynomial of degree at least 1.
+    pub fn with_modulus(p: &Integer, modulus: &[Integer]) -> Result<Self, Error> {
+        let modulus = Poly::from_coeffs(modulus.to_vec(), p).monic(p);
+        if modulus.degree().unwrap_or(0) == 0 || !is_irreducible(&modulus, p) {
+            return Err(Error::ReducibleModulus);
+        }
+        Ok(Self::from_poly(p, &modulus))
+    }
+
+    fn from_poly(p: &Integer, modulus: &Poly) -> Self {
+        let modulus = PolyModulus::new(modulus, p);
+        let x_p = modulus.pow(&Poly::x(), p);
+        Self {
+            p: p.clone(),
+            modulus,
+            x_p,
+        }
+    }
+
+    /// Returns the characteristic `p` of the field.
+    pub fn p(&self) -> &Integer {
+        &self.p
+    }
+
+    /// Returns the degree `k` of the field over `F_p`.
+    pub fn degree(&self) -> usize {
+        self.modulus.degree()
+    }
+
+    /// Returns the number of elements of the field, `p**k`.
+    pub fn size(&self) -> Integer {
+        self.p.clone().pow(self.degree() as u32)
+    }
+
+    /// Returns the coefficients of the (monic) modulus of the field, lowest degree first.
+    pub fn modulus(&self) -> Vec<Integer> {
+        FieldElement(self.modulus.modulus().clone()).coefficients()
+    }
+
+    /// Returns the element `c0 + c1*x + c2*x**2 + ...` with the given coefficients (lowest degree first).
+    pub fn element(&self, coeffs: &[Integer]) -> FieldElement {
+        FieldElement(
+            self.modulus
+                .reduce(&Poly::from_coeffs(coeffs.to_vec(), &self.p)),
+        )
+    }
+
+    /// Returns the element of the prime field `F_p` represented by `c`.
+    pub fn constant(&self, c: &Integer) -> FieldElement {
+        FieldElement(Poly::constant(c, &self.p))
+    }
+
+    /// Returns the zero element.
+    pub fn zero(&self) -> FieldElement {
+        FieldElement(Poly::zero())
+    }
+
+    /// Returns the unit element.
+    pub fn one(&self) -> FieldElement {
+        FieldElement(Poly::one())
+    }
+
+    /// Returns the generator `x` of the polynomial basis.
+    pub fn x(&self) -> FieldElement {
+        FieldElement(self.modulus.reduce(&Poly::x()))
+    }
+
+    /// Returns a random element of the field.
+    pub fn random(&self, rand_state: &mut RandState<'_>) -> FieldElement {
+        FieldElement(Poly::from_coeffs(
+            (0..self.degree())
+                .map(|_| self.p.clone().random_below(rand_state))
+                .collect(),
+            &self.p,
+        ))
+    }
+
+    /// Returns `a + b`.
+    pub fn add(&self, a: &FieldElement, b: &FieldElement) -> FieldElement {
+        FieldElement(a.0.add(&b.0, &self.p))
+    }
+
+    /// Returns `a - b`.
+    pub fn sub(&self, a: &FieldElement, b: &FieldElement) -> FieldElement {
+        FieldElement(a.0.sub(&b.0, &self.p))
+    }
+
+    /// Returns `-a`.
+    pub fn neg(&self, a: &FieldElement) -> FieldElement {
+        FieldElement(a.0.neg(&self.p))
+    }
+
+    /// Returns `a * b`.
+    pub fn mul(&self, a: &FieldElement, b: &FieldElement) -> FieldElement {
+        FieldElement(self.modulus.mul(&a.0, &b.0))
+    }
+
+    /// Returns `a**2`.
+    pub fn square(&self, a: &FieldElement) -> FieldElement {
+        FieldElement(self.modulus.square(&a.0))
+    }
+
+    /// Returns the inverse of `a`, `None` if `a` is zero.
+    pub fn invert(&self, a: &FieldElement) -> Option<FieldElement> {
+        a.0.invert(self.modulus.modulus(), &self.p)
+            .map(FieldElement)
+    }
+
+    /// Returns the image of `a` by the `i`-th power of the Frobenius endomorphism, `a**(p**i)`.
+    ///
+    /// As the coefficients lie in `F_p`, `a(x)**p = a(x**p)`: the computation is a polynomial composition with the
+    /// precomputed `x**p` instead of an exponentiation.
+    pub fn frobenius(&self, a: &FieldElement, i: usize) -> FieldElement {
+        let mut result = a.0.clone();
+        for _ in 0..i % self.degree() {
+            let degree = result.degree().unwrap_or(0);
+            result = (0..=degree).rev().fold(Poly::zero(), |acc, j| {
+                self.modulus
+                    .mul(&acc, &self.x_p)
+                    .add(&Poly::constant(&result.coeff(j), &self.p), &self.p)
+            });
+        }
+        FieldElement(result)
+    }
+
+    /// Returns the norm of `a` over `F_p`, the product of its conjugates `a**(p**i)`.
+    pub fn norm(&self, a: &FieldElement) -> Integer {
+        (1..self.degree())
+            .fold(a.clone(), |acc, i| self.mul(&acc, &self.frobenius(a, i)))
+            .coefficient(0)
+    }
+
+    /// Returns a square root of `a`, `None` if `a` is not a square (Tonelli-Shanks algorithm).
+    pub fn sqrt(&self, a: &FieldElement, rand_state: &mut RandState<'_>) -> Option<FieldElement> {
+        if a.is_zero() {
+            return Some(self.zero());
+        }
+        let q1 = self.size() - 1u32;
+        let half = Integer::from(&q1 >> 1);
+        if !self.pow(a, &half).is_one() {
+            return None;
+        }
+
+        let s = q1.find_one(0).unwrap();
+        let t = Integer::from(&q1 >> s);
+        let z = loop {
+            let z = self.random(rand_state);
+            if !z.is_zero() && !self.pow(&z, &half).is_one() {
+                break z;
+            }
+        };
+
+        let mut m = s;
+        let mut c = self.pow(&z, &t);
+        let mut u = self.pow(a, &t);
+        let mut r = self.pow(a, &(Integer::from(&t + 1u32) >> 1));
+        while !u.is_one() {
+            let mut i = 0;
+            let mut u2 = u.clone();
+            while !u2.is_one() {
+                u2 = self.square(&u2);
+                i += 1;
+            }
+            let b = self.pow(&c, &(Integer::from(1) << (m - i - 1)));
+            m = i;
+            c = self.square(&b);
+            u = self.mul(&u, &c);
+            r = self.mul(&r, &b);
+        }
+        Some(r)
+    }
+
+    /// Returns the prime factorization of the order `p**k - 1` of the multiplicative group.
+    ///
+    /// `p**k - 1` is first split into the cyclotomic values `Phi_d(p)` for the divisors `d` of `k`, which are then
+    /// factored separately.
+    pub fn multiplicative_order_factors(&self) -> Factorization {
+        factor_power_minus_one(&self.p, self.degree())
+    }
+}
+
+impl Group for ExtensionField {
+    type Element = FieldElement;
+
+    fn identity(&self) -> FieldElement {
+        self.one()
+    }
+
+    fn op(&self, a: &FieldElement, b: &FieldElement) -> FieldElement {
+        self.mul(a, b)
+    }
+
+    /// Returns the inverse of `a` in the field.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `a` is zero.
+    fn inverse(&self, a: &FieldElement) -> FieldElement {
+        self.invert(a).expect("element is not invertible")
+    }
+
+    fn encode(&self, a: &FieldElement) -> Integer {
+        (0..self.degree())
+            .rev()
+            .fold(Integer::ZERO, |acc, i| acc * &self.p + a.coefficient(i))
+    }
+
+    fn pow(&self, a: &FieldElement, k: &Integer) -> FieldElement {
+        if *k < 0 {
+            FieldElement(self.modulus.pow(&self.inverse(a).0, &Integer::from(-k)))
+        } else {
+            FieldElement(self.modulus.pow(&a.0, k))
+        }
+    }
+}
+
+/// Returns `true` if the monic polynomial `m` of degree `k` is irreducible over `F_p` (Rabin's test).
+///
+/// `m` is irreducible if and only if it divides `x**(p**k) - x` and is coprime with `x**(p**(k/q)) - x` for every
+/// prime `q` dividing `k`.
+fn is_irreducible(m: &Poly, p: &Integer) -> bool {
+    let k = m.degree().unwrap();
+    if k == 1 {
+        return true;
+    }
+    let modulus = PolyModulus::new(m, p);
+
+    // x**(p**i) for i = 0..=k
+    let mut frobenius = vec![Poly::x()];
+    for i in 0..k {
+        frobenius.push(modulus.pow(&frobenius[i], p));
+    }
+    if frobenius[k] != Poly::x() {
+        return false;
+    }
+
+    (2..=k)
+        .filter(|q| k.is_multiple_of(*q) && (2..*q).all(|d| !q.is_multiple_of(d)))
+        .all(|q| frobenius[k / q].sub(&Poly::x(), p).gcd(m, p).is_one())
+}
+
+/// Compute the discrete logarithm of `a` in base `b` in the finite field `field` (smallest non-negative integer `x`
+/// where `b**x = a` in `F_{p^k}`).
+///
+/// The order of `F_{p^k}*`, `p**k - 1`, is factored (see `ExtensionField::multiplicative_order_factors`) to compute
+/// the order of `b`, then the logarithm is computed with the Pohlig-Hellman algorithm, its prime order sub-problems
+/// being solved with `discrete_log_group`.
+pub fn discrete_log_fpk(
+    field: &ExtensionField,
+    a: &FieldElement,
+    b: &FieldElement,
+) -> Result<Integer, Error> {
+    discrete_log_fpk_with_options(field, a, b, &SolverOptions::default())
+}
+
+/// Compute the discrete logarithm of `a` in base `b` in the finite field `field` (smallest non-negative integer `x`
+/// where `b**x = a` in `F_{p^k}`).
+///
+/// The solver is configured with `options`: when the primality of the factors of `p**k - 1` must be proven, they are
+/// certified before computing the order of `b` and running Pohlig-Hellman. See `discrete_log_fpk`.
+pub fn discrete_log_fpk_with_options(
+    field: &ExtensionField,
+    a: &FieldElement,
+    b: &FieldElement,
+    options: &SolverOptions,
+) -> Result<Integer, Error> {
+    if a.is_zero() || b.is_zero() {
+        return Err(Error::LogDoesNotExist);
+    }
+    let (order, order_factors) =
+        order_from_multiple(field, b, field.multiplicative_order_factors(), options)?;
+
+    let x = pohlig_hellman_with_factors(field, a, b, &order, &order_factors, |aj, bj, pi| {
+        discrete_log_group(field, aj, bj, pi)
+    })?;
+    if field.pow(b, &x) == *a {
+        Ok(x)
+    } else {
+        Err(Error::LogDoesNotExist)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn extension_field() {
+        let p = Integer::from(1_000_003);
+        for k in 1..=6 {
+            let field = ExtensionField::new(&p, k);
+            assert_eq!(field.degree(), k);
+
+            let mut rand_state = RandState::new();
+            let a = field.random(&mut rand_state);
+            let b = field.random(&mut rand_state);
+            assert!(field.mul(&a, &field.inverse(&a)).is_one());
+            assert_eq!(
+                field.mul(&field.add(&a, &b), &field.sub(&a, &b)),
+                field.sub(&field.square(&a), &field.square(&b))
+            );
+            // Every non-zero element is a (p**k - 1)-th root of unity
+            assert!(field.pow(&a, &(field.size() - 1u32)).is_one());
+            assert_eq!(field.frobenius(&a, 1), field.pow(&a, &p));
+            assert_eq!(field.frobenius(&a, k), a);
+            assert_eq!(
+                field.norm(&a),
+                field
+                    .pow(&a, &((field.size() - 1u32) / (p.clone() - 1u32)))
+                    .coefficient(0)
+            );
+
+            let r = field.sqrt(&field.square(&a), &mut rand_state).unwrap();
+            assert_eq!(field.square(&r), field.square(&a));
+
+            assert_eq!(
+                field.multiplicative_order_factors().value(),
+                field.size() - 1u32
+            );
+        }
+    }
+
+    #[test]
+    fn modulus() {
+        // F_7[i] with i^2 = -1
+        let p = Integer::from(7);
+        let field = ExtensionField::with_modulus(&p, &[1.into(), 0.into(), 1.into()]).unwrap();
+        assert_eq!(field.modulus(), vec![1, 0, 1]);
+        let i = field.x();
+        assert_eq!(field.square(&i), field.constant(&(-1).into()));
+        assert_eq!(
+            field
+                .element(&[0.into(), 0.into(), 1.into()])
+                .coefficients(),
+            vec![6]
+        );
+
+        // x^2 + 2 = (x - 1)(x + 1) modulo 3
+        assert_eq!(
+            ExtensionField::with_modulus(&3.into(), &[2.into(), 0.into(), 1.into()]).unwrap_err(),
+            Error::ReducibleModulus
+        );
+    }
+
+    #[test]
+    fn small_characteristic() {
+        // No irreducible trinomial x^k + c1*x + c0 exists for F_{2^5} and F_{2^8}
+        for (p, k, modulus) in [
+            (2, 5, vec![1, 0, 1, 0, 0, 1]),
+            (2, 8, vec![1, 1, 0, 1, 1, 0, 0, 0, 1]),
+            (3, 4, vec![2, 1, 0, 0, 1]),
+        ] {
+            let p = Integer::from(p);
+            let field = ExtensionField::new(&p, k);
+            assert_eq!(field.degree(), k);
+            assert_eq!(field.modulus(), modulus);
+
+            let b = field.element(&[1.into(), 1.into()]);
+            let x = Integer::from(37);
+            let a = field.pow(&b, &x);
+            let log = discrete_log_fpk(&field, &a, &b).unwrap();
+            assert_eq!(field.pow(&b, &log), a);
+        }
+    }
+
+    #[test]
+    fn discrete_log_fpk_() {
+        // F_{p^2} and F_{p^6} with p = 1000003
+        let p = Integer::from(1_000_003);
+        for k in [2, 6] {
+            let field = ExtensionField::new(&p, k);
+            let mut rand_state = RandState::new();
+            let b = field.random(&mut rand_state);
+            let x = Integer::from(123456789);
+            let a = field.pow(&b, &x);
+            let log = discrete_log_fpk(&field, &a, &b).unwrap();
+            assert_eq!(field.pow(&b, &log), a);
+
+            let options = SolverOptions {
+                require_proven_primality: true,
+                ..Default::default()
+            };
+            assert_eq!(
+                discrete_log_fpk_with_options(&field, &a, &b, &options),
+                Ok(log)
+            );
+        }
+
+        let field = ExtensionField::new(&p, 2);
+        assert_eq!(
+            discrete_log_fpk(&field, &field.zero(), &field.x()),
+            Err(Error::LogDoesNotExist)
+        );
+    }
+}
@@ -0,0 +1,233 @@
+use std::{fmt::Debug, hash::Hash};
+
+use rug::{ops::Pow, ops::RemRounding, Integer};
+
+use crate::Factorization;
+
+/// A finite group in which discrete logarithms can be computed.
+///
+/// The group law is written multiplicatively: `op` is the group operation, `identity` its neutral element and `pow`
+/// the repeated application of `op`. Every generic solver of this crate (`discrete_log_group`,
+/// `discrete_log_pohlig_hellman_group`, ...) only relies on this trait.
+pub trait Group {
+    /// Type of the group elements.
+    type Element: Clone + Debug + Eq + Hash;
+
+    /// Returns the identity element of the group.
+    fn identity(&self) -> Self::Element;
+
+    /// Returns the result of the group operation applied to `a` and `b`.
+    fn op(&self, a: &Self::Element, b: &Self::Element) -> Self::Element;
+
+    /// Returns the inverse of `a`.
+    fn inverse(&self, a: &Self::Element) -> Self::Element;
+
+    /// Returns a canonical integer encoding of `a`.
+    ///
+    /// Two elements must have the same encoding if and only if they are equal. The encoding is used to partition the
+    /// group in random walks (e.g. `discrete_log_pollard_rho_group`).
+    fn encode(&self, a: &Self::Element) -> Integer;
+
+    /// Returns `a` raised to the power `k` (`a` operated with itself `k` times).
+    ///
+    /// Negative exponents are computed using the inverse of `a`.
+    fn pow(&self, a: &Self::Element, k: &Integer) -> Self::Element {
+        let base = if *k < 0 { self.inverse(a) } else { a.clone() };
+        let k = k.clone().abs();
+
+        let mut result = self.identity();
+        for i in (0..k.significant_bits()).rev() {
+            result = self.op(&result, &result);
+            if k.get_bit(i) {
+                result = self.op(&result, &base);
+            }
+        }
+        result
+    }
+
+    /// Returns `true` if `a` is the identity element.
+    fn is_identity(&self, a: &Self::Element) -> bool {
+        *a == self.identity()
+    }
+}
+
+/// Returns the order of `a` in `group`.
+///
+/// `multiple_factors` must be the prime factorization of a multiple of the order of `a`, such as the group order. A
+/// composite factor is not split, so that the result is then only a multiple of the order.
+pub fn element_order<G: Group>(
+    group: &G,
+    a: &G::Element,
+    multiple_factors: &Factorization,
+) -> Integer {
+    let mut order = multiple_factors
+        .iter()
+        .map(|(q, e)| q.clone().pow(e as u32))
+        .product::<Integer>();
+    for (q, e) in multiple_factors.iter() {
+        for _ in 0..e {
+            let reduced = Integer::from(&order / q);
+            if !group.is_identity(&group.pow(a, &reduced)) {
+                break;
+            }
+            order = reduced;
+        }
+    }
+    order
+}
+
+/// A group whose inversion is cheap enough to be used as a negation map, such as the group of points of an elliptic
+/// curve.
+///
+/// Random walks can then work on the equivalence classes `{a, a**-1}`, which halves the size of the search space
+/// (see `discrete_log_pollard_rho_negation`).
+pub trait NegationMap: Group {
+    /// Returns the representative of the class `{a, a**-1}`, and `true` if it is the inverse of `a`.
+    ///
+    /// The representative is the element with the smallest canonical encoding.
+    fn canonical(&self, a: &Self::Element) -> (Self::Element, bool) {
+        let inverse = self.inverse(a);
+        if self.encode(&inverse) < self.encode(a) {
+            (inverse, true)
+        } else {
+            (a.clone(), false)
+        }
+    }
+}
+
+/// The multiplicative group of integers modulo `n`, `(Z/nZ)*`.
+///
+/// Elements are represented by their least non-negative residue modulo `n`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ModularGroup {
+    n: Integer,
+}
+
+impl ModularGroup {
+    /// Creates the multiplicative group of integers modulo `n`.
+    pub fn new(n: Integer) -> Self {
+        Self { n }
+    }
+
+    /// Returns the modulus `n` of the group.
+    pub fn modulus(&self) -> &Integer {
+        &self.n
+    }
+
+    /// Returns the element of the group represented by `x` (the least non-negative residue of `x` modulo `n`).
+    pub fn element(&self, x: &Integer) -> Integer {
+        x.clone().rem_euc(&self.n)
+    }
+}
+
+impl Group for ModularGroup {
+    type Element = Integer;
+
+    fn identity(&self) -> Integer {
+        Integer::from(1) % &self.n
+    }
+
+    fn op(&self, a: &Integer, b: &Integer) -> Integer {
+        Integer::from(a * b) % &self.n
+    }
+
+    /// Returns the inverse of `a` modulo `n`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `a` is not invertible modulo `n`.
+    fn inverse(&self, a: &Integer) -> Integer {
+        a.invert_ref(&self.n)
+            .map(Integer::from)
+            .expect("element is not invertible")
+    }
+
+    fn encode(&self, a: &Integer) -> Integer {
+        a.clone()
+    }
+
+    fn pow(&self, a: &Integer, k: &Integer) -> Integer {
+        a.pow_mod_ref(k, &self.n)
+            .map(Integer::from)
+            .expect("element is not invertible")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        discrete_log_group, discrete_log_pohlig_hellman_group, discrete_log_pollard_rho_group,
+    };
+
+    /// Additive group of integers modulo `m`, written multiplicatively.
+    struct AdditiveGroup(Integer);
+
+    impl Group for AdditiveGroup {
+        type Element = Integer;
+
+        fn identity(&self) -> Integer {
+            Integer::ZERO
+        }
+
+        fn op(&self, a: &Integer, b: &Integer) -> Integer {
+            Integer::from(a + b) % &self.0
+        }
+
+        fn inverse(&self, a: &Integer) -> Integer {
+            Integer::from(&self.0 - a) % &self.0
+        }
+
+        fn encode(&self, a: &Integer) -> Integer {
+            a.clone()
+        }
+    }
+
+    #[test]
+    fn modular_group() {
+        let group = ModularGroup::new(13.into());
+        assert_eq!(group.identity(), 1);
+        assert_eq!(group.op(&5.into(), &6.into()), 4);
+        assert_eq!(group.inverse(&5.into()), 8);
+        assert_eq!(group.pow(&2.into(), &12.into()), 1);
+        assert_eq!(group.pow(&2.into(), &(-1).into()), 7);
+        assert_eq!(group.element(&(-1).into()), 12);
+        assert!(group.is_identity(&1.into()));
+    }
+
+    #[test]
+    fn order() {
+        let group = ModularGroup::new(13.into());
+        let factors = Factorization::from_iter([(2.into(), 2), (3.into(), 1)]);
+        assert_eq!(element_order(&group, &2.into(), &factors), 12);
+        assert_eq!(element_order(&group, &3.into(), &factors), 3);
+        assert_eq!(element_order(&group, &12.into(), &factors), 2);
+        assert_eq!(element_order(&group, &1.into(), &factors), 1);
+    }
+
+    #[test]
+    fn default_pow() {
+        let group = AdditiveGroup(101.into());
+        assert_eq!(group.pow(&7.into(), &0.into()), 0);
+        assert_eq!(group.pow(&7.into(), &1.into()), 7);
+        assert_eq!(group.pow(&7.into(), &15.into()), 4);
+        assert_eq!(group.pow(&7.into(), &(-15).into()), 97);
+    }
+
+    #[test]
+    fn generic_solvers() {
+        // x * 7 = 1234 (mod 1000003)
+        let group = AdditiveGroup(1000003.into());
+        let x = discrete_log_group(&group, &1234.into(), &7.into(), &1000003.into()).unwrap();
+        assert_eq!(x * 7 % 1000003, 1234);
+        let x = discrete_log_pollard_rho_group(&group, &1234.into(), &7.into(), &1000003.into())
+            .unwrap();
+        assert_eq!(x * 7 % 1000003, 1234);
+
+        // x * 5 = 42 (mod 2^4 * 3^5 * 1009)
+        let group = AdditiveGroup(3922992.into());
+        let x = discrete_log_pohlig_hellman_group(&group, &42.into(), &5.into(), &3922992.into())
+            .unwrap();
+        assert_eq!(x * 5 % 3922992, 42);
+    }
+}
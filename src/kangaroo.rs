@@ -0,0 +1,454 @@
+use std::{
+    collections::HashMap,
+    sync::{
+        atomic::{AtomicBool, AtomicUsize, Ordering},
+        Mutex,
+    },
+    thread,
+};
+
+use rug::{rand::RandState, Integer};
+
+use crate::{utils::thread_count, Error, Group, ModularGroup};
+
+const RETRIES: usize = 10;
+
+/// Intervals shorter than this are searched by trial multiplication.
+const TRIAL_MUL_WIDTH: u32 = 256;
+
+/// Expected number of distinguished points found by a kangaroo before the collision (`log2`), which bounds the size
+/// of the table of distinguished points.
+const DISTINGUISHED_POINTS_LOG2: u32 = 5;
+
+/// Pollard's kangaroo (lambda) algorithm for computing the discrete logarithm of `a` in base `b` modulo `n` when it is
+/// known to lie in the interval `[lo, hi]` (integer `x` where `lo <= x <= hi` and `b**x = a (mod n)`).
+///
+/// It runs in `O(sqrt(hi - lo))` group operations whatever the order of `b`, and requires a constant amount of memory.
+/// See `discrete_log_interval_group`.
+pub fn discrete_log_interval(
+    n: &Integer,
+    a: &Integer,
+    b: &Integer,
+    lo: &Integer,
+    hi: &Integer,
+) -> Result<Integer, Error> {
+    let a = a.clone() % n;
+    let b = b.clone() % n;
+
+    discrete_log_interval_group(&ModularGroup::new(n.clone()), &a, &b, lo, hi)
+}
+
+/// Pollard's kangaroo (lambda) algorithm for computing the discrete logarithm of `a` in base `b` in any `group` when it
+/// is known to lie in the interval `[lo, hi]` (integer `x` where `lo <= x <= hi` and `b**x = a`).
+///
+/// A tame kangaroo starts from the middle of the interval and a wild one from `a`, both jumping by powers of `b` chosen
+/// from the encoding of their position (see `Group::encode`), with a mean jump of about `sqrt(hi - lo) / 2`. Only the
+/// distinguished positions are stored, and the logarithm is recovered when the wild kangaroo lands on a distinguished
+/// position of the tame one. The algorithm is randomized: `Error::LogDoesNotExist` is returned when the kangaroos do
+/// not meet after several attempts. If the interval is longer than the order of `b`, any of the logarithms in the
+/// interval may be returned. `lo` must be non-negative and at most `hi`, `Error::InvalidInterval` is returned
+/// otherwise.
+pub fn discrete_log_interval_group<G: Group>(
+    group: &G,
+    a: &G::Element,
+    b: &G::Element,
+    lo: &Integer,
+    hi: &Integer,
+) -> Result<Integer, Error> {
+    if *lo < 0 || lo > hi {
+        return Err(Error::InvalidInterval);
+    }
+    let width = Integer::from(hi - lo);
+
+    if width < TRIAL_MUL_WIDTH {
+        let mut x = group.pow(b, lo);
+        let mut i = lo.clone();
+        while i <= *hi {
+            if x == *a {
+                return Ok(i);
+            }
+            x = group.op(&x, b);
+            i += 1;
+        }
+        return Err(Error::LogDoesNotExist);
+    }
+
+    let walk = KangarooWalk::new(group, a, b, lo, &width, 1);
+    let mut rand_state = RandState::new();
+    for _ in 0..RETRIES {
+        let mut tame = walk.tame(&mut rand_state);
+        let mut wild = walk.wild(&mut rand_state);
+
+        // Distinguished positions, with their distance and whether a tame kangaroo reached them
+        let mut distinguished = HashMap::<G::Element, (Integer, bool)>::new();
+        let mut steps = Integer::new();
+        'walk: while steps < walk.max_steps {
+            for (kangaroo, is_tame) in [(&mut tame, true), (&mut wild, false)] {
+                walk.jump(kangaroo);
+                if !walk.is_distinguished(&kangaroo.position) {
+                    continue;
+                }
+                match distinguished.get(&kangaroo.position) {
+                    None => {
+                        distinguished.insert(
+                            kangaroo.position.clone(),
+                            (kangaroo.distance.clone(), is_tame),
+                        );
+                    }
+                    Some((distance, was_tame)) if *was_tame != is_tame => {
+                        if let Some(x) = walk.logarithm(kangaroo, is_tame, distance) {
+                            return Ok(x);
+                        }
+                        // The kangaroos now follow the same path
+                        break 'walk;
+                    }
+                    // A kangaroo is trapped in a cycle
+                    Some(_) => break 'walk,
+                }
+            }
+            steps += 1;
+        }
+    }
+
+    Err(Error::LogDoesNotExist)
+}
+
+/// Parallel version of `discrete_log_interval` (van Oorschot-Wiener), running `threads` pairs of kangaroos at once
+/// (`0` for all the available cores).
+///
+/// See `discrete_log_interval_parallel_group`.
+pub fn discrete_log_interval_parallel(
+    n: &Integer,
+    a: &Integer,
+    b: &Integer,
+    lo: &Integer,
+    hi: &Integer,
+    threads: usize,
+) -> Result<Integer, Error> {
+    let a = a.clone() % n;
+    let b = b.clone() % n;
+
+    discrete_log_interval_parallel_group(&ModularGroup::new(n.clone()), &a, &b, lo, hi, threads)
+}
+
+/// Parallel version of `discrete_log_interval_group` (van Oorschot-Wiener), running `threads` pairs of kangaroos at
+/// once (`0` for all the available cores).
+///
+/// Each thread runs a tame and a wild kangaroo, whose mean jump is scaled by the number of threads so that the herds
+/// cover the interval `threads` times faster. The distinguished positions are shared by all the threads: a kangaroo
+/// landing on a distinguished position of the same herd, or on one of the other herd which does not give the
+/// logarithm, is restarted from a new random position. This gives a near-linear speed-up in the number of threads.
+pub fn discrete_log_interval_parallel_group<G>(
+    group: &G,
+    a: &G::Element,
+    b: &G::Element,
+    lo: &Integer,
+    hi: &Integer,
+    threads: usize,
+) -> Result<Integer, Error>
+where
+    G: Group + Sync,
+    G::Element: Send + Sync,
+{
+    let threads = thread_count(threads);
+    if *lo < 0 || lo > hi {
+        return Err(Error::InvalidInterval);
+    }
+    let width = Integer::from(hi - lo);
+    if threads == 1 || width < TRIAL_MUL_WIDTH {
+        return discrete_log_interval_group(group, a, b, lo, hi);
+    }
+
+    let walk = KangarooWalk::new(group, a, b, lo, &width, threads);
+    let max_steps = Integer::from(&walk.max_steps * RETRIES as u32);
+    let distinguished = Mutex::new(HashMap::<G::Element, (Integer, bool)>::new());
+    let result = Mutex::new(None);
+    let done = AtomicBool::new(false);
+    let failures = AtomicUsize::new(0);
+
+    thread::scope(|scope| {
+        for i in 0..threads {
+            let (walk, distinguished, result, done, failures) =
+                (&walk, &distinguished, &result, &done, &failures);
+            let max_steps = &max_steps;
+            scope.spawn(move || {
+                let mut rand_state = RandState::new();
+                rand_state.seed(&Integer::from(i));
+                let mut kangaroos = [
+                    (walk.tame(&mut rand_state), true),
+                    (walk.wild(&mut rand_state), false),
+                ];
+                let mut steps = Integer::new();
+                while steps < *max_steps && !done.load(Ordering::Relaxed) {
+                    for (kangaroo, is_tame) in &mut kangaroos {
+                        walk.jump(kangaroo);
+                        if !walk.is_distinguished(&kangaroo.position) {
+                            continue;
+                        }
+                        let mut distinguished = distinguished.lock().unwrap();
+                        match distinguished.get(&kangaroo.position) {
+                            None => {
+                                distinguished.insert(
+                                    kangaroo.position.clone(),
+                                    (kangaroo.distance.clone(), *is_tame),
+                                );
+                                continue;
+                            }
+                            Some((distance, was_tame)) if *was_tame != *is_tame => {
+                                if let Some(x) = walk.logarithm(kangaroo, *is_tame, distance) {
+                                    *result.lock().unwrap() = Some(x);
+                                    done.store(true, Ordering::Relaxed);
+                                    return;
+                                }
+                                if failures.fetch_add(1, Ordering::Relaxed) + 1 >= RETRIES {
+                                    done.store(true, Ordering::Relaxed);
+                                }
+                            }
+                            Some(_) => {}
+                        }
+                        // The kangaroo follows the path of another one
+                        *kangaroo = if *is_tame {
+                            walk.tame(&mut rand_state)
+                        } else {
+                            walk.wild(&mut rand_state)
+                        };
+                    }
+                    steps += 1;
+                }
+            });
+        }
+    });
+
+    result.into_inner().unwrap().ok_or(Error::LogDoesNotExist)
+}
+
+/// Kangaroo at `position`, `distance` being the exponent of `b` it travelled (plus its unknown starting exponent).
+struct Kangaroo<E> {
+    position: E,
+    distance: Integer,
+}
+
+/// Pseudo-random walk of the kangaroos, whose jumps are powers of `b` with their exponent.
+struct KangarooWalk<'a, G: Group> {
+    group: &'a G,
+    a: &'a G::Element,
+    b: &'a G::Element,
+    lo: &'a Integer,
+    hi: Integer,
+    jumps: Vec<(G::Element, Integer)>,
+    mask: Integer,
+    /// Number of jumps of each kangaroo after which they are not expected to meet anymore.
+    max_steps: Integer,
+}
+
+impl<'a, G: Group> KangarooWalk<'a, G> {
+    /// Creates the walk of `herd` pairs of tame and wild kangaroos in the interval `[lo, lo + width]`.
+    fn new(
+        group: &'a G,
+        a: &'a G::Element,
+        b: &'a G::Element,
+        lo: &'a Integer,
+        width: &Integer,
+        herd: usize,
+    ) -> Self {
+        // Jumps b**(2**i) for i < k, where k is the smallest integer with a mean jump (2**k - 1) / k >= herd *
+        // sqrt(width) / 2
+        let root = width.clone().sqrt();
+        let mean = Integer::from(&root * herd as u32);
+        let mut k = 1;
+        while ((Integer::from(1) << k) - 1) * 2 < Integer::from(&mean * k) {
+            k += 1;
+        }
+        let jumps = (0..k)
+            .map(|i| {
+                let distance = Integer::from(1) << i;
+                (group.pow(b, &distance), distance)
+            })
+            .collect::<Vec<_>>();
+
+        // A position is distinguished when the bits of its encoding above the jump index are zero modulo 2**d, so
+        // that each kangaroo goes through about 2**DISTINGUISHED_POINTS_LOG2 of them before the collision
+        let d = (Integer::from(&root / herd as u32).significant_bits())
+            .saturating_sub(DISTINGUISHED_POINTS_LOG2 + 1);
+        let max_steps = Integer::from(&root * 8) / herd as u32 + (Integer::from(1) << d) * 8;
+
+        Self {
+            group,
+            a,
+            b,
+            lo,
+            hi: Integer::from(lo + width),
+            jumps,
+            mask: (Integer::from(1) << d) - 1,
+            max_steps,
+        }
+    }
+
+    /// Returns a tame kangaroo at a random `b**t` with `t` in the third quarter of the interval.
+    fn tame(&self, rand_state: &mut RandState<'_>) -> Kangaroo<G::Element> {
+        let width = Integer::from(&self.hi - self.lo);
+        let quarter = Integer::from(&width >> 2);
+        let t = (width >> 1) + self.lo + quarter.random_below(rand_state);
+        Kangaroo {
+            position: self.group.pow(self.b, &t),
+            distance: t,
+        }
+    }
+
+    /// Returns a wild kangaroo at a random `a * b**w` with `w` in the first quarter of the interval (`x + w` being
+    /// unknown).
+    fn wild(&self, rand_state: &mut RandState<'_>) -> Kangaroo<G::Element> {
+        let quarter = Integer::from(&self.hi - self.lo) >> 2u32;
+        let w = quarter.random_below(rand_state);
+        Kangaroo {
+            position: self.group.op(self.a, &self.group.pow(self.b, &w)),
+            distance: w,
+        }
+    }
+
+    /// Returns the logarithm given by a kangaroo landing on a position reached at `distance` by a kangaroo of the other
+    /// herd, if it lies in the interval.
+    fn logarithm(
+        &self,
+        kangaroo: &Kangaroo<G::Element>,
+        is_tame: bool,
+        distance: &Integer,
+    ) -> Option<Integer> {
+        let x = if is_tame {
+            Integer::from(&kangaroo.distance - distance)
+        } else {
+            Integer::from(distance - &kangaroo.distance)
+        };
+        (x >= *self.lo && x <= self.hi && self.group.pow(self.b, &x) == *self.a).then_some(x)
+    }
+
+    /// Moves `kangaroo` by the jump selected by its position.
+    fn jump(&self, kangaroo: &mut Kangaroo<G::Element>) {
+        let j = (self.group.encode(&kangaroo.position) % self.jumps.len() as u32)
+            .to_usize()
+            .unwrap();
+        let (jump, distance) = &self.jumps[j];
+        kangaroo.position = self.group.op(&kangaroo.position, jump);
+        kangaroo.distance += distance;
+    }
+
+    /// Returns `true` if `position` is distinguished.
+    fn is_distinguished(&self, position: &G::Element) -> bool {
+        let bits = self.group.encode(position) / self.jumps.len() as u32;
+        (bits & &self.mask) == 0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rug::ops::Pow;
+
+    use super::*;
+    use crate::elliptic_curve::tests::{prime_order_curve, PRIME_ORDER_LOG};
+
+    /// Returns `n = 2**127 - 1`, `a = 3**x (mod n)`, the 40-bit exponent `x` and the interval `[lo, hi]` containing it.
+    fn mersenne_interval() -> (Integer, Integer, Integer, Integer, Integer) {
+        let n = Integer::from(2).pow(127) - 1;
+        let x = Integer::from(0x9d3a51c7e2u64);
+        let a = Integer::from(3).pow_mod(&x, &n).unwrap();
+        (n, a, x, Integer::from(1) << 39, Integer::from(1) << 40)
+    }
+
+    #[test]
+    fn kangaroo() {
+        let n = Integer::from(1000000007);
+        let b = Integer::from(5);
+        for x in [0u64, 200, 123456, 999999, 65536] {
+            let a = b.clone().pow_mod(&x.into(), &n).unwrap();
+            assert_eq!(
+                discrete_log_interval(&n, &a, &b, &0.into(), &1000000.into()).unwrap(),
+                x
+            );
+        }
+
+        // 40-bit exponent modulo a 127-bit prime
+        let (n, a, x, lo, hi) = mersenne_interval();
+        assert_eq!(
+            discrete_log_interval(&n, &a, &3.into(), &lo, &hi).unwrap(),
+            x
+        );
+
+        assert_eq!(
+            discrete_log_interval(&n, &a, &3.into(), &0.into(), &100000.into()),
+            Err(Error::LogDoesNotExist)
+        );
+        assert_eq!(
+            discrete_log_interval(&n, &a, &3.into(), &hi, &lo),
+            Err(Error::InvalidInterval)
+        );
+        assert_eq!(
+            discrete_log_interval(&n, &a, &3.into(), &(-1).into(), &lo),
+            Err(Error::InvalidInterval)
+        );
+        assert_eq!(
+            discrete_log_interval(&11.into(), &7.into(), &31.into(), &0.into(), &20.into()),
+            Err(Error::LogDoesNotExist)
+        );
+    }
+
+    #[test]
+    fn kangaroo_parallel() {
+        let (n, a, x, lo, hi) = mersenne_interval();
+        for threads in [1, 4, 0] {
+            assert_eq!(
+                discrete_log_interval_parallel(&n, &a, &3.into(), &lo, &hi, threads).unwrap(),
+                x
+            );
+        }
+        assert_eq!(
+            discrete_log_interval_parallel(&n, &a, &3.into(), &0.into(), &100000.into(), 4),
+            Err(Error::LogDoesNotExist)
+        );
+        assert_eq!(
+            discrete_log_interval_parallel(&n, &a, &3.into(), &hi, &lo, 4),
+            Err(Error::InvalidInterval)
+        );
+    }
+
+    #[test]
+    fn kangaroo_parallel_shared_table() {
+        // With many threads on a short interval every position is distinguished, so that the kangaroos keep landing
+        // on the positions stored by the other threads and are restarted until a tame and a wild one meet
+        let n = Integer::from(1000000007);
+        let b = Integer::from(5);
+        for x in [0u64, 1, 1234, 2048, 4095, 4096] {
+            let a = b.clone().pow_mod(&x.into(), &n).unwrap();
+            assert_eq!(
+                discrete_log_interval_parallel(&n, &a, &b, &0.into(), &4096.into(), 16).unwrap(),
+                x
+            );
+        }
+        let a = b.clone().pow_mod(&5000.into(), &n).unwrap();
+        assert_eq!(
+            discrete_log_interval_parallel(&n, &a, &b, &0.into(), &4096.into(), 16),
+            Err(Error::LogDoesNotExist)
+        );
+    }
+
+    #[test]
+    fn kangaroo_group() {
+        let (curve, _, g, q) = prime_order_curve();
+        assert_eq!(
+            discrete_log_interval_group(&curve, &q, &g, &131000000.into(), &132000000.into())
+                .unwrap(),
+            PRIME_ORDER_LOG
+        );
+        assert_eq!(
+            discrete_log_interval_parallel_group(
+                &curve,
+                &q,
+                &g,
+                &0.into(),
+                &(1u64 << 32).into(),
+                4
+            )
+            .unwrap(),
+            PRIME_ORDER_LOG
+        );
+    }
+}
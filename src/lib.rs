@@ -2,24 +2,87 @@
 #![deny(rust_2018_idioms)]
 #![warn(missing_docs)]
 
-use std::collections::HashMap;
-
 use n_order::n_order_with_factors;
-use rug::{integer::IsPrime, Integer};
+use rug::{integer::IsPrime, ops::RemRounding, Integer};
+mod anomalous;
+mod binary_field;
+mod certificate;
+mod class_polynomial;
+mod composite;
+pub mod crt;
+mod elliptic_curve;
+mod factorization;
+pub mod factorize;
+mod finite_field;
+mod group;
 mod index_calculus;
+mod kangaroo;
 mod n_order;
+mod pairing;
 mod pohlig_hellman;
+mod point_counting;
 mod pollard_rho;
+mod poly;
+mod prime_power;
 mod shanks_steps;
+mod singular_curve;
+mod siqs;
+mod solution;
 mod trial_mul;
+mod unit_group;
 mod utils;
+mod vector_log;
 
+pub use anomalous::ec_discrete_log_smart;
+pub use binary_field::{discrete_log_binary, discrete_log_binary_index_calculus, BinaryField};
+pub use certificate::{prove_prime, PrimalityCertificate};
+pub use composite::{discrete_log_composite, CompositeDlog};
+pub use elliptic_curve::{
+    ec_discrete_log, ec_discrete_log_report, ec_discrete_log_with_options, Curve, EcDlog,
+    EcDlogMethod, Point,
+};
+pub use factorization::{Factorization, Primality};
+pub use finite_field::{
+    discrete_log_fpk, discrete_log_fpk_with_options, ExtensionField, FieldElement,
+};
+pub use group::{element_order, Group, ModularGroup, NegationMap};
 pub use index_calculus::discrete_log_index_calculus;
+pub use kangaroo::{
+    discrete_log_interval, discrete_log_interval_group, discrete_log_interval_parallel,
+    discrete_log_interval_parallel_group,
+};
 pub use n_order::n_order;
-pub use pohlig_hellman::discrete_log_pohlig_hellman;
-pub use pollard_rho::discrete_log_pollard_rho;
-pub use shanks_steps::discrete_log_shanks_steps;
-pub use trial_mul::discrete_log_trial_mul;
+pub use pairing::{
+    ec_discrete_log_mov, ec_embedding_degree, ec_tate_pairing, ec_weil_pairing, MovReduction,
+    MAX_EMBEDDING_DEGREE,
+};
+pub use pohlig_hellman::{
+    discrete_log_pohlig_hellman, discrete_log_pohlig_hellman_bounded,
+    discrete_log_pohlig_hellman_bounded_group, discrete_log_pohlig_hellman_group,
+    discrete_log_pohlig_hellman_partial, discrete_log_pohlig_hellman_partial_group, PartialDlog,
+};
+pub use point_counting::{
+    ec_group_order, ec_group_order_mestre, ec_group_order_schoof, ec_group_order_with_options,
+    ec_point_order, ec_point_order_with_factors,
+};
+pub use pollard_rho::{
+    discrete_log_pollard_rho, discrete_log_pollard_rho_group, discrete_log_pollard_rho_negation,
+    discrete_log_pollard_rho_parallel, discrete_log_pollard_rho_parallel_group,
+};
+pub use prime_power::discrete_log_prime_power;
+pub use shanks_steps::{discrete_log_shanks_steps, discrete_log_shanks_steps_group};
+pub use singular_curve::ec_discrete_log_singular;
+pub use solution::DlogSolution;
+pub use trial_mul::{discrete_log_trial_mul, discrete_log_trial_mul_group};
+pub use unit_group::{
+    carmichael_lambda, carmichael_lambda_with_factors, euler_phi, euler_phi_with_factors,
+    is_primitive_root, primitive_root, primitive_roots, unit_group_structure,
+    unit_group_structure_with_factors,
+};
+pub use vector_log::{
+    discrete_log_vector, subgroup_basis, unit_group_basis, unit_group_basis_with_factors,
+    UnitGroupBasis,
+};
 
 /// Discrete logarithm error
 #[derive(thiserror::Error, Debug, Clone, PartialEq, Eq)]
@@ -30,25 +93,153 @@ pub enum Error {
     /// A and n are not relatively prime
     #[error("A and n are not relatively prime")]
     NotRelativelyPrime,
+    /// Point is not on the curve
+    #[error("Point is not on the curve")]
+    PointNotOnCurve,
+    /// Curve is singular
+    #[error("Curve is singular")]
+    SingularCurve,
+    /// Embedding degree is too large
+    #[error("Embedding degree is too large")]
+    EmbeddingDegreeTooLarge,
+    /// Field is too large to count the points of the curve
+    #[error("Field is too large to count the points of the curve")]
+    FieldTooLarge,
+    /// Pairing is degenerate
+    #[error("Pairing is degenerate")]
+    DegeneratePairing,
+    /// Modulus is not irreducible
+    #[error("Modulus is not irreducible")]
+    ReducibleModulus,
+    /// Factorization does not match the factored number
+    #[error("Factorization does not match the factored number")]
+    InvalidFactorization,
+    /// Factorization has composite factors
+    #[error("Factorization has composite factors")]
+    IncompleteFactorization,
+    /// Primality could not be proven
+    #[error("Primality could not be proven")]
+    PrimalityNotProven,
+    /// Modulus is not valid (not positive, or not of the required form)
+    #[error("Modulus is not valid")]
+    InvalidModulus,
+    /// Multiplicative group is not cyclic
+    #[error("Multiplicative group is not cyclic")]
+    NotCyclic,
+    /// Interval is not valid (negative lower bound, or lower bound greater than the upper bound)
+    #[error("Interval is not valid")]
+    InvalidInterval,
+}
+
+/// Options of the `*_with_options` solvers.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SolverOptions {
+    /// Require the prime factors of the order of the base to be proven prime (see `prove_prime`) instead of passing a
+    /// probabilistic test, so that the algorithm selection and the Pohlig-Hellman decomposition are proven correct.
+    /// The solvers return `Error::PrimalityNotProven` when a certificate cannot be found.
+    pub require_proven_primality: bool,
+    /// Count the points of curves over fields of more than 128 bits with Schoof's algorithm when the order is not
+    /// given (see `ec_group_order_with_options`), instead of returning `Error::FieldTooLarge`. Without the SEA
+    /// algorithm, this takes minutes at 160 bits and is out of reach at cryptographic sizes.
+    pub allow_slow_point_counting: bool,
 }
 
 /// Compute the discrete logarithm of `a` in base `b` modulo `n` (smallest non-negative integer `x` where `b**x = a (mod n)`).
+///
+/// `b` need not be a unit modulo `n`: its powers are then eventually periodic, and the common factors of `b` and `n`
+/// are peeled off to find the pre-periodic part before solving the problem in the unit group.
 pub fn discrete_log(n: &Integer, a: &Integer, b: &Integer) -> Result<Integer, Error> {
-    discrete_log_with_order(n, a, b, &n_order(b, n)?)
+    if *n > 1 && Integer::from(b.gcd_ref(n)) != 1 {
+        return discrete_log_non_unit(n, a, b);
+    }
+    discrete_log_solution(n, a, b).map(|solution| solution.x0)
+}
+
+/// Compute all the discrete logarithms of `a` in base `b` modulo `n` (integers `x` where `b**x = a (mod n)`), which
+/// are the `x0 + k * period` for the smallest one `x0` and the order `period` of `b`.
+///
+/// `b` must be a unit modulo `n` (`Error::NotRelativelyPrime` otherwise).
+pub fn discrete_log_solution(n: &Integer, a: &Integer, b: &Integer) -> Result<DlogSolution, Error> {
+    if *n > 1 && Integer::from(b.gcd_ref(n)) == 1 {
+        let n_factors = factorize::factor(n);
+        return discrete_log_composite(n, a, b, &n_factors).map(|report| report.solution);
+    }
+    discrete_log_solution_with_order(n, a, b, &n_order(b, n)?)
+}
+
+/// Compute all the discrete logarithms of `a` in base `b` modulo `n` (integers `x` where `b**x = a (mod n)`), which
+/// are the `x0 + k * period` for the smallest one `x0` and the order `period` of `b`.
+///
+/// `order` must be the order of `b`.
+pub fn discrete_log_solution_with_order(
+    n: &Integer,
+    a: &Integer,
+    b: &Integer,
+    order: &Integer,
+) -> Result<DlogSolution, Error> {
+    let x0 = discrete_log_with_order(n, a, b, order)?;
+    let period = if *n == 1 {
+        Integer::from(1)
+    } else {
+        order.clone()
+    };
+    Ok(DlogSolution::new(x0, period))
 }
 
 /// Compute the discrete logarithm of `a` in base `b` modulo `n` (smallest non-negative integer `x` where `b**x = a (mod n)`).
 ///
-/// If the prime factorization of `n` is known, it can be passed as `n_factors` to speed up the computation.
+/// If the prime factorization of `n` is known, it can be passed as `n_factors` to speed up the computation. When `n`
+/// has several prime factors, the problem is split with `discrete_log_composite`.
 pub fn discrete_log_with_factors(
     n: &Integer,
     a: &Integer,
     b: &Integer,
-    n_factors: &HashMap<Integer, usize>,
+    n_factors: &Factorization,
 ) -> Result<Integer, Error> {
+    if *n > 1 && Integer::from(b.gcd_ref(n)) != 1 {
+        return discrete_log_non_unit(n, a, b);
+    }
+    if n_factors.len() > 1 {
+        return discrete_log_composite(n, a, b, n_factors).map(|report| report.solution.x0);
+    }
     discrete_log_with_order(n, a, b, &n_order_with_factors(b, n, n_factors)?)
 }
 
+/// Compute the discrete logarithm of `a` in base `b` modulo `n > 1` for a base `b` which is not a unit modulo `n`.
+///
+/// While `g = gcd(b, n) > 1`, the congruence `b**x = a (mod n)` with `x >= 1` is divided by `g`, which leaves
+/// `c * b**(x - k) = a' (mod n')` after `k` steps, `c` being a unit modulo `n'`. The exponents below `k` are checked on
+/// the way, and the remaining problem is solved in `(Z/n'Z)*`.
+fn discrete_log_non_unit(n: &Integer, a: &Integer, b: &Integer) -> Result<Integer, Error> {
+    let b = b.clone().rem_euc(n);
+    let mut n = n.clone();
+    let mut a = a.clone().rem_euc(&n);
+    let mut c = Integer::from(1);
+    let mut k = 0u32;
+    loop {
+        if Integer::from(&a - &c).is_divisible(&n) {
+            return Ok(Integer::from(k));
+        }
+        let g = Integer::from(b.gcd_ref(&n));
+        if g == 1 {
+            break;
+        }
+        if !a.is_divisible(&g) {
+            return Err(Error::LogDoesNotExist);
+        }
+        n /= &g;
+        a /= &g;
+        c = c * Integer::from(&b / &g) % &n;
+        k += 1;
+    }
+
+    let target = a * c.invert(&n).unwrap() % &n;
+    if Integer::from(target.gcd_ref(&n)) != 1 {
+        return Err(Error::LogDoesNotExist);
+    }
+    Ok(discrete_log(&n, &target, &b)? + k)
+}
+
 /// Compute the discrete logarithm of `a` in base `b` modulo `n` (smallest non-negative integer `x` where `b**x = a (mod n)`).
 ///
 /// If the order of the group is known, it can be passed as `order` to speed up the computation.
@@ -57,6 +248,20 @@ pub fn discrete_log_with_order(
     a: &Integer,
     b: &Integer,
     order: &Integer,
+) -> Result<Integer, Error> {
+    discrete_log_with_order_factors(n, a, b, order, None)
+}
+
+/// Compute the discrete logarithm of `a` in base `b` modulo `n`, `order` being the order of `b`.
+///
+/// If the prime factorization of `order` is known, it is used to tell prime orders apart and for the Pohlig-Hellman
+/// decomposition, instead of a probabilistic primality test and a new factorization.
+pub(crate) fn discrete_log_with_order_factors(
+    n: &Integer,
+    a: &Integer,
+    b: &Integer,
+    order: &Integer,
+    order_factors: Option<&Factorization>,
 ) -> Result<Integer, Error> {
     // Validate input: n should be positive
     if *n < 1 {
@@ -69,7 +274,12 @@ pub fn discrete_log_with_order(
 
     if *order < 1000 {
         discrete_log_trial_mul(n, a, b, Some(order))
-    } else if order.is_probably_prime(100) != IsPrime::No {
+    } else if n.is_perfect_power()
+        && matches!(prime_power::odd_prime_power(n), Some((_, k)) if k > 1)
+    {
+        // The primality test of the root is only run on perfect powers
+        prime_power::discrete_log_prime_power_with_order(n, a, b, order)
+    } else if is_prime_order(order, order_factors) {
         // Shanks and Pollard rho are O(sqrt(order)) while index calculus is O(exp(2*sqrt(log(n)log(log(n)))))
         // we compare the expected running times to determine the algorithm which is expected to be faster
         let n_f64 = n.to_f64();
@@ -86,11 +296,156 @@ pub fn discrete_log_with_order(
         } else {
             discrete_log_pollard_rho(n, a, b, Some(order))
         }
+    } else if let Some(order_factors) = order_factors {
+        pohlig_hellman::pohlig_hellman_with_factors(
+            &ModularGroup::new(n.clone()),
+            &a.clone().rem_euc(n),
+            &b.clone().rem_euc(n),
+            order,
+            order_factors,
+            |aj, bj, pi| discrete_log_with_order(n, aj, bj, pi),
+        )
     } else {
         discrete_log_pohlig_hellman(n, a, b, Some(order))
     }
 }
 
+/// Returns `true` if `order` is prime, according to its prime factorization if it is known and to a probabilistic
+/// test otherwise.
+fn is_prime_order(order: &Integer, order_factors: Option<&Factorization>) -> bool {
+    match order_factors {
+        Some(factors) => factors.len() == 1 && factors.exponent(order) == 1,
+        None => order.is_probably_prime(100) != IsPrime::No,
+    }
+}
+
+/// Compute the discrete logarithm of `a` in base `b` modulo `n` (smallest non-negative integer `x` where `b**x = a (mod n)`).
+///
+/// The solver is configured with `options`. When the primality of the factors of the order must be proven, the
+/// factorization of `n` is certified before computing the order of `b`, which is then made exact with the certified
+/// factorization of the order, and the latter selects the algorithm and drives the Pohlig-Hellman decomposition.
+pub fn discrete_log_with_options(
+    n: &Integer,
+    a: &Integer,
+    b: &Integer,
+    options: &SolverOptions,
+) -> Result<Integer, Error> {
+    if !options.require_proven_primality {
+        return discrete_log_with_order(n, a, b, &n_order(b, n)?);
+    }
+    let mut n_factors = factorize::factor(n);
+    n_factors.certify()?;
+    let multiple = n_order_with_factors(b, n, &n_factors)?;
+    let b = b.clone().rem_euc(n);
+    let (order, order_factors) = order_from_multiple(
+        &ModularGroup::new(n.clone()),
+        &b,
+        factorize::factor(&multiple),
+        options,
+    )?;
+    discrete_log_with_order_factors(n, a, &b, &order, Some(&order_factors))
+}
+
+/// Compute the discrete logarithm of `a` in base `b` in any `group` (smallest non-negative integer `x` where `b**x = a`).
+///
+/// `order` must be the order of `b`, and the solver is configured with `options` (see `discrete_log_with_options`).
+pub fn discrete_log_group_with_options<G: Group>(
+    group: &G,
+    a: &G::Element,
+    b: &G::Element,
+    order: &Integer,
+    options: &SolverOptions,
+) -> Result<Integer, Error> {
+    if options.require_proven_primality {
+        let (order, order_factors) =
+            order_from_multiple(group, b, factorize::factor(order), options)?;
+        discrete_log_group_with_order_factors(group, a, b, &order, Some(&order_factors))
+    } else {
+        discrete_log_group(group, a, b, order)
+    }
+}
+
+/// Returns the order of `b` with its factorization, computed from the factorization `multiple_factors` of a multiple
+/// of the order, whose factors are first proven prime if `options.require_proven_primality` is set
+/// (`Error::IncompleteFactorization` if a factor is composite).
+pub(crate) fn order_from_multiple<G: Group>(
+    group: &G,
+    b: &G::Element,
+    mut multiple_factors: Factorization,
+    options: &SolverOptions,
+) -> Result<(Integer, Factorization), Error> {
+    if options.require_proven_primality {
+        multiple_factors.certify()?;
+    } else if !multiple_factors.is_complete() {
+        return Err(Error::IncompleteFactorization);
+    }
+    let order = element_order(group, b, &multiple_factors);
+    let mut order_factors = Factorization::new();
+    for (q, _, primality) in multiple_factors.iter_with_primality() {
+        let e = order.clone().remove_factor(q).1 as usize;
+        order_factors.insert(q.clone(), e, primality);
+    }
+    Ok((order, order_factors))
+}
+
+/// Compute all the discrete logarithms of `a` in base `b` in any `group` (integers `x` where `b**x = a`), which are the
+/// `x0 + k * order` for the smallest one `x0`.
+///
+/// `order` must be the order of `b`.
+pub fn discrete_log_group_solution<G: Group>(
+    group: &G,
+    a: &G::Element,
+    b: &G::Element,
+    order: &Integer,
+) -> Result<DlogSolution, Error> {
+    let x0 = discrete_log_group(group, a, b, order)?;
+    Ok(DlogSolution::new(x0, order.clone()))
+}
+
+/// Compute the discrete logarithm of `a` in base `b` in any `group` (smallest non-negative integer `x` where `b**x = a`).
+///
+/// `order` must be the order of `b`. The algorithm is selected the same way as in `discrete_log_with_order`, except
+/// that index calculus, which is specific to `(Z/nZ)*`, is never used.
+pub fn discrete_log_group<G: Group>(
+    group: &G,
+    a: &G::Element,
+    b: &G::Element,
+    order: &Integer,
+) -> Result<Integer, Error> {
+    discrete_log_group_with_order_factors(group, a, b, order, None)
+}
+
+/// Compute the discrete logarithm of `a` in base `b` in any `group`, `order` being the order of `b`, with the prime
+/// factorization of `order` if it is known (see `discrete_log_with_order_factors`).
+fn discrete_log_group_with_order_factors<G: Group>(
+    group: &G,
+    a: &G::Element,
+    b: &G::Element,
+    order: &Integer,
+    order_factors: Option<&Factorization>,
+) -> Result<Integer, Error> {
+    if *order < 1000 {
+        discrete_log_trial_mul_group(group, a, b, order)
+    } else if is_prime_order(order, order_factors) {
+        if *order < shanks_steps::MAX_ORDER {
+            discrete_log_shanks_steps_group(group, a, b, order)
+        } else {
+            discrete_log_pollard_rho_group(group, a, b, order)
+        }
+    } else if let Some(order_factors) = order_factors {
+        pohlig_hellman::pohlig_hellman_with_factors(
+            group,
+            a,
+            b,
+            order,
+            order_factors,
+            |aj, bj, pi| discrete_log_group(group, aj, bj, pi),
+        )
+    } else {
+        discrete_log_pohlig_hellman_group(group, a, b, order)
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use std::str::FromStr;
@@ -158,6 +513,109 @@ mod tests {
         );
     }
 
+    #[test]
+    fn discrete_log_non_unit() {
+        assert_eq!(discrete_log(&12.into(), &8.into(), &2.into()).unwrap(), 3);
+        assert_eq!(discrete_log(&36.into(), &0.into(), &6.into()).unwrap(), 2);
+        assert_eq!(
+            discrete_log(&12.into(), &3.into(), &2.into()),
+            Err(Error::LogDoesNotExist)
+        );
+        assert_eq!(
+            discrete_log_with_factors(
+                &12.into(),
+                &4.into(),
+                &2.into(),
+                &factorize::factor(&12.into())
+            )
+            .unwrap(),
+            2
+        );
+        // Smallest exponent found by brute force
+        for n in 2..32u32 {
+            for b in 0..n {
+                for a in 0..n {
+                    let mut power = 1 % n;
+                    let expected = (0..2 * n).find(|_| {
+                        let found = power == a;
+                        power = power * b % n;
+                        found
+                    });
+                    let result = discrete_log(&n.into(), &a.into(), &b.into());
+                    match expected {
+                        Some(x) => {
+                            assert_eq!(result, Ok(Integer::from(x)), "{b}^x = {a} (mod {n})")
+                        }
+                        None => assert!(result.is_err(), "{b}^x = {a} (mod {n})"),
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn discrete_log_solution_() {
+        let solution = discrete_log_solution(&41.into(), &37.into(), &2.into()).unwrap();
+        assert_eq!(solution, DlogSolution::new(12.into(), 20.into()));
+        assert_eq!(
+            solution
+                .solutions_in(&0.into(), &60.into())
+                .collect::<Vec<_>>(),
+            [12, 32, 52]
+        );
+        assert_eq!(
+            discrete_log_solution(&1.into(), &0.into(), &0.into()).unwrap(),
+            DlogSolution::new(0.into(), 1.into())
+        );
+        let group = ModularGroup::new(587.into());
+        assert_eq!(
+            discrete_log_group_solution(&group, &512.into(), &2.into(), &586.into()).unwrap(),
+            DlogSolution::new(9.into(), 586.into())
+        );
+    }
+
+    #[test]
+    fn discrete_log_with_options_() {
+        let options = SolverOptions {
+            require_proven_primality: true,
+            ..Default::default()
+        };
+        assert_eq!(
+            discrete_log_with_options(
+                &Integer::from_str("265390227570863").unwrap(),
+                &Integer::from_str("184500076053622").unwrap(),
+                &2.into(),
+                &options,
+            )
+            .unwrap(),
+            Integer::from_str("17835221372061").unwrap(),
+        );
+        assert_eq!(
+            discrete_log_with_options(
+                &2456747.into(),
+                &(Integer::from(3).pow(51)),
+                &3.into(),
+                &options
+            )
+            .unwrap(),
+            51
+        );
+        // Composite modulus, the order of 3 being computed from the certified factorization of n
+        let n = Integer::from(1000003) * 1000033;
+        let x = Integer::from(123456789);
+        let a = Integer::from(3).pow_mod(&x, &n).unwrap();
+        assert_eq!(
+            discrete_log_with_options(&n, &a, &3.into(), &options).unwrap(),
+            discrete_log(&n, &a, &3.into()).unwrap()
+        );
+        let group = ModularGroup::new(587.into());
+        assert_eq!(
+            discrete_log_group_with_options(&group, &512.into(), &2.into(), &586.into(), &options)
+                .unwrap(),
+            9
+        );
+    }
+
     #[test]
     fn discrete_log_n_equals_one() {
         // Special case: n == 1 should return 0
@@ -1,8 +1,6 @@
-use std::collections::HashMap;
-
 use rug::{ops::Pow, Integer};
 
-use crate::{utils::fast_factor, Error};
+use crate::{factorize::factor, unit_group::totient_factors, Error, Factorization};
 
 /// Returns the order of `a` modulo `n`.
 ///
@@ -28,7 +26,7 @@ pub fn n_order(a: &Integer, n: &Integer) -> Result<Integer, Error> {
         return Err(Error::NotRelativelyPrime);
     }
 
-    let factors = fast_factor(n);
+    let factors = factor(n);
     n_order_with_factors(a, n, &factors)
 }
 
@@ -37,10 +35,11 @@ pub fn n_order(a: &Integer, n: &Integer) -> Result<Integer, Error> {
 /// The order of `a` modulo `n` is the smallest integer `k` such that `a**k` leaves a remainder of 1 with `n`.
 ///
 /// If the prime factorization of `n` is known, it can be passed as `n_factors` to speed up the computation.
+/// Returns `Error::IncompleteFactorization` if a factor of `n` or of `p - 1` for a prime `p` dividing `n` is composite.
 pub fn n_order_with_factors(
     a: &Integer,
     n: &Integer,
-    n_factors: &HashMap<Integer, usize>,
+    n_factors: &Factorization,
 ) -> Result<Integer, Error> {
     // Special case: n == 1, order is always 1
     if *n == 1 {
@@ -62,31 +61,25 @@ pub fn n_order_with_factors(
         return Err(Error::NotRelativelyPrime);
     }
 
-    let mut factors = HashMap::new();
-    for (px, kx) in n_factors.iter() {
-        if *kx > 1 {
-            *factors.entry(px.clone()).or_insert(0) += kx - 1;
-        }
-        let fpx = fast_factor(&(px.clone() - 1));
-        for (py, ky) in fpx.iter() {
-            *factors.entry(py.clone()).or_insert(0) += ky;
-        }
+    if !n_factors.is_complete() {
+        return Err(Error::IncompleteFactorization);
     }
-
-    let mut group_order = Integer::from(1);
-    for (px, kx) in factors.iter() {
-        group_order *= px.clone().pow(*kx as u32);
+    let factors = totient_factors(n_factors);
+    if !factors.is_complete() {
+        return Err(Error::IncompleteFactorization);
     }
 
+    let group_order = factors.value();
+
     let mut order = Integer::from(1);
-    for (p, e) in factors {
+    for (p, e) in factors.iter() {
         let mut exponent = group_order.clone();
         for f in 0..=e {
             if a_mod.clone().pow_mod(&exponent, n).unwrap() != 1 {
                 order *= p.clone().pow((e - f + 1) as u32);
                 break;
             }
-            exponent /= &p;
+            exponent /= p;
         }
     }
 
@@ -98,6 +91,7 @@ mod tests {
     use std::str::FromStr;
 
     use super::*;
+    use crate::Primality;
 
     #[test]
     fn trial_mul() {
@@ -116,7 +110,14 @@ mod tests {
             Err(Error::NotRelativelyPrime)
         );
 
-        assert_eq!(n_order_with_factors(&11.into(), &(Integer::from(10).pow(50) + 151u64).square(), &HashMap::from([(Integer::from(10).pow(50) + 151, 2)])).unwrap(), Integer::from_str("10000000000000000000000000000000000000000000000030100000000000000000000000000000000000000000000022650").unwrap());
+        assert_eq!(n_order_with_factors(&11.into(), &(Integer::from(10).pow(50) + 151u64).square(), &Factorization::from_iter([(Integer::from(10).pow(50) + 151, 2)])).unwrap(), Integer::from_str("10000000000000000000000000000000000000000000000030100000000000000000000000000000000000000000000022650").unwrap());
+
+        let mut incomplete = Factorization::new();
+        incomplete.insert(119.into(), 1, Primality::Composite);
+        assert_eq!(
+            n_order_with_factors(&101.into(), &119.into(), &incomplete),
+            Err(Error::IncompleteFactorization)
+        );
     }
 
     #[test]
@@ -0,0 +1,427 @@
+use rug::{ops::Pow, rand::RandState, Integer};
+
+use crate::{
+    discrete_log_group, discrete_log_with_order, ec_point_order, factorize::factor,
+    pohlig_hellman::pohlig_hellman_with_factors, Curve, Error, ExtensionField, FieldElement, Group,
+    Point,
+};
+
+/// Largest embedding degree handled by `ec_discrete_log_mov`.
+pub const MAX_EMBEDDING_DEGREE: usize = 6;
+
+/// Number of random points tried before a pairing is considered degenerate.
+const PAIRING_ATTEMPTS: usize = 32;
+
+/// Report of a MOV / Frey-Rück reduction, returned by `ec_discrete_log_mov`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MovReduction {
+    /// Discrete logarithm of `q` in base `p`.
+    pub log: Integer,
+    /// Order of `p`, which is also the order of the pairing values.
+    pub order: Integer,
+    /// Embedding degree `k` of `order`: the discrete logarithm was computed in `F_{p^k}*`.
+    pub embedding_degree: usize,
+    /// Size `p**k` of the field the discrete logarithm was transferred to.
+    pub field_size: Integer,
+}
+
+/// Returns the embedding degree of `n` relative to the field of `curve` (smallest `k` such that `n` divides
+/// `p**k - 1`), `None` if it is greater than `max_degree`.
+///
+/// When `n` is the order of a point, the pairings of this point take values in `F_{p^k}`.
+pub fn ec_embedding_degree(curve: &Curve, n: &Integer, max_degree: usize) -> Option<usize> {
+    let base = Integer::from(curve.p() % n);
+    let mut power = base.clone();
+    for k in 1..=max_degree {
+        if Integer::from(&power - 1u32).is_divisible(n) {
+            return Some(k);
+        }
+        power = power * &base % n;
+    }
+    None
+}
+
+/// Returns the value `f(s)` of the Miller function `f` of divisor `n*(point) - n*(O)` at the point `s` of the curve
+/// over `field`, `None` if `s` is a zero or a pole of one of the lines of Miller's loop.
+fn miller(
+    curve: &Curve,
+    field: &ExtensionField,
+    point: &Point,
+    n: &Integer,
+    (xs, ys): (&FieldElement, &FieldElement),
+) -> Option<FieldElement> {
+    let (mut num, mut den) = (field.one(), field.one());
+    let mut t = point.clone();
+    for i in (0..n.significant_bits().saturating_sub(1)).rev() {
+        num = field.square(&num);
+        den = field.square(&den);
+        let (l, v, double) = line(curve, field, &t, &t, (xs, ys));
+        num = field.mul(&num, &l);
+        den = field.mul(&den, &v);
+        t = double;
+        if n.get_bit(i) {
+            let (l, v, sum) = line(curve, field, &t, point, (xs, ys));
+            num = field.mul(&num, &l);
+            den = field.mul(&den, &v);
+            t = sum;
+        }
+    }
+    if num.is_zero() || den.is_zero() {
+        return None;
+    }
+    Some(field.mul(&num, &field.invert(&den)?))
+}
+
+/// Returns the values at `s` of the line through `t1` and `t2` (the tangent if they are equal) and of the vertical
+/// line through `t1 + t2`, together with `t1 + t2`.
+fn line(
+    curve: &Curve,
+    field: &ExtensionField,
+    t1: &Point,
+    t2: &Point,
+    (xs, ys): (&FieldElement, &FieldElement),
+) -> (FieldElement, FieldElement, Point) {
+    let p = curve.p();
+    let sum = curve.add(t1, t2);
+    let (x1, y1) = match (t1, t2) {
+        (Point::Affine(x1, y1), Point::Affine(..)) => (x1, y1),
+        // One of the points is at infinity: the function is constant
+        _ => return (field.one(), field.one(), sum),
+    };
+    let x_diff = field.sub(xs, &field.constant(x1));
+    let (x3, lambda) = match (&sum, t2) {
+        (Point::Infinity, _) => return (x_diff, field.one(), sum),
+        (Point::Affine(x3, _), Point::Affine(x2, y2)) => {
+            let lambda = if x1 == x2 {
+                let num = Integer::from(3) * Integer::from(x1.square_ref()) + curve.a();
+                num * Integer::from(y1 * 2).invert(p).unwrap()
+            } else {
+                Integer::from(y2 - y1) * Integer::from(x2 - x1).invert(p).unwrap()
+            };
+            (x3, lambda)
+        }
+        (Point::Affine(..), Point::Infinity) => unreachable!(),
+    };
+    let l = field.sub(
+        &field.sub(ys, &field.constant(y1)),
+        &field.mul(&field.constant(&lambda), &x_diff),
+    );
+    let v = field.sub(xs, &field.constant(x3));
+    (l, v, sum)
+}
+
+/// Embeds a point of the curve over `F_p` into the curve over `field`, `None` for the point at infinity.
+fn embed(field: &ExtensionField, point: &Point) -> Option<(FieldElement, FieldElement)> {
+    match point {
+        Point::Infinity => None,
+        Point::Affine(x, y) => Some((field.constant(x), field.constant(y))),
+    }
+}
+
+/// Returns a random finite point of the curve over `field`.
+fn random_point(
+    curve: &Curve,
+    field: &ExtensionField,
+    rand_state: &mut RandState<'_>,
+) -> (FieldElement, FieldElement) {
+    loop {
+        let x = field.random(rand_state);
+        let rhs = field.add(
+            &field.mul(
+                &field.add(&field.square(&x), &field.constant(curve.a())),
+                &x,
+            ),
+            &field.constant(curve.b()),
+        );
+        if let Some(y) = field.sqrt(&rhs, rand_state) {
+            return (x, y);
+        }
+    }
+}
+
+/// Compute the Weil pairing `e_n(p, q)` of two `n`-torsion points of `curve` defined over `F_p`.
+///
+/// The pairing is computed with Miller's loop as `(-1)**n * f_p(q) / f_q(p)`, where `f_p` is the function of divisor
+/// `n*(p) - n*(O)`. It is an `n`-th root of unity of `F_p`, which is not `1` only if `p` and `q` are independent (this
+/// requires the whole `n`-torsion to be defined over `F_p`).
+pub fn ec_weil_pairing(curve: &Curve, p: &Point, q: &Point, n: &Integer) -> Integer {
+    let field = ExtensionField::new(curve.p(), 1);
+    let (Some(ps), Some(qs)) = (embed(&field, p), embed(&field, q)) else {
+        return Integer::from(1);
+    };
+    if p == q {
+        return Integer::from(1);
+    }
+    let (Some(fp), Some(fq)) = (
+        miller(curve, &field, p, n, (&qs.0, &qs.1)),
+        miller(curve, &field, q, n, (&ps.0, &ps.1)),
+    ) else {
+        // `q` is a multiple of `p`
+        return Integer::from(1);
+    };
+
+    let pairing = field.mul(&fp, &field.inverse(&fq));
+    let pairing = if n.is_odd() {
+        field.neg(&pairing)
+    } else {
+        pairing
+    };
+    pairing.coefficient(0)
+}
+
+/// Compute the reduced Tate pairing `t_n(p, q) = f_p(q)**((p - 1)/n)` of an `n`-torsion point `p` and a point `q` of
+/// `curve` defined over `F_p`.
+///
+/// `f_p` is the function of divisor `n*(p) - n*(O)`, evaluated on a divisor `(q + r) - (r)` equivalent to `q`. The
+/// pairing is an `n`-th root of unity of `F_p`, so the embedding degree of `n` must be one (`n` divides `p - 1`).
+pub fn ec_tate_pairing(curve: &Curve, p: &Point, q: &Point, n: &Integer) -> Result<Integer, Error> {
+    if ec_embedding_degree(curve, n, 1).is_none() {
+        return Err(Error::EmbeddingDegreeTooLarge);
+    }
+    if *p == Point::Infinity || *q == Point::Infinity {
+        return Ok(Integer::from(1));
+    }
+
+    let field = ExtensionField::new(curve.p(), 1);
+    let exponent = Integer::from(curve.p() - 1u32) / n;
+    let shifts = (0..)
+        .filter_map(|x| curve.lift_x(&Integer::from(x)))
+        .take(PAIRING_ATTEMPTS);
+    for r in shifts {
+        let (Some(qr), Some(rs)) = (embed(&field, &curve.add(q, &r)), embed(&field, &r)) else {
+            continue;
+        };
+        if let (Some(f_qr), Some(f_r)) = (
+            miller(curve, &field, p, n, (&qr.0, &qr.1)),
+            miller(curve, &field, p, n, (&rs.0, &rs.1)),
+        ) {
+            let value = field.mul(&f_qr, &field.inverse(&f_r));
+            return Ok(field.pow(&value, &exponent).coefficient(0));
+        }
+    }
+    Err(Error::DegeneratePairing)
+}
+
+/// Transfers the discrete logarithm of `q` in base `p` (of order `order`, with embedding degree `k`) to `F_{p^k}*`
+/// with the Tate pairing, and solves it there.
+pub(crate) fn mov(
+    curve: &Curve,
+    p: &Point,
+    q: &Point,
+    order: &Integer,
+    k: usize,
+) -> Result<Integer, Error> {
+    if *q == Point::Infinity {
+        return Ok(Integer::ZERO);
+    }
+
+    // The pairing is evaluated at a random point outside of E(F_p), in F_{p^2} at least, whose values lie in F_{p^k}
+    let field = ExtensionField::new(curve.p(), k.max(2));
+    let exponent = field.size() - 1u32;
+    let exponent = exponent / order;
+    let order_factors = factor(order);
+    if !order_factors.is_complete() {
+        return Err(Error::IncompleteFactorization);
+    }
+    let mut rand_state = RandState::new();
+
+    for _ in 0..PAIRING_ATTEMPTS {
+        let (xs, ys) = random_point(curve, &field, &mut rand_state);
+        let (Some(fp), Some(fq)) = (
+            miller(curve, &field, p, order, (&xs, &ys)),
+            miller(curve, &field, q, order, (&xs, &ys)),
+        ) else {
+            continue;
+        };
+        let tp = field.pow(&fp, &exponent);
+        // The pairing value must have order `order` for the logarithm to be unique
+        if order_factors
+            .iter()
+            .any(|(f, _)| field.pow(&tp, &Integer::from(order / f)).is_one())
+        {
+            continue;
+        }
+        let tq = field.pow(&fq, &exponent);
+
+        let log = if k == 1 {
+            discrete_log_with_order(curve.p(), &tq.coefficient(0), &tp.coefficient(0), order)?
+        } else {
+            // The order of the pairing values is known: F_{p^k}* need not be factored
+            pohlig_hellman_with_factors(&field, &tq, &tp, order, &order_factors, |aj, bj, pi| {
+                discrete_log_group(&field, aj, bj, pi)
+            })?
+        };
+        return if curve.mul(p, &log) == *q {
+            Ok(log)
+        } else {
+            Err(Error::LogDoesNotExist)
+        };
+    }
+    Err(Error::DegeneratePairing)
+}
+
+/// Compute the elliptic curve discrete logarithm of `q` in base `p` (smallest non-negative integer `k` where `k*p = q`)
+/// with the MOV / Frey-Rück reduction.
+///
+/// The Tate pairing, computed with Miller's loop, maps the subgroup generated by `p` to the `n`-th roots of unity of
+/// `F_{p^k}`, `n` being the order of `p` and `k` its embedding degree (see `ec_embedding_degree`). The discrete
+/// logarithm is then computed in `F_{p^k}*`: with `discrete_log_with_order` (and thus index calculus when it is
+/// expected to be faster) when `k = 1`, and otherwise with Pohlig-Hellman in the subgroup of order `n` (`p**k - 1`
+/// is not factored, unlike in `discrete_log_fpk`). This is efficient for supersingular
+/// curves (`k <= 2`) and other curves with a small embedding degree, up to `MAX_EMBEDDING_DEGREE`.
+///
+/// If the order of the point `p` is known, it can be passed as `order` to speed up the computation.
+///
+/// The returned report gives the logarithm and how it was computed.
+pub fn ec_discrete_log_mov(
+    curve: &Curve,
+    p: &Point,
+    q: &Point,
+    order: Option<&Integer>,
+) -> Result<MovReduction, Error> {
+    if !curve.contains(p) || !curve.contains(q) {
+        return Err(Error::PointNotOnCurve);
+    }
+    let order = match order {
+        Some(order) => order.clone(),
+        None => ec_point_order(curve, p)?,
+    };
+    let embedding_degree = ec_embedding_degree(curve, &order, MAX_EMBEDDING_DEGREE)
+        .ok_or(Error::EmbeddingDegreeTooLarge)?;
+
+    let log = mov(curve, p, q, &order, embedding_degree)?;
+    Ok(MovReduction {
+        log,
+        field_size: curve.p().clone().pow(embedding_degree as u32),
+        order,
+        embedding_degree,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use super::*;
+    use crate::{ec_discrete_log, ec_discrete_log_report, EcDlogMethod};
+
+    fn int(s: &str) -> Integer {
+        Integer::from_str(s).unwrap()
+    }
+
+    /// `y^2 = x^3 + 8x` over a 91-bit field, with `r^2 * 2034` points, the whole `r`-torsion being rational.
+    fn full_torsion_curve() -> (Curve, Integer, Point, Point) {
+        let curve = Curve::new(int("2458955172339879739145687149"), 8.into(), 0.into());
+        let r = int("1099511640127");
+        let p = curve
+            .point(
+                int("978744333600626092150641770"),
+                int("1815231841637251082658795252"),
+            )
+            .unwrap();
+        let q = curve
+            .point(
+                int("1262380262660514284574601027"),
+                int("2379270326743967562250939131"),
+            )
+            .unwrap();
+        (curve, r, p, q)
+    }
+
+    #[test]
+    fn pairings() {
+        let (curve, r, p, q) = full_torsion_curve();
+        assert_eq!(ec_embedding_degree(&curve, &r, 6), Some(1));
+        assert_eq!(ec_embedding_degree(&curve, &r.clone().square(), 6), None);
+
+        let modulus = curve.p();
+        let (a, b) = (Integer::from(12345), Integer::from(678));
+        let (pa, qb) = (curve.mul(&p, &a), curve.mul(&q, &b));
+        let ab = Integer::from(&a * &b);
+
+        let weil = ec_weil_pairing(&curve, &p, &q, &r);
+        assert_ne!(weil, 1);
+        assert_eq!(weil.clone().pow_mod(&r, modulus).unwrap(), 1);
+        assert_eq!(
+            ec_weil_pairing(&curve, &pa, &qb, &r),
+            weil.pow_mod(&ab, modulus).unwrap()
+        );
+        assert_eq!(ec_weil_pairing(&curve, &p, &pa, &r), 1);
+
+        let tate = ec_tate_pairing(&curve, &p, &q, &r).unwrap();
+        assert_ne!(tate, 1);
+        assert_eq!(
+            ec_tate_pairing(&curve, &pa, &qb, &r).unwrap(),
+            tate.pow_mod(&ab, modulus).unwrap()
+        );
+    }
+
+    #[test]
+    fn mov_embedding_degree_1() {
+        let (curve, r, p, _) = full_torsion_curve();
+        let q = curve
+            .point(
+                int("403077252963510096038978814"),
+                int("1466070261738989630340088277"),
+            )
+            .unwrap();
+        let report = ec_discrete_log_mov(&curve, &p, &q, Some(&r)).unwrap();
+        assert_eq!(report.log, 159121178576u64);
+        assert_eq!(report.embedding_degree, 1);
+        assert_eq!(report.field_size, *curve.p());
+
+        // Large prime order sub-problems of embedding degree 1 are transferred automatically
+        assert_eq!(
+            ec_discrete_log(&curve, &p, &q, Some(&r)).unwrap(),
+            159121178576u64
+        );
+    }
+
+    #[test]
+    fn mov_supersingular() {
+        // y^2 = x^3 + x over p = 3 (mod 4) has p + 1 points, so the embedding degree is 2
+        let curve = Curve::new(1152922071223770983u64.into(), 1.into(), 0.into());
+        let r = Integer::from(68719477741u64);
+        let p = curve
+            .point(919476302575393853u64.into(), 772790895431743898u64.into())
+            .unwrap();
+        let q = curve
+            .point(18229322527036229u64.into(), 1066254985695373314u64.into())
+            .unwrap();
+        let report = ec_discrete_log_mov(&curve, &p, &q, Some(&r)).unwrap();
+        assert_eq!(report.log, 57697068890u64);
+        assert_eq!(report.embedding_degree, 2);
+        assert_eq!(report.field_size, curve.p().clone().square());
+
+        assert_eq!(
+            ec_discrete_log_mov(&Curve::new(97.into(), 2.into(), 3.into()), &p, &q, None),
+            Err(Error::PointNotOnCurve)
+        );
+    }
+
+    #[test]
+    fn mov_supersingular_dispatch() {
+        // p + 1 = 36 * r with r prime above the baby-step giant-step limit, embedding degree 2
+        let curve = Curve::new(36000000001403u64.into(), 1.into(), 0.into());
+        let r = Integer::from(1000000000039u64);
+        let p = curve
+            .point(3255081303023u64.into(), 24107809444307u64.into())
+            .unwrap();
+        let q = curve
+            .point(7498276117078u64.into(), 31934788573041u64.into())
+            .unwrap();
+        let report = ec_discrete_log_report(&curve, &p, &q, Some(&r)).unwrap();
+        assert_eq!(report.log, 734529038271u64);
+        assert_eq!(
+            report.method,
+            EcDlogMethod::PohligHellman {
+                mov: vec![MovReduction {
+                    log: 734529038271u64.into(),
+                    order: r,
+                    embedding_degree: 2,
+                    field_size: curve.p().clone().square(),
+                }]
+            }
+        );
+    }
+}
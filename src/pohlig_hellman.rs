@@ -1,11 +1,23 @@
 use rug::{ops::Pow, Integer};
 
 use crate::{
-    discrete_log_with_order, n_order,
-    utils::{crt, fast_factor},
-    Error,
+    crt::crt, discrete_log_group, discrete_log_interval_group, discrete_log_shanks_steps_group,
+    discrete_log_with_order, factorize::factor, n_order, pollard_rho::pollard_rho_with_budget,
+    shanks_steps, DlogSolution, Error, Factorization, Group, ModularGroup, Primality,
 };
 
+/// Partial discrete logarithm computed by `discrete_log_pohlig_hellman_partial`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PartialDlog {
+    /// Solutions modulo the product of the prime power components of the order which could be solved (`x0` is the
+    /// logarithm modulo this product).
+    pub solution: DlogSolution,
+    /// Prime power components `p**e` of the order whose logarithm is unknown (the order is
+    /// `solution.period * unsolved.value()`), including the factors of the order which could not be split, recorded as
+    /// `Primality::Composite`.
+    pub unsolved: Factorization,
+}
+
 /// Pohlig-Hellman algorithm for computing the discrete logarithm of `a` in base `b` modulo `n` (smallest non-negative integer `x` where `b**x = a (mod n)`).
 ///
 /// In order to compute the discrete logarithm, the algorithm takes advantage of the factorization of the group order. It is more efficient when the group order factors into many small primes.
@@ -24,33 +36,304 @@ pub fn discrete_log_pohlig_hellman(
         None => n_order(&b, n)?,
     };
 
-    let order_factors = fast_factor(&order);
+    pohlig_hellman(
+        &ModularGroup::new(n.clone()),
+        &a,
+        &b,
+        &order,
+        |aj, bj, pi| discrete_log_with_order(n, aj, bj, pi),
+    )
+}
+
+/// Pohlig-Hellman algorithm for computing the discrete logarithm of `a` in base `b` in any `group` (smallest non-negative integer `x` where `b**x = a`).
+///
+/// The sub-problems of prime order are solved with `discrete_log_group`. `order` must be the order of `b`.
+pub fn discrete_log_pohlig_hellman_group<G: Group>(
+    group: &G,
+    a: &G::Element,
+    b: &G::Element,
+    order: &Integer,
+) -> Result<Integer, Error> {
+    pohlig_hellman(group, a, b, order, |aj, bj, pi| {
+        discrete_log_group(group, aj, bj, pi)
+    })
+}
+
+/// Pohlig-Hellman algorithm for computing the discrete logarithm of `a` in base `b` modulo `n` when it is at most
+/// `bound` (smallest non-negative integer `x <= bound` where `b**x = a (mod n)`), for group orders which are smooth
+/// except for some large factors.
+///
+/// If the order of the group is known, it can be passed as `order` to speed up the computation. See
+/// `discrete_log_pohlig_hellman_bounded_group`.
+pub fn discrete_log_pohlig_hellman_bounded(
+    n: &Integer,
+    a: &Integer,
+    b: &Integer,
+    order: Option<&Integer>,
+    bound: &Integer,
+) -> Result<Integer, Error> {
+    let a = a.clone() % n;
+    let b = b.clone() % n;
+    let order = match order {
+        Some(order) => order.clone(),
+        None => n_order(&b, n)?,
+    };
+
+    pohlig_hellman_bounded(
+        &ModularGroup::new(n.clone()),
+        &a,
+        &b,
+        &factor(&order),
+        bound,
+        |aj, bj, pi| discrete_log_with_order(n, aj, bj, pi),
+    )
+}
+
+/// Pohlig-Hellman algorithm for computing the discrete logarithm of `a` in base `b` in any `group` when it is at most
+/// `bound` (smallest non-negative integer `x <= bound` where `b**x = a`), for group orders which are smooth except for
+/// some large factors.
+///
+/// The order of `b` is split into a smooth part `m`, made of the prime factors below 10^12, and
+/// the remaining part. The residue `r` of the logarithm modulo `m` is computed with Pohlig-Hellman, and the logarithm
+/// `x = r + m * t` is then found by searching `t <= (bound - r) / m` in the subgroup generated by `b**m`, with
+/// Baby-Step Giant-Step when this range is small enough and with Pollard's kangaroo (`discrete_log_interval_group`)
+/// otherwise (in which case the logarithm returned is not necessarily the smallest one if several are at most
+/// `bound`). This takes `O(sqrt(bound / m))` operations instead of the `O(sqrt(q))` of Pohlig-Hellman for a large
+/// prime factor `q`. `order` must be the order of `b`.
+pub fn discrete_log_pohlig_hellman_bounded_group<G: Group>(
+    group: &G,
+    a: &G::Element,
+    b: &G::Element,
+    order: &Integer,
+    bound: &Integer,
+) -> Result<Integer, Error> {
+    pohlig_hellman_bounded(group, a, b, &factor(order), bound, |aj, bj, pi| {
+        discrete_log_group(group, aj, bj, pi)
+    })
+}
+
+/// Pohlig-Hellman reduction on the smooth part of `order` followed by a bounded search of the logarithm, using
+/// `solve` to compute the discrete logarithms in the subgroups of prime order.
+///
+/// The factors of `order_factors` which could not be split are left in the remaining part, like the large primes.
+fn pohlig_hellman_bounded<G: Group>(
+    group: &G,
+    a: &G::Element,
+    b: &G::Element,
+    order_factors: &Factorization,
+    bound: &Integer,
+    solve: impl FnMut(&G::Element, &G::Element, &Integer) -> Result<Integer, Error>,
+) -> Result<Integer, Error> {
+    if *bound < 0 {
+        return Err(Error::InvalidInterval);
+    }
+
+    let mut smooth = Factorization::new();
+    let mut rough = Integer::from(1);
+    for (p, e, primality) in order_factors.iter_with_primality() {
+        if primality != Primality::Composite && *p < shanks_steps::MAX_ORDER {
+            smooth.insert(p.clone(), e, primality);
+        } else {
+            rough *= p.clone().pow(e as u32);
+        }
+    }
+    let m = smooth.value();
+    let residue = pohlig_hellman_with_factors(
+        group,
+        &group.pow(a, &rough),
+        &group.pow(b, &rough),
+        &m,
+        &smooth,
+        solve,
+    )?;
+    if residue > *bound {
+        return Err(Error::LogDoesNotExist);
+    }
+
+    // b**(r + m * t) = a, where b**m has order `rough`
+    let steps = Integer::from(bound - &residue) / &m;
+    let steps = steps.min(Integer::from(&rough - 1));
+    let g = group.pow(b, &m);
+    let h = group.op(a, &group.pow(b, &Integer::from(-&residue)));
+    let t = if steps < shanks_steps::MAX_ORDER {
+        discrete_log_shanks_steps_group(group, &h, &g, &Integer::from(&steps + 1))
+            .ok()
+            .filter(|t| *t <= steps)
+            .ok_or(Error::LogDoesNotExist)?
+    } else {
+        discrete_log_interval_group(group, &h, &g, &Integer::new(), &steps)?
+    };
+    Ok(residue + m * t)
+}
+
+/// Pohlig-Hellman algorithm for computing the discrete logarithm of `a` in base `b` modulo `n` (smallest non-negative
+/// integer `x` where `b**x = a (mod n)`) modulo the components of the group order which can be solved within `budget`.
+///
+/// If the order of the group is known, it can be passed as `order` to speed up the computation. See
+/// `discrete_log_pohlig_hellman_partial_group`.
+pub fn discrete_log_pohlig_hellman_partial(
+    n: &Integer,
+    a: &Integer,
+    b: &Integer,
+    order: Option<&Integer>,
+    budget: &Integer,
+) -> Result<PartialDlog, Error> {
+    let a = a.clone() % n;
+    let b = b.clone() % n;
+    let order = match order {
+        Some(order) => order.clone(),
+        None => n_order(&b, n)?,
+    };
+
+    discrete_log_pohlig_hellman_partial_group(&ModularGroup::new(n.clone()), &a, &b, &order, budget)
+}
+
+/// Pohlig-Hellman algorithm for computing the discrete logarithm of `a` in base `b` in any `group` (smallest
+/// non-negative integer `x` where `b**x = a`) modulo the components of the group order which can be solved within
+/// `budget`.
+///
+/// Instead of failing when one of the sub-problems cannot be solved, the logarithm is returned modulo the product of
+/// the prime powers `p**j` dividing the order for which it is known, along with the unsolved remaining part of the
+/// order. `budget` bounds the work spent on each sub-problem of prime order `p`: it is solved with Baby-Step
+/// Giant-Step when `sqrt(p) <= budget` (and `p < 10^12`), and otherwise with Pollard's rho stopped after `budget`
+/// steps. A sub-problem whose budget is exhausted leaves the rest of its component unsolved, while
+/// `Error::LogDoesNotExist` is returned when a sub-problem has no solution. `order` must be the order of `b`.
+pub fn discrete_log_pohlig_hellman_partial_group<G: Group>(
+    group: &G,
+    a: &G::Element,
+    b: &G::Element,
+    order: &Integer,
+    budget: &Integer,
+) -> Result<PartialDlog, Error> {
+    pohlig_hellman_partial(group, a, b, order, &factor(order), budget)
+}
+
+/// Partial Pohlig-Hellman reduction with the factorization of `order` already known, its composite factors (which
+/// could not be split) being left unsolved.
+fn pohlig_hellman_partial<G: Group>(
+    group: &G,
+    a: &G::Element,
+    b: &G::Element,
+    order: &Integer,
+    order_factors: &Factorization,
+    budget: &Integer,
+) -> Result<PartialDlog, Error> {
+    let mut residues = Vec::new();
+    let mut moduli = Vec::new();
+    let mut unsolved = Factorization::new();
+
+    for (pi, ri, primality) in order_factors.iter_with_primality() {
+        let mut residue = Integer::new();
+        let mut solved = 0;
+        if primality != Primality::Composite {
+            let bj = group.pow(b, &(order / pi.clone()));
+            while solved < ri as u32 {
+                let gj = group.pow(b, &residue);
+                let aj = group.pow(
+                    &group.op(a, &group.inverse(&gj)),
+                    &(order / pi.clone().pow(solved + 1)),
+                );
+                let Some(cj) = solve_within_budget(group, &aj, &bj, pi, budget)? else {
+                    break;
+                };
+                residue += cj * pi.clone().pow(solved);
+                solved += 1;
+            }
+        }
+
+        if solved > 0 {
+            residues.push(residue);
+            moduli.push(pi.clone().pow(solved));
+        }
+        if solved < ri as u32 {
+            unsolved.insert(pi.clone(), ri - solved as usize, primality);
+        }
+    }
+
+    let (x0, period) = crt(&residues, &moduli).map_err(|_| Error::LogDoesNotExist)?;
+    Ok(PartialDlog {
+        solution: DlogSolution::new(x0, period),
+        unsolved,
+    })
+}
+
+/// Solves `bj**x = aj` in the subgroup of prime order `p` generated by `bj` within `budget` (see
+/// `discrete_log_pohlig_hellman_partial_group`), `None` if the budget is exhausted.
+fn solve_within_budget<G: Group>(
+    group: &G,
+    aj: &G::Element,
+    bj: &G::Element,
+    p: &Integer,
+    budget: &Integer,
+) -> Result<Option<Integer>, Error> {
+    if group.pow(aj, p) != group.identity() {
+        return Err(Error::LogDoesNotExist);
+    }
+    if *p < shanks_steps::MAX_ORDER && Integer::from(p.sqrt_ref()) <= *budget {
+        return discrete_log_shanks_steps_group(group, aj, bj, p).map(Some);
+    }
+    Ok(pollard_rho_with_budget(
+        group,
+        aj,
+        bj,
+        p,
+        budget
+            .to_u64()
+            .unwrap_or(if *budget < 0 { 0 } else { u64::MAX }),
+    ))
+}
+
+/// Pohlig-Hellman reduction, using `solve` to compute the discrete logarithms in the subgroups of prime order.
+pub(crate) fn pohlig_hellman<G: Group>(
+    group: &G,
+    a: &G::Element,
+    b: &G::Element,
+    order: &Integer,
+    solve: impl FnMut(&G::Element, &G::Element, &Integer) -> Result<Integer, Error>,
+) -> Result<Integer, Error> {
+    pohlig_hellman_with_factors(group, a, b, order, &factor(order), solve)
+}
+
+/// Pohlig-Hellman reduction with the prime factorization of `order` already known.
+///
+/// Returns `Error::IncompleteFactorization` if a factor of `order` is composite.
+pub(crate) fn pohlig_hellman_with_factors<G: Group>(
+    group: &G,
+    a: &G::Element,
+    b: &G::Element,
+    order: &Integer,
+    order_factors: &Factorization,
+    mut solve: impl FnMut(&G::Element, &G::Element, &Integer) -> Result<Integer, Error>,
+) -> Result<Integer, Error> {
+    if !order_factors.is_complete() {
+        return Err(Error::IncompleteFactorization);
+    }
+
     let mut residues = (0..order_factors.len())
         .map(|_| Integer::from(0))
         .collect::<Vec<_>>();
 
     for (i, (pi, ri)) in order_factors.iter().enumerate() {
-        for j in 0..*ri as u32 {
-            let gj = b.clone().pow_mod(&residues[i], n).unwrap();
-            let aj = (&a * gj.clone().invert(n).unwrap())
-                .pow_mod(&(&order / pi.clone().pow(j + 1)), n)
-                .unwrap();
-            let bj = b.clone().pow_mod(&(&order / pi.clone()), n).unwrap();
-            let cj = discrete_log_with_order(n, &aj, &bj, pi)?;
+        let bj = group.pow(b, &(order / pi.clone()));
+        for j in 0..ri as u32 {
+            let gj = group.pow(b, &residues[i]);
+            let aj = group.pow(
+                &group.op(a, &group.inverse(&gj)),
+                &(order / pi.clone().pow(j + 1)),
+            );
+            let cj = solve(&aj, &bj, pi)?;
             residues[i] += &cj * pi.clone().pow(j);
         }
     }
 
     let modulis = order_factors
         .iter()
-        .map(|(pi, ri)| pi.clone().pow(*ri as u32))
+        .map(|(pi, ri)| pi.clone().pow(ri as u32))
         .collect::<Vec<_>>();
 
-    if let Some(d) = crt(&residues, &modulis) {
-        Ok(d)
-    } else {
-        Err(Error::LogDoesNotExist)
-    }
+    crt(&residues, &modulis)
+        .map(|(d, _)| d)
+        .map_err(|_| Error::LogDoesNotExist)
 }
 
 #[cfg(test)]
@@ -59,6 +342,166 @@ mod tests {
 
     use super::*;
 
+    /// Base of the logarithms modulo `smooth_times_prime`, a primitive root.
+    const BASE: u32 = 43;
+
+    /// Returns a prime `p` and a 64-bit prime `q` such that `p - 1 = s * q` with a 40-bit smooth `s`.
+    fn smooth_times_prime() -> (Integer, Integer) {
+        (
+            Integer::from_str_radix("13526934046238577330706888548001", 10).unwrap(),
+            Integer::from(18446744073709552157u128),
+        )
+    }
+
+    #[test]
+    fn pohlig_hellman_partial() {
+        let (p, q) = smooth_times_prime();
+        let order = Integer::from(&p - 1);
+        let s = Integer::from(&order / &q);
+        let b = Integer::from(BASE);
+        let x = Integer::from(0x3243f6a8885a308d3131u128);
+        let a = b.clone().pow_mod(&x, &p).unwrap();
+
+        let partial =
+            discrete_log_pohlig_hellman_partial(&p, &a, &b, Some(&order), &1000.into()).unwrap();
+        assert_eq!(partial.solution, DlogSolution::new(x.clone(), s.clone()));
+        assert_eq!(partial.unsolved.iter().collect::<Vec<_>>(), [(&q, 1)]);
+
+        // With a budget below sqrt(5), 2**5 * 3**4 is solved with Baby-Step Giant-Step and the other sub-problems get
+        // a single step of Pollard's rho
+        let partial =
+            discrete_log_pohlig_hellman_partial(&p, &a, &b, Some(&order), &1.into()).unwrap();
+        assert!(partial.solution.period.is_divisible_u(2592));
+        assert!(partial.solution.contains(&x));
+        assert!(partial.unsolved.iter().any(|(p, _)| *p == q));
+        assert_eq!(partial.solution.period * partial.unsolved.value(), order);
+
+        // The primitive root is not a square: an unsolvable sub-problem is an error, not an unsolved component
+        assert_eq!(
+            discrete_log_pohlig_hellman_partial(
+                &p,
+                &b,
+                &b.clone().square(),
+                Some(&Integer::from(&order / 2)),
+                &1000.into()
+            ),
+            Err(Error::LogDoesNotExist)
+        );
+
+        // Everything is solved when the order is smooth
+        let partial = discrete_log_pohlig_hellman_partial(
+            &98376431.into(),
+            &(Integer::from(11).pow(444)),
+            &11.into(),
+            None,
+            &1000.into(),
+        )
+        .unwrap();
+        assert!(partial.unsolved.is_empty());
+        assert!(partial.solution.contains(&444.into()));
+    }
+
+    #[test]
+    fn pohlig_hellman_incomplete_factorization() {
+        // 120120 = 2^3 * 3 * 5 * 7 * 143, 29 being a primitive root modulo 120121, with 143 = 11 * 13 left unsplit
+        let group = ModularGroup::new(120121.into());
+        let order = Integer::from(120120);
+        let mut factors =
+            Factorization::from_iter([(2.into(), 3), (3.into(), 1), (5.into(), 1), (7.into(), 1)]);
+        factors.insert(143.into(), 1, Primality::Composite);
+        let b = Integer::from(29);
+        let x = Integer::from(12345);
+        let a = b.clone().pow_mod(&x, group.modulus()).unwrap();
+        let solve =
+            |aj: &Integer, bj: &Integer, pi: &Integer| discrete_log_group(&group, aj, bj, pi);
+
+        assert_eq!(
+            pohlig_hellman_with_factors(&group, &a, &b, &order, &factors, solve),
+            Err(Error::IncompleteFactorization)
+        );
+
+        let partial =
+            super::pohlig_hellman_partial(&group, &a, &b, &order, &factors, &1000.into()).unwrap();
+        assert_eq!(partial.solution, DlogSolution::new(x.clone(), 840.into()));
+        assert_eq!(
+            partial.unsolved.iter_with_primality().collect::<Vec<_>>(),
+            [(&143.into(), 1, Primality::Composite)]
+        );
+
+        // The composite factor is part of the remainder searched up to the bound
+        assert_eq!(
+            super::pohlig_hellman_bounded(&group, &a, &b, &factors, &20000.into(), solve),
+            Ok(x)
+        );
+    }
+
+    #[test]
+    fn pohlig_hellman_bounded() {
+        let (p, _) = smooth_times_prime();
+        let order = Integer::from(&p - 1);
+        let b = Integer::from(BASE);
+        for (x, bound) in [
+            // Baby-Step Giant-Step on the remainder
+            (
+                Integer::from(0x2b7e151628aed2a6au128),
+                Integer::from(1) << 70,
+            ),
+            // Pollard's kangaroo on the remainder
+            (
+                Integer::from(0x3243f6a8885a308d3131u128),
+                Integer::from(1) << 82,
+            ),
+        ] {
+            let a = b.clone().pow_mod(&x, &p).unwrap();
+            assert_eq!(
+                discrete_log_pohlig_hellman_bounded(&p, &a, &b, Some(&order), &bound).unwrap(),
+                x
+            );
+        }
+
+        let a = b
+            .clone()
+            .pow_mod(&0x2b7e151628aed2a6au128.into(), &p)
+            .unwrap();
+        assert_eq!(
+            discrete_log_pohlig_hellman_bounded(
+                &p,
+                &a,
+                &b,
+                Some(&order),
+                &(Integer::from(1) << 60)
+            ),
+            Err(Error::LogDoesNotExist)
+        );
+        assert_eq!(
+            discrete_log_pohlig_hellman_bounded(&p, &a, &b, Some(&order), &(-1).into()),
+            Err(Error::InvalidInterval)
+        );
+
+        // Smooth order: the bound only filters the logarithm
+        assert_eq!(
+            discrete_log_pohlig_hellman_bounded(
+                &98376431.into(),
+                &(Integer::from(11).pow(9)),
+                &11.into(),
+                None,
+                &100.into()
+            )
+            .unwrap(),
+            9
+        );
+        assert_eq!(
+            discrete_log_pohlig_hellman_bounded(
+                &98376431.into(),
+                &(Integer::from(11).pow(9)),
+                &11.into(),
+                None,
+                &8.into()
+            ),
+            Err(Error::LogDoesNotExist)
+        );
+    }
+
     #[test]
     fn pollard_rho() {
         assert_eq!(
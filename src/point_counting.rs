@@ -0,0 +1,629 @@
+use std::collections::{HashMap, HashSet};
+
+use primal::Primes;
+use rug::{rand::RandState, Integer};
+
+use crate::{
+    crt::crt,
+    element_order,
+    factorize::factor,
+    poly::{Poly, PolyModulus},
+    Curve, Error, Factorization, Point, SolverOptions,
+};
+
+/// Curves over fields smaller than this are counted point by point.
+const NAIVE_MAX: u32 = 10_000;
+
+/// Curves over fields of at least this many bits are counted with Schoof's algorithm.
+const SCHOOF_MIN_BITS: u32 = 64;
+
+/// Curves over fields of more bits than this are only counted by `ec_group_order_with_options` on request (Schoof's
+/// algorithm takes under a minute at 128 bits, and about four times longer for every 32 more bits).
+const SCHOOF_MAX_BITS: u32 = 128;
+
+/// Points having more multiples than this in the Hasse interval are not used by the baby-step giant-step counting.
+const MAX_MULTIPLES: usize = 64;
+
+/// Returns the number of points of `curve`, including the point at infinity.
+///
+/// Small fields are counted point by point, medium ones with the baby-step giant-step algorithm of Mestre
+/// (`ec_group_order_mestre`) and fields of 64 to 128 bits with Schoof's algorithm (`ec_group_order_schoof`).
+/// Cryptographic sizes are out of reach without the Elkies primes of the SEA algorithm: larger fields return
+/// `Error::FieldTooLarge`, and the order of the points should then be given to the solvers, or Schoof's algorithm
+/// explicitly requested (see `ec_group_order_with_options`).
+pub fn ec_group_order(curve: &Curve) -> Result<Integer, Error> {
+    ec_group_order_with_options(curve, &SolverOptions::default())
+}
+
+/// Returns the number of points of `curve`, including the point at infinity.
+///
+/// The algorithm is selected as in `ec_group_order`, except that fields of more than 128 bits are counted with
+/// Schoof's algorithm when `options.allow_slow_point_counting` is set.
+pub fn ec_group_order_with_options(
+    curve: &Curve,
+    options: &SolverOptions,
+) -> Result<Integer, Error> {
+    if curve.is_singular() {
+        return Err(Error::SingularCurve);
+    }
+    if curve.p().significant_bits() > SCHOOF_MAX_BITS && !options.allow_slow_point_counting {
+        return Err(Error::FieldTooLarge);
+    }
+
+    if *curve.p() < NAIVE_MAX {
+        let p = curve.p().to_u32().unwrap();
+        let mut order = Integer::from(p + 1);
+        for x in 0..p {
+            let rhs = (Integer::from(x * x) + curve.a()) * x + curve.b();
+            order += rhs.legendre(curve.p());
+        }
+        Ok(order)
+    } else if curve.p().significant_bits() < SCHOOF_MIN_BITS {
+        ec_group_order_mestre(curve)
+    } else {
+        ec_group_order_schoof(curve)
+    }
+}
+
+/// Returns the number of points of `curve` with the baby-step giant-step algorithm of Mestre.
+///
+/// Random points of the curve and of its quadratic twist are used to narrow down the multiples of their orders lying
+/// in the Hasse interval `[p + 1 - 2*sqrt(p), p + 1 + 2*sqrt(p)]`, until a single candidate remains.
+/// It runs in `O(p**(1/4))` group operations and memory.
+pub fn ec_group_order_mestre(curve: &Curve) -> Result<Integer, Error> {
+    if curve.is_singular() {
+        return Err(Error::SingularCurve);
+    }
+
+    let p = curve.p();
+    let twist = curve.quadratic_twist();
+    let sum = Integer::from(p * 2) + 2;
+    let bound = Integer::from(p * 4).sqrt();
+    let lo = Integer::from(p + 1) - &bound;
+    let hi = Integer::from(p + 1) + &bound;
+
+    let mut rand_state = RandState::new();
+    let mut candidates: Option<HashSet<Integer>> = None;
+    for i in 0.. {
+        // Alternate between the curve and its twist, whose order is 2*p + 2 - #E
+        let multiples = if i % 2 == 0 {
+            multiples_in_interval(curve, &curve.random_point(&mut rand_state), &lo, &hi)
+        } else {
+            let point = twist.random_point(&mut rand_state);
+            multiples_in_interval(&twist, &point, &lo, &hi)
+                .map(|m| m.into_iter().map(|m| &sum - m).collect())
+        };
+        let Some(multiples) = multiples else {
+            continue;
+        };
+
+        let multiples = match candidates {
+            Some(candidates) => candidates.intersection(&multiples).cloned().collect(),
+            None => multiples,
+        };
+        if multiples.len() == 1 {
+            return Ok(multiples.into_iter().next().unwrap());
+        }
+        candidates = Some(multiples);
+    }
+
+    unreachable!()
+}
+
+/// Returns the integers `m` in `[lo, hi]` such that `m * point` is the point at infinity, or `None` if there are more
+/// than `MAX_MULTIPLES` of them.
+fn multiples_in_interval(
+    curve: &Curve,
+    point: &Point,
+    lo: &Integer,
+    hi: &Integer,
+) -> Option<HashSet<Integer>> {
+    let w: Integer = Integer::from(hi - lo).sqrt() + 1;
+    let w_usize = w.to_usize().unwrap();
+
+    // Baby steps: j * point for 0 <= j < w, indexed by the low bits of their abscissa
+    let mut baby = Vec::with_capacity(w_usize);
+    let mut table: HashMap<u64, Vec<usize>> = HashMap::new();
+    let mut x = Point::Infinity;
+    for j in 0..w_usize {
+        if let Point::Affine(px, _) = &x {
+            table.entry(px.to_u64_wrapping()).or_default().push(j);
+        } else if j > 0 {
+            // The order of the point is too small
+            return None;
+        }
+        baby.push(x.clone());
+        x = curve.add(&x, point);
+    }
+
+    // Giant steps: (lo + i*w) * point = -j * point or j * point
+    let mut multiples = HashSet::new();
+    let step = curve.mul(point, &w);
+    let mut g = curve.mul(point, lo);
+    let mut m = lo.clone();
+    while m <= *hi {
+        let js = match &g {
+            Point::Infinity => vec![0],
+            Point::Affine(gx, _) => table
+                .get(&gx.to_u64_wrapping())
+                .cloned()
+                .unwrap_or_default(),
+        };
+        for j in js {
+            if baby[j] == curve.neg(&g) {
+                multiples.insert(Integer::from(&m + j));
+            }
+            if baby[j] == g {
+                multiples.insert(Integer::from(&m - j));
+            }
+        }
+        if multiples.len() > MAX_MULTIPLES {
+            return None;
+        }
+        g = curve.add(&g, &step);
+        m += &w;
+    }
+
+    Some(
+        multiples
+            .into_iter()
+            .filter(|m| *m >= *lo && *m <= *hi)
+            .collect(),
+    )
+}
+
+/// Returns the number of points of `curve` with Schoof's algorithm.
+///
+/// The trace `t = p + 1 - #E` of the Frobenius endomorphism is computed modulo small primes `l` until their product
+/// exceeds `4*sqrt(p)`, using the characteristic equation `phi**2 - t*phi + p = 0` on the `l`-torsion points.
+/// Those are handled symbolically, modulo the `l`-th division polynomial (or one of its factors).
+/// It runs in polynomial time in `log(p)`, but without the Elkies primes of the SEA algorithm it is only practical up
+/// to about 128 bits: it takes a few seconds at 64 bits, under a minute at 128 bits and a few minutes at 160 bits.
+pub fn ec_group_order_schoof(curve: &Curve) -> Result<Integer, Error> {
+    if curve.is_singular() {
+        return Err(Error::SingularCurve);
+    }
+
+    let p = curve.p();
+    let bound = Integer::from(p * 16).sqrt() + 1;
+
+    // Small primes l whose product exceeds 4*sqrt(p)
+    let mut primes = vec![2u64];
+    let mut product = Integer::from(2);
+    for l in Primes::all().skip(1) {
+        if product > bound {
+            break;
+        }
+        if *p != l {
+            primes.push(l as u64);
+            product *= l;
+        }
+    }
+
+    let division_polynomials = division_polynomials(curve, *primes.last().unwrap() as usize);
+    let f = Poly::from_coeffs(
+        vec![curve.b().clone(), curve.a().clone(), 0.into(), 1.into()],
+        p,
+    );
+
+    let mut residues = Vec::with_capacity(primes.len());
+    for &l in &primes {
+        let t = if l == 2 {
+            // t is even if and only if the curve has a point of order 2, i.e. x**3 + a*x + b has a root in F_p
+            let modulus = PolyModulus::new(&f, p);
+            let xp = modulus.pow(&Poly::x(), p);
+            if xp.sub(&Poly::x(), p).gcd(&f, p).is_one() {
+                1
+            } else {
+                0
+            }
+        } else {
+            trace_mod_l(curve, &f, l, &division_polynomials[l as usize])
+        };
+        residues.push(Integer::from(t));
+    }
+
+    let moduli = primes.iter().map(|&l| Integer::from(l)).collect::<Vec<_>>();
+    let (mut t, _) = crt(&residues, &moduli).unwrap();
+    if Integer::from(&t * 2) > product {
+        t -= &product;
+    }
+    Ok(Integer::from(p + 1) - t)
+}
+
+/// Returns the division polynomials of `curve` up to `n`.
+///
+/// The odd division polynomials are polynomials in `x`, the even ones are `y` times a polynomial in `x`: only this
+/// polynomial is returned, `y**2` being replaced by `x**3 + a*x + b`.
+fn division_polynomials(curve: &Curve, n: usize) -> Vec<Poly> {
+    let p = curve.p();
+    let (a, b) = (curve.a(), curve.b());
+    let f = Poly::from_coeffs(vec![b.clone(), a.clone(), 0.into(), 1.into()], p);
+    let f2 = f.square(p);
+    let a2 = Integer::from(a * a);
+
+    let mut psi = vec![
+        Poly::zero(),
+        Poly::one(),
+        Poly::constant(&2.into(), p),
+        Poly::from_coeffs(
+            vec![
+                -a2.clone(),
+                Integer::from(b * 12),
+                Integer::from(a * 6),
+                0.into(),
+                3.into(),
+            ],
+            p,
+        ),
+        Poly::from_coeffs(
+            vec![
+                Integer::from(b * b) * -32 - Integer::from(&a2 * a) * 4,
+                Integer::from(a * b) * -16,
+                Integer::from(&a2 * -20),
+                Integer::from(b * 80),
+                Integer::from(a * 20),
+                0.into(),
+                4.into(),
+            ],
+            p,
+        ),
+    ];
+
+    let inv2 = Integer::from(2).invert(p).unwrap();
+    for k in 5..=n {
+        let m = k / 2;
+        let poly = if k % 2 == 1 {
+            let t1 = psi[m + 2].mul(&psi[m].square(p).mul(&psi[m], p), p);
+            let t2 = psi[m - 1].mul(&psi[m + 1].square(p).mul(&psi[m + 1], p), p);
+            if m % 2 == 0 {
+                f2.mul(&t1, p).sub(&t2, p)
+            } else {
+                t1.sub(&f2.mul(&t2, p), p)
+            }
+        } else {
+            let t1 = psi[m + 2].mul(&psi[m - 1].square(p), p);
+            let t2 = psi[m - 2].mul(&psi[m + 1].square(p), p);
+            psi[m].mul(&t1.sub(&t2, p), p).scale(&inv2, p)
+        };
+        psi.push(poly);
+    }
+
+    psi
+}
+
+/// Point of the `l`-torsion with coordinates in `F_p[x]/(h)`, in Jacobian coordinates: it represents the point
+/// `(X/Z**2, y*Y/Z**3)`, `(x, y)` being a generic point of the `l`-torsion.
+#[derive(Clone)]
+struct TorsionPoint {
+    x: Poly,
+    y: Poly,
+    z: Poly,
+}
+
+/// Arithmetic on the `l`-torsion points, modulo `h` (a factor of the `l`-th division polynomial).
+struct TorsionRing<'a> {
+    curve: &'a Curve,
+    modulus: PolyModulus,
+    f: Poly,
+}
+
+impl TorsionRing<'_> {
+    fn p(&self) -> &Integer {
+        self.curve.p()
+    }
+
+    /// Returns the affine point `(x, y*y_coeff)` in Jacobian coordinates.
+    fn affine(&self, x: &Poly, y: &Poly) -> TorsionPoint {
+        TorsionPoint {
+            x: x.clone(),
+            y: y.clone(),
+            z: Poly::one(),
+        }
+    }
+
+    /// Returns `2 * point`.
+    fn double(&self, point: &TorsionPoint) -> TorsionPoint {
+        let (p, m) = (self.p(), &self.modulus);
+        let yy = m.mul(&self.f, &m.square(&point.y));
+        let s = m.mul(&point.x, &yy).scale(&4.into(), p);
+        let zz = m.square(&point.z);
+        let mm = m
+            .square(&point.x)
+            .scale(&3.into(), p)
+            .add(&m.square(&zz).scale(self.curve.a(), p), p);
+        let x3 = m.square(&mm).sub(&s.scale(&2.into(), p), p);
+        let y3 = m
+            .mul(&mm, &s.sub(&x3, p))
+            .sub(&m.square(&yy).scale(&8.into(), p), p);
+        // The y coordinate of the result is not a multiple of y: the point is rescaled by y
+        TorsionPoint {
+            x: m.mul(&self.f, &x3),
+            y: m.mul(&self.f, &y3),
+            z: m.mul(&self.f, &m.mul(&point.y, &point.z))
+                .scale(&2.into(), p),
+        }
+    }
+
+    /// Returns `point + (x2, y*y2)`, the two points having different abscissas.
+    fn add_affine(&self, point: &TorsionPoint, x2: &Poly, y2: &Poly) -> TorsionPoint {
+        let (p, m) = (self.p(), &self.modulus);
+        let z1z1 = m.square(&point.z);
+        let u2 = m.mul(x2, &z1z1);
+        let s2 = m.mul(y2, &m.mul(&point.z, &z1z1));
+        let h = u2.sub(&point.x, p);
+        let r = s2.sub(&point.y, p);
+        let hh = m.square(&h);
+        let hhh = m.mul(&h, &hh);
+        let v = m.mul(&point.x, &hh);
+        let x3 = m
+            .mul(&self.f, &m.square(&r))
+            .sub(&hhh, p)
+            .sub(&v.scale(&2.into(), p), p);
+        let y3 = m.mul(&r, &v.sub(&x3, p)).sub(&m.mul(&point.y, &hhh), p);
+        TorsionPoint {
+            x: x3,
+            y: y3,
+            z: m.mul(&point.z, &h),
+        }
+    }
+
+    /// Returns `k * (x, y*y_coeff)`, `k` being positive and smaller than `l`.
+    fn mul(&self, x: &Poly, y: &Poly, k: u64) -> TorsionPoint {
+        let mut result = self.affine(x, y);
+        for i in (0..63 - k.leading_zeros()).rev() {
+            result = self.double(&result);
+            if (k >> i) & 1 == 1 {
+                result = self.add_affine(&result, x, y);
+            }
+        }
+        result
+    }
+
+    /// Returns the difference of the abscissas of two points, up to a unit factor.
+    fn x_diff(&self, p1: &TorsionPoint, p2: &TorsionPoint) -> Poly {
+        let m = &self.modulus;
+        m.mul(&p1.x, &m.square(&p2.z))
+            .sub(&m.mul(&p2.x, &m.square(&p1.z)), self.p())
+    }
+
+    /// Returns the difference of the ordinates of two points, up to a unit factor.
+    fn y_diff(&self, p1: &TorsionPoint, p2: &TorsionPoint) -> Poly {
+        let m = &self.modulus;
+        let z1 = m.mul(&p1.z, &m.square(&p1.z));
+        let z2 = m.mul(&p2.z, &m.square(&p2.z));
+        m.mul(&p1.y, &z2).sub(&m.mul(&p2.y, &z1), self.p())
+    }
+}
+
+/// Returns the trace of the Frobenius endomorphism modulo the odd prime `l`, working modulo `h`, a factor of the
+/// `l`-th division polynomial.
+fn trace_mod_l(curve: &Curve, f: &Poly, l: u64, h: &Poly) -> u64 {
+    let p = curve.p();
+    let ring = TorsionRing {
+        curve,
+        modulus: PolyModulus::new(h, p),
+        f: f.rem(h, p),
+    };
+    let m = &ring.modulus;
+
+    // Frobenius: phi(x, y) = (x**p, y * f**((p - 1)/2)) and phi**2
+    let xp = m.pow(&Poly::x(), p);
+    let yp = m.pow(&ring.f, &(Integer::from(p - 1) >> 1));
+    let xp2 = m.pow(&xp, p);
+    let yp2 = m.mul(&yp, &m.pow(&yp, p));
+    let phi = ring.affine(&xp, &yp);
+    let phi2 = ring.affine(&xp2, &yp2);
+
+    let k = (p.clone() % l).to_u64().unwrap();
+    let kp = ring.mul(&Poly::x(), &Poly::one(), k);
+
+    // A partial common factor means that the relation only holds on a part of the torsion: restrict to it
+    let g = ring.x_diff(&phi2, &kp).gcd(m.modulus(), p);
+    if !g.is_one() && g != *m.modulus() {
+        return trace_mod_l(curve, f, l, &g);
+    }
+
+    if g.is_one() {
+        // Generic case: find t such that phi**2 + k = t * phi
+        let s = ring.add_affine(&kp, &xp2, &yp2);
+        let mut tau_phi = phi.clone();
+        for tau in 1..=(l - 1) / 2 {
+            if tau == 2 {
+                tau_phi = ring.double(&phi);
+            } else if tau > 2 {
+                tau_phi = ring.add_affine(&tau_phi, &xp, &yp);
+            }
+            if ring.x_diff(&s, &tau_phi).is_zero() {
+                return if ring.y_diff(&s, &tau_phi).is_zero() {
+                    tau
+                } else {
+                    l - tau
+                };
+            }
+        }
+        unreachable!("Frobenius trace not found");
+    }
+
+    // phi**2 = -k or phi**2 = k on the whole torsion
+    let y_diff = ring.y_diff(&phi2, &kp);
+    if !y_diff.is_zero() {
+        let g = y_diff.gcd(m.modulus(), p);
+        if !g.is_one() {
+            return trace_mod_l(curve, f, l, &g);
+        }
+        // phi**2 = -k: t = 0
+        return 0;
+    }
+
+    // phi**2 = k: t = 0 if k is not a square modulo l, t = 2w or -2w otherwise, with w**2 = k
+    let Some(w) = (1..l).find(|w| w * w % l == k) else {
+        return 0;
+    };
+    let wp = ring.mul(&Poly::x(), &Poly::one(), w);
+    let g = ring.x_diff(&phi, &wp).gcd(m.modulus(), p);
+    if g.is_one() {
+        return 0;
+    }
+    let sub_ring = TorsionRing {
+        curve,
+        modulus: PolyModulus::new(&g, p),
+        f: f.rem(&g, p),
+    };
+    let reduce = |point: &TorsionPoint| TorsionPoint {
+        x: point.x.rem(&g, p),
+        y: point.y.rem(&g, p),
+        z: point.z.rem(&g, p),
+    };
+    if sub_ring.y_diff(&reduce(&phi), &reduce(&wp)).is_zero() {
+        2 * w % l
+    } else {
+        (l - 2 * w % l) % l
+    }
+}
+
+/// Returns the order of `point` on `curve`.
+///
+/// The order of the group of points is computed with `ec_group_order`.
+pub fn ec_point_order(curve: &Curve, point: &Point) -> Result<Integer, Error> {
+    let group_order = ec_group_order(curve)?;
+    ec_point_order_with_factors(curve, point, &factor(&group_order))
+}
+
+/// Returns the order of `point` on `curve`.
+///
+/// `order_factors` must be the prime factorization of the order of the group of points (or of any multiple of the
+/// order of `point`). Returns `Error::IncompleteFactorization` if a factor is composite.
+pub fn ec_point_order_with_factors(
+    curve: &Curve,
+    point: &Point,
+    order_factors: &Factorization,
+) -> Result<Integer, Error> {
+    if !curve.contains(point) {
+        return Err(Error::PointNotOnCurve);
+    }
+    if !order_factors.is_complete() {
+        return Err(Error::IncompleteFactorization);
+    }
+
+    Ok(element_order(curve, point, order_factors))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use super::*;
+    use crate::elliptic_curve::tests::prime_order_curve;
+
+    #[test]
+    fn group_order() {
+        assert_eq!(
+            ec_group_order(&Curve::new(97.into(), 2.into(), 3.into())).unwrap(),
+            100
+        );
+        assert_eq!(
+            ec_group_order(&Curve::new(97.into(), 0.into(), 0.into())),
+            Err(Error::SingularCurve)
+        );
+
+        let (curve, order, _, _) = prime_order_curve();
+        assert_eq!(ec_group_order(&curve).unwrap(), order);
+        assert_eq!(ec_group_order_schoof(&curve).unwrap(), order);
+        let curve = Curve::new(4294967311u64.into(), 9.into(), 11.into());
+        assert_eq!(ec_group_order_mestre(&curve).unwrap(), 4295025775u64);
+        assert_eq!(ec_group_order_schoof(&curve).unwrap(), 4295025775u64);
+    }
+
+    #[test]
+    fn group_order_small_fields() {
+        for p in [10007u32, 10009, 10037] {
+            for (a, b) in [(1, 1), (0, 5), (7, 0), (-3, 11)] {
+                let curve = Curve::new(p.into(), a.into(), b.into());
+                let mut naive = Integer::from(p + 1);
+                for x in 0..p {
+                    let rhs = (Integer::from(x) * x + curve.a()) * x + curve.b();
+                    naive += rhs.legendre(curve.p());
+                }
+                assert_eq!(ec_group_order_mestre(&curve).unwrap(), naive);
+                assert_eq!(ec_group_order_schoof(&curve).unwrap(), naive);
+            }
+        }
+    }
+
+    #[test]
+    fn group_order_schoof() {
+        // y^2 = x^3 + 7 over a 96-bit field
+        let curve = Curve::new(
+            Integer::from_str("79228162514264337593543950397").unwrap(),
+            0.into(),
+            7.into(),
+        );
+        let order = ec_group_order(&curve).unwrap();
+        let mut rand_state = RandState::new();
+        for _ in 0..5 {
+            let point = curve.random_point(&mut rand_state);
+            assert_eq!(curve.mul(&point, &order), Point::Infinity);
+        }
+    }
+
+    #[test]
+    fn group_order_field_too_large() {
+        let curve = Curve::new(
+            (Integer::from(1) << 128u32).next_prime(),
+            3.into(),
+            7.into(),
+        );
+        assert_eq!(ec_group_order(&curve), Err(Error::FieldTooLarge));
+        assert_eq!(
+            ec_point_order(&curve, &Point::Infinity),
+            Err(Error::FieldTooLarge)
+        );
+    }
+
+    #[test]
+    #[ignore = "takes about a minute"]
+    fn group_order_schoof_128_bits() {
+        // 2^128 - 159 is the largest 128-bit prime
+        let curve = Curve::new((Integer::from(1) << 128u32) - 159, 3.into(), 7.into());
+        let order = ec_group_order(&curve).unwrap();
+        let mut rand_state = RandState::new();
+        for _ in 0..5 {
+            let point = curve.random_point(&mut rand_state);
+            assert_eq!(curve.mul(&point, &order), Point::Infinity);
+        }
+
+        // Above 128 bits on request
+        let curve = Curve::new(
+            (Integer::from(1) << 128u32).next_prime(),
+            3.into(),
+            7.into(),
+        );
+        let options = SolverOptions {
+            allow_slow_point_counting: true,
+            ..Default::default()
+        };
+        let order = ec_group_order_with_options(&curve, &options).unwrap();
+        for _ in 0..5 {
+            let point = curve.random_point(&mut rand_state);
+            assert_eq!(curve.mul(&point, &order), Point::Infinity);
+        }
+    }
+
+    #[test]
+    fn point_order() {
+        let curve = Curve::new(97.into(), 2.into(), 3.into());
+        let point = curve.point(3.into(), 6.into()).unwrap();
+        assert_eq!(ec_point_order(&curve, &point).unwrap(), 5);
+        assert_eq!(
+            ec_point_order_with_factors(
+                &curve,
+                &point,
+                &Factorization::from_iter([(2.into(), 2), (5.into(), 2)])
+            )
+            .unwrap(),
+            5
+        );
+        assert_eq!(ec_point_order(&curve, &Point::Infinity).unwrap(), 1);
+    }
+}
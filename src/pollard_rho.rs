@@ -1,9 +1,31 @@
+use std::{
+    collections::HashMap,
+    sync::{
+        atomic::{AtomicBool, AtomicUsize, Ordering},
+        Mutex,
+    },
+    thread,
+};
+
 use rug::{rand::RandState, Integer};
 
-use crate::{n_order, Error};
+use crate::{n_order, utils::thread_count, Error, Group, ModularGroup, NegationMap};
 
 const RETRIES: usize = 10;
 
+/// Number of partitions of the r-adding walk used with the negation map.
+const PARTITIONS: usize = 64;
+
+/// Number of steps between two fruitless cycle checks of the walk used with the negation map.
+const FRUITLESS_CHECK: u64 = 64;
+
+/// Expected number of distinguished points found by each thread of the parallel walk (`log2`).
+const DISTINGUISHED_POINTS_LOG2: u32 = 5;
+
+/// Length of the walks of the parallel algorithm, in multiples of the mean distance between distinguished points,
+/// after which they are considered trapped in a cycle.
+const MAX_WALK: u32 = 20;
+
 /// Pollard's Rho  algorithm for computing the discrete logarithm of `a` in base `b` modulo `n` (smallest non-negative integer `x` where `b**x = a (mod n)`).
 ///
 /// It is a randomized algorithm with the same expected running time as `discrete_log_shanks_steps`, but requires a negligible amount of memory.
@@ -22,82 +44,324 @@ pub fn discrete_log_pollard_rho(
         None => n_order(&b, n)?,
     };
 
+    discrete_log_pollard_rho_group(&ModularGroup::new(n.clone()), &a, &b, &order)
+}
+
+/// Pollard's Rho algorithm for computing the discrete logarithm of `a` in base `b` in any `group` (smallest non-negative integer `x` where `b**x = a`).
+///
+/// The group is partitioned in three sets using the canonical encoding of its elements (see `Group::encode`).
+/// `order` must be the order of `b`.
+pub fn discrete_log_pollard_rho_group<G: Group>(
+    group: &G,
+    a: &G::Element,
+    b: &G::Element,
+    order: &Integer,
+) -> Result<Integer, Error> {
+    pollard_rho_with_budget(group, a, b, order, u64::MAX).ok_or(Error::LogDoesNotExist)
+}
+
+/// Pollard's Rho algorithm in any `group` (see `discrete_log_pollard_rho_group`), stopped after `budget` steps of the
+/// walks (of three group operations each). Returns `None` if the logarithm was not found within them.
+pub(crate) fn pollard_rho_with_budget<G: Group>(
+    group: &G,
+    a: &G::Element,
+    b: &G::Element,
+    order: &Integer,
+    mut budget: u64,
+) -> Option<Integer> {
     let mut rand_state = RandState::new();
 
-    let order_minus_2 = Integer::from(&order - 2);
+    let order_minus_2 = Integer::from(order - 2);
 
     for _ in 0..RETRIES {
+        if budget == 0 {
+            return None;
+        }
         let mut aa = order_minus_2.clone().random_below(&mut rand_state) + 1;
         let mut ba = order_minus_2.clone().random_below(&mut rand_state) + 1;
-        let mut xa = b.clone().pow_mod(&aa, n).unwrap() * a.clone().pow_mod(&ba, n).unwrap() % n;
-
-        let c = xa.clone() % 3;
-        let mut xb;
-        let mut ab;
-        let mut bb;
-        if c == 0 {
-            xb = a.clone() * &xa % n;
-            ab = aa.clone();
-            bb = (ba.clone() + 1) % &order;
-        } else if c == 1 {
-            xb = xa.clone() * &xa % n;
-            ab = (aa.clone() + &aa) % &order;
-            bb = (ba.clone() + &ba) % &order;
-        } else {
-            xb = b.clone() * &xa % n;
-            ab = (aa.clone() + 1) % &order;
-            bb = ba.clone();
-        }
+        let mut xa = group.op(&group.pow(b, &aa), &group.pow(a, &ba));
+
+        let (mut xb, mut ab, mut bb) = (xa.clone(), aa.clone(), ba.clone());
+        step(group, a, b, order, &mut xb, &mut ab, &mut bb);
 
         for _ in 0..order.to_u32().unwrap_or(u32::MAX) {
-            let c = xa.clone() % 3;
-            if c == 0 {
-                xa = a.clone() * &xa % n;
-                ba = (ba.clone() + 1) % &order;
-            } else if c == 1 {
-                xa = xa.clone() * &xa % n;
-                aa = (aa.clone() + &aa) % &order;
-                ba = (ba.clone() + &ba) % &order;
-            } else {
-                xa = b.clone() * &xa % n;
-                aa = (aa.clone() + 1) % &order;
+            if budget == 0 {
+                return None;
             }
+            budget -= 1;
+            step(group, a, b, order, &mut xa, &mut aa, &mut ba);
+            step(group, a, b, order, &mut xb, &mut ab, &mut bb);
+            step(group, a, b, order, &mut xb, &mut ab, &mut bb);
 
-            let c = xb.clone() % 3;
-            if c == 0 {
-                xb = a.clone() * &xb % n;
-                bb = (bb.clone() + 1) % &order;
-            } else if c == 1 {
-                xb = xb.clone() * &xb % n;
-                ab = (ab.clone() + &ab) % &order;
-                bb = (bb.clone() + &bb) % &order;
-            } else {
-                xb = b.clone() * &xb % n;
-                ab = (ab.clone() + 1) % &order;
+            if xa == xb {
+                let r = (ba.clone() - &bb) % order;
+                if let Ok(i) = r.invert(order) {
+                    let e = (i * (ab.clone() - aa.clone()) % order + order) % order;
+                    if group.pow(b, &e) == *a {
+                        return Some(e);
+                    }
+                }
+                break;
             }
+        }
+    }
 
-            let c = xb.clone() % 3;
-            if c == 0 {
-                xb = a.clone() * &xb % n;
-                bb = (bb.clone() + 1) % &order;
-            } else if c == 1 {
-                xb = xb.clone() * &xb % n;
-                ab = (ab.clone() + &ab) % &order;
-                bb = (bb.clone() + &bb) % &order;
-            } else {
-                xb = b.clone() * &xb % n;
-                ab = (ab.clone() + 1) % &order;
-            }
+    None
+}
 
-            if xa == xb {
-                let r = (ba.clone() - &bb) % &order;
-                if let Ok(i) = r.invert(&order) {
-                    let e = (i * (ab.clone() - aa.clone()) % &order + &order) % &order;
-                    if (b.clone().pow_mod(&e, n).unwrap() - &a) % n == 0 {
+/// Parallel Pollard's Rho algorithm (van Oorschot-Wiener) for computing the discrete logarithm of `a` in base `b`
+/// modulo `n` (smallest non-negative integer `x` where `b**x = a (mod n)`), running `threads` walks at once (`0` for
+/// all the available cores).
+///
+/// If the order of the group is known, it can be passed as `order` to speed up the computation. See
+/// `discrete_log_pollard_rho_parallel_group`.
+pub fn discrete_log_pollard_rho_parallel(
+    n: &Integer,
+    a: &Integer,
+    b: &Integer,
+    order: Option<&Integer>,
+    threads: usize,
+) -> Result<Integer, Error> {
+    let a = a.clone() % n;
+    let b = b.clone() % n;
+    let order = match order {
+        Some(order) => order.clone(),
+        None => n_order(&b, n)?,
+    };
+
+    discrete_log_pollard_rho_parallel_group(&ModularGroup::new(n.clone()), &a, &b, &order, threads)
+}
+
+/// Parallel Pollard's Rho algorithm (van Oorschot-Wiener) for computing the discrete logarithm of `a` in base `b` in any
+/// `group` (smallest non-negative integer `x` where `b**x = a`), running `threads` walks at once (`0` for all the
+/// available cores).
+///
+/// Each thread repeatedly starts an r-adding walk from a random `b**alpha * a**beta` and follows it up to a
+/// distinguished element, which is stored in a table shared by all the threads. Two walks reaching the same
+/// distinguished element give the logarithm, which gives a near-linear speed-up in the number of threads. `order` must
+/// be the order of `b` and should be prime.
+pub fn discrete_log_pollard_rho_parallel_group<G>(
+    group: &G,
+    a: &G::Element,
+    b: &G::Element,
+    order: &Integer,
+    threads: usize,
+) -> Result<Integer, Error>
+where
+    G: Group + Sync,
+    G::Element: Send + Sync,
+{
+    let threads = thread_count(threads);
+    let mut rand_state = RandState::new();
+    let steps = (0..PARTITIONS)
+        .map(|_| {
+            let alpha = order.clone().random_below(&mut rand_state);
+            let beta = order.clone().random_below(&mut rand_state);
+            let x = group.op(&group.pow(b, &alpha), &group.pow(a, &beta));
+            Walker { x, alpha, beta }
+        })
+        .collect::<Vec<_>>();
+
+    // An element is distinguished when the bits of its encoding above the partition are zero modulo 2**d, so that each
+    // thread finds about 2**DISTINGUISHED_POINTS_LOG2 of them before the collision
+    let root = order.clone().sqrt();
+    let d = Integer::from(&root / threads as u32)
+        .significant_bits()
+        .saturating_sub(DISTINGUISHED_POINTS_LOG2);
+    let walk = AddingWalk {
+        group,
+        order,
+        steps,
+        mask: (Integer::from(1) << d) - 1,
+    };
+    let max_walk = Integer::from(1) << d;
+    let max_walk = max_walk * MAX_WALK;
+    let max_steps = (Integer::from(&root * 8) / threads as u32 + &max_walk) * RETRIES as u32;
+
+    // Distinguished elements, with their exponents
+    let distinguished = Mutex::new(HashMap::<G::Element, (Integer, Integer)>::new());
+    let result = Mutex::new(None);
+    let done = AtomicBool::new(false);
+    let failures = AtomicUsize::new(0);
+
+    thread::scope(|scope| {
+        for i in 0..threads {
+            let (walk, distinguished, result, done, failures) =
+                (&walk, &distinguished, &result, &done, &failures);
+            let (max_walk, max_steps) = (&max_walk, &max_steps);
+            scope.spawn(move || {
+                let mut rand_state = RandState::new();
+                rand_state.seed(&Integer::from(i));
+                let mut steps = Integer::new();
+                'walks: while steps < *max_steps && !done.load(Ordering::Relaxed) {
+                    let alpha = order.clone().random_below(&mut rand_state);
+                    let beta = order.clone().random_below(&mut rand_state);
+                    let x = group.op(&group.pow(b, &alpha), &group.pow(a, &beta));
+                    let mut x = Walker { x, alpha, beta };
+
+                    let mut length = Integer::new();
+                    while !walk.is_distinguished(&x.x) {
+                        if length >= *max_walk || done.load(Ordering::Relaxed) {
+                            steps += length;
+                            continue 'walks;
+                        }
+                        x = walk.next(&x);
+                        length += 1;
+                    }
+                    steps += length;
+
+                    let mut distinguished = distinguished.lock().unwrap();
+                    let Some((alpha, beta)) = distinguished.get(&x.x) else {
+                        distinguished.insert(x.x, (x.alpha, x.beta));
+                        continue;
+                    };
+                    let r = Integer::from(&x.beta - beta) % order;
+                    if let Ok(i) = r.invert(order) {
+                        let e = (i * Integer::from(alpha - &x.alpha) % order + order) % order;
+                        if group.pow(b, &e) == *a {
+                            *result.lock().unwrap() = Some(e);
+                            done.store(true, Ordering::Relaxed);
+                            return;
+                        }
+                    }
+                    if failures.fetch_add(1, Ordering::Relaxed) + 1 >= RETRIES {
+                        done.store(true, Ordering::Relaxed);
+                    }
+                }
+            });
+        }
+    });
+
+    result.into_inner().unwrap().ok_or(Error::LogDoesNotExist)
+}
+
+/// r-adding walk of the parallel Pollard's Rho algorithm.
+struct AddingWalk<'a, G: Group> {
+    group: &'a G,
+    order: &'a Integer,
+    steps: Vec<Walker<G::Element>>,
+    mask: Integer,
+}
+
+impl<G: Group> AddingWalk<'_, G> {
+    /// Returns the next element of the walk.
+    fn next(&self, walker: &Walker<G::Element>) -> Walker<G::Element> {
+        let encoding = self.group.encode(&walker.x);
+        let step = &self.steps[(encoding % PARTITIONS as u32).to_usize().unwrap()];
+        Walker {
+            x: self.group.op(&walker.x, &step.x),
+            alpha: Integer::from(&walker.alpha + &step.alpha) % self.order,
+            beta: Integer::from(&walker.beta + &step.beta) % self.order,
+        }
+    }
+
+    /// Returns `true` if `x` is distinguished.
+    fn is_distinguished(&self, x: &G::Element) -> bool {
+        let bits = self.group.encode(x) / PARTITIONS as u32;
+        (bits & &self.mask) == 0
+    }
+}
+
+/// Advances the walk by one step, keeping `x = b**alpha * a**beta` up to date.
+fn step<G: Group>(
+    group: &G,
+    a: &G::Element,
+    b: &G::Element,
+    order: &Integer,
+    x: &mut G::Element,
+    alpha: &mut Integer,
+    beta: &mut Integer,
+) {
+    let c = group.encode(x) % 3;
+    if c == 0 {
+        *x = group.op(a, x);
+        *beta = (beta.clone() + 1) % order;
+    } else if c == 1 {
+        *x = group.op(x, x);
+        *alpha = (alpha.clone() * 2) % order;
+        *beta = (beta.clone() * 2) % order;
+    } else {
+        *x = group.op(b, x);
+        *alpha = (alpha.clone() + 1) % order;
+    }
+}
+
+/// Pollard's Rho algorithm with the negation map for computing the discrete logarithm of `a` in base `b` in a `group`
+/// with cheap inversion (smallest non-negative integer `x` where `b**x = a`).
+///
+/// The random walk is an r-adding walk on the classes `{c, c**-1}` (see `NegationMap`), which gives a `sqrt(2)`
+/// speed-up over `discrete_log_pollard_rho_group`. Fruitless 2-cycles are avoided by skipping to the next partition
+/// when a step would stay in the same one. Longer fruitless cycles are caught by a checkpoint refreshed every
+/// `FRUITLESS_CHECK` steps and escaped by doubling their smallest element, while collisions are detected with Brent's
+/// algorithm, which keeps working across escapes. `order` must be the order of `b` and should be prime.
+pub fn discrete_log_pollard_rho_negation<G: NegationMap>(
+    group: &G,
+    a: &G::Element,
+    b: &G::Element,
+    order: &Integer,
+) -> Result<Integer, Error> {
+    let mut rand_state = RandState::new();
+
+    let order_minus_2 = Integer::from(order - 2);
+
+    'retry: for _ in 0..RETRIES {
+        let mut random = || order_minus_2.clone().random_below(&mut rand_state) + 1;
+        let steps = (0..PARTITIONS)
+            .map(|_| {
+                let (alpha, beta) = (random(), random());
+                let x = group.op(&group.pow(b, &alpha), &group.pow(a, &beta));
+                Walker { x, alpha, beta }
+            })
+            .collect::<Vec<_>>();
+        let walk = NegationWalk {
+            group,
+            order,
+            steps,
+        };
+
+        let (alpha, beta) = (random(), random());
+        let x = group.op(&group.pow(b, &alpha), &group.pow(a, &beta));
+        let mut x = walk.canonical(Walker { x, alpha, beta });
+
+        // Brent's checkpoint, refreshed at powers of two, and fruitless cycle checkpoint
+        let mut saved = x.clone();
+        let mut recent = x.clone();
+        let mut power = 1u64;
+        let mut lambda = 0u64;
+
+        for i in 1..=order.to_u64().unwrap_or(u64::MAX) {
+            x = walk.next(&x);
+
+            for checkpoint in [&saved, &recent] {
+                if x.x != checkpoint.x {
+                    continue;
+                }
+                let r = (x.beta.clone() - &checkpoint.beta) % order;
+                if r == 0 {
+                    if Integer::from(&x.alpha - &checkpoint.alpha).is_divisible(order) {
+                        // Fruitless cycle: leave it and keep walking
+                        x = walk.escape(&x);
+                        break;
+                    }
+                    continue 'retry;
+                }
+                if let Ok(i) = r.invert(order) {
+                    let e = (i * (checkpoint.alpha.clone() - &x.alpha) % order + order) % order;
+                    if group.pow(b, &e) == *a {
                         return Ok(e);
                     }
                 }
-                break;
+                continue 'retry;
+            }
+
+            lambda += 1;
+            if lambda == power {
+                saved = x.clone();
+                power *= 2;
+                lambda = 0;
+            }
+            if i % FRUITLESS_CHECK == 0 {
+                recent = x.clone();
             }
         }
     }
@@ -105,11 +369,92 @@ pub fn discrete_log_pollard_rho(
     Err(Error::LogDoesNotExist)
 }
 
+/// Element `x = b**alpha * a**beta` of a random walk.
+#[derive(Clone)]
+struct Walker<E> {
+    x: E,
+    alpha: Integer,
+    beta: Integer,
+}
+
+/// r-adding walk on the classes `{c, c**-1}` of a group with a negation map.
+struct NegationWalk<'a, G: NegationMap> {
+    group: &'a G,
+    order: &'a Integer,
+    steps: Vec<Walker<G::Element>>,
+}
+
+impl<G: NegationMap> NegationWalk<'_, G> {
+    /// Returns the partition of `x`.
+    fn partition(&self, x: &G::Element) -> usize {
+        (self.group.encode(x) % PARTITIONS as u32)
+            .to_usize()
+            .unwrap()
+    }
+
+    /// Replaces the element of `walker` by the representative of its class.
+    fn canonical(&self, walker: Walker<G::Element>) -> Walker<G::Element> {
+        let (x, negated) = self.group.canonical(&walker.x);
+        if negated {
+            Walker {
+                x,
+                alpha: (self.order - walker.alpha) % self.order,
+                beta: (self.order - walker.beta) % self.order,
+            }
+        } else {
+            walker
+        }
+    }
+
+    /// Returns the next element of the walk.
+    ///
+    /// If the next element falls in the same partition as the current one, the following partitions are tried
+    /// instead, which prevents the fruitless 2-cycles `c -> (c*s)**-1 -> c`.
+    fn next(&self, walker: &Walker<G::Element>) -> Walker<G::Element> {
+        let mut j = self.partition(&walker.x);
+        let mut next = None;
+        for _ in 0..PARTITIONS {
+            let step = &self.steps[j];
+            let candidate = self.canonical(Walker {
+                x: self.group.op(&walker.x, &step.x),
+                alpha: (Integer::from(&walker.alpha + &step.alpha)) % self.order,
+                beta: (Integer::from(&walker.beta + &step.beta)) % self.order,
+            });
+            let same_partition = self.partition(&candidate.x) == j;
+            next = Some(candidate);
+            if !same_partition {
+                break;
+            }
+            j = (j + 1) % PARTITIONS;
+        }
+        next.unwrap()
+    }
+
+    /// Leaves the fruitless cycle containing `walker` by doubling its element with the smallest encoding.
+    fn escape(&self, walker: &Walker<G::Element>) -> Walker<G::Element> {
+        let mut smallest = walker.clone();
+        let mut current = self.next(walker);
+        while current.x != walker.x {
+            if self.group.encode(&current.x) < self.group.encode(&smallest.x) {
+                smallest = current.clone();
+            }
+            current = self.next(&current);
+        }
+
+        self.canonical(Walker {
+            x: self.group.op(&smallest.x, &smallest.x),
+            alpha: Integer::from(&smallest.alpha * 2) % self.order,
+            beta: Integer::from(&smallest.beta * 2) % self.order,
+        })
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use rug::ops::Pow;
 
     use super::*;
+    use crate::elliptic_curve::tests::{prime_order_curve, PRIME_ORDER_LOG};
 
     #[test]
     fn pollard_rho() {
@@ -157,4 +502,49 @@ mod tests {
             Err(Error::LogDoesNotExist)
         );
     }
+
+    #[test]
+    fn pollard_rho_parallel() {
+        // 4 has prime order 1000000289 modulo the safe prime 2000000579
+        let n = Integer::from(2000000579);
+        let order = Integer::from(1000000289);
+        for threads in [1, 4] {
+            let a = Integer::from(4).pow_mod(&123456789.into(), &n).unwrap();
+            assert_eq!(
+                discrete_log_pollard_rho_parallel(&n, &a, &4.into(), Some(&order), threads)
+                    .unwrap(),
+                123456789
+            );
+        }
+        assert_eq!(
+            discrete_log_pollard_rho_parallel(
+                &24567899.into(),
+                &(Integer::from(3).pow(333)),
+                &3.into(),
+                None,
+                0
+            )
+            .unwrap(),
+            333
+        );
+        assert_eq!(
+            discrete_log_pollard_rho_parallel(&11.into(), &7.into(), &31.into(), None, 2),
+            Err(Error::LogDoesNotExist)
+        );
+
+        let (curve, order, g, q) = prime_order_curve();
+        assert_eq!(
+            discrete_log_pollard_rho_parallel_group(&curve, &q, &g, &order, 4).unwrap(),
+            PRIME_ORDER_LOG
+        );
+    }
+
+    #[test]
+    fn pollard_rho_negation() {
+        let (curve, order, g, q) = prime_order_curve();
+        assert_eq!(
+            discrete_log_pollard_rho_negation(&curve, &q, &g, &order).unwrap(),
+            PRIME_ORDER_LOG
+        );
+    }
 }
@@ -0,0 +1,481 @@
+use rug::{integer::Order, ops::RemRounding, rand::RandState, Integer};
+
+/// Polynomials shorter than this are multiplied with the schoolbook method instead of Kronecker substitution.
+const KRONECKER_MIN_LEN: usize = 16;
+
+/// Polynomial with coefficients in `F_p`.
+///
+/// Coefficients are stored from the lowest to the highest degree, reduced modulo `p`, without leading zeros.
+/// The modulus `p` is not stored and must be passed to every operation.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub(crate) struct Poly {
+    coeffs: Vec<Integer>,
+}
+
+impl Poly {
+    /// Returns the zero polynomial.
+    pub fn zero() -> Self {
+        Self { coeffs: Vec::new() }
+    }
+
+    /// Returns the constant polynomial `1`.
+    pub fn one() -> Self {
+        Self {
+            coeffs: vec![Integer::from(1)],
+        }
+    }
+
+    /// Returns the polynomial `x`.
+    pub fn x() -> Self {
+        Self {
+            coeffs: vec![Integer::ZERO, Integer::from(1)],
+        }
+    }
+
+    /// Returns the constant polynomial `c`.
+    pub fn constant(c: &Integer, p: &Integer) -> Self {
+        Self::from_coeffs(vec![c.clone()], p)
+    }
+
+    /// Creates a polynomial from its coefficients (lowest degree first), reducing them modulo `p`.
+    pub fn from_coeffs(coeffs: Vec<Integer>, p: &Integer) -> Self {
+        let mut poly = Self {
+            coeffs: coeffs.into_iter().map(|c| c.rem_euc(p)).collect(),
+        };
+        poly.normalize();
+        poly
+    }
+
+    /// Returns the coefficient of `x**i`.
+    pub fn coeff(&self, i: usize) -> Integer {
+        self.coeffs.get(i).cloned().unwrap_or_default()
+    }
+
+    /// Returns the degree of the polynomial, `None` for the zero polynomial.
+    pub fn degree(&self) -> Option<usize> {
+        self.coeffs.len().checked_sub(1)
+    }
+
+    /// Returns `true` if the polynomial is zero.
+    pub fn is_zero(&self) -> bool {
+        self.coeffs.is_empty()
+    }
+
+    /// Returns `true` if the polynomial is the constant `1`.
+    pub fn is_one(&self) -> bool {
+        self.coeffs.len() == 1 && self.coeffs[0] == 1
+    }
+
+    /// Returns the leading coefficient of the polynomial (zero for the zero polynomial).
+    pub fn leading(&self) -> Integer {
+        self.coeffs.last().cloned().unwrap_or_default()
+    }
+
+    /// Removes the leading zeros.
+    fn normalize(&mut self) {
+        while self.coeffs.last().is_some_and(|c| *c == 0) {
+            self.coeffs.pop();
+        }
+    }
+
+    /// Returns the polynomial truncated modulo `x**n`.
+    fn truncate(&self, n: usize) -> Self {
+        let mut poly = Self {
+            coeffs: self.coeffs.iter().take(n).cloned().collect(),
+        };
+        poly.normalize();
+        poly
+    }
+
+    /// Returns the polynomial with its `n` first coefficients reversed (`x**(n-1) * self(1/x)`).
+    fn reverse(&self, n: usize) -> Self {
+        let mut poly = Self {
+            coeffs: (0..n).rev().map(|i| self.coeff(i)).collect(),
+        };
+        poly.normalize();
+        poly
+    }
+
+    /// Returns `self + other`.
+    pub fn add(&self, other: &Self, p: &Integer) -> Self {
+        let len = self.coeffs.len().max(other.coeffs.len());
+        let mut poly = Self {
+            coeffs: (0..len)
+                .map(|i| {
+                    let c = Integer::from(
+                        self.coeffs.get(i).unwrap_or(&Integer::ZERO)
+                            + other.coeffs.get(i).unwrap_or(&Integer::ZERO),
+                    );
+                    if c >= *p {
+                        c - p
+                    } else {
+                        c
+                    }
+                })
+                .collect(),
+        };
+        poly.normalize();
+        poly
+    }
+
+    /// Returns `-self`.
+    pub fn neg(&self, p: &Integer) -> Self {
+        Self {
+            coeffs: self
+                .coeffs
+                .iter()
+                .map(|c| {
+                    if *c == 0 {
+                        c.clone()
+                    } else {
+                        Integer::from(p - c)
+                    }
+                })
+                .collect(),
+        }
+    }
+
+    /// Returns `self - other`.
+    pub fn sub(&self, other: &Self, p: &Integer) -> Self {
+        self.add(&other.neg(p), p)
+    }
+
+    /// Returns `c * self`.
+    pub fn scale(&self, c: &Integer, p: &Integer) -> Self {
+        let c = c.clone().rem_euc(p);
+        let mut poly = Self {
+            coeffs: self
+                .coeffs
+                .iter()
+                .map(|x| Integer::from(x * &c) % p)
+                .collect(),
+        };
+        poly.normalize();
+        poly
+    }
+
+    /// Returns `self * other`.
+    ///
+    /// Large polynomials are multiplied with Kronecker substitution: both polynomials are packed into integers,
+    /// whose product (computed by GMP) contains the coefficients of the product.
+    pub fn mul(&self, other: &Self, p: &Integer) -> Self {
+        if self.is_zero() || other.is_zero() {
+            return Self::zero();
+        }
+        let len = self.coeffs.len() + other.coeffs.len() - 1;
+        let min_len = self.coeffs.len().min(other.coeffs.len());
+
+        let mut poly = if min_len < KRONECKER_MIN_LEN {
+            let mut coeffs = vec![Integer::ZERO; len];
+            for (i, x) in self.coeffs.iter().enumerate() {
+                for (j, y) in other.coeffs.iter().enumerate() {
+                    coeffs[i + j] += x * y;
+                }
+            }
+            Self {
+                coeffs: coeffs.into_iter().map(|c| c % p).collect(),
+            }
+        } else {
+            let bits = 2 * p.significant_bits() + usize::BITS - min_len.leading_zeros() + 1;
+            let limbs = bits.div_ceil(64) as usize;
+            let product = pack(&self.coeffs, limbs) * pack(&other.coeffs, limbs);
+            let digits = to_digits(&product);
+            Self {
+                coeffs: (0..len)
+                    .map(|i| {
+                        let start = (i * limbs).min(digits.len());
+                        let end = ((i + 1) * limbs).min(digits.len());
+                        Integer::from_digits(&digits[start..end], Order::Lsf) % p
+                    })
+                    .collect(),
+            }
+        };
+        poly.normalize();
+        poly
+    }
+
+    /// Returns `self**2`.
+    pub fn square(&self, p: &Integer) -> Self {
+        self.mul(self, p)
+    }
+
+    /// Returns the quotient and the remainder of the euclidean division of `self` by `other`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `other` is zero.
+    pub fn div_rem(&self, other: &Self, p: &Integer) -> (Self, Self) {
+        let d = other.degree().expect("division by the zero polynomial");
+        let n = match self.degree() {
+            Some(n) if n >= d => n,
+            _ => return (Self::zero(), self.clone()),
+        };
+
+        let inv = other.leading().invert(p).unwrap();
+        let mut rem = self.coeffs.clone();
+        let mut quo = vec![Integer::ZERO; n - d + 1];
+        for i in (0..=n - d).rev() {
+            let q = Integer::from(&rem[i + d] * &inv) % p;
+            if q != 0 {
+                for (j, c) in other.coeffs.iter().enumerate() {
+                    rem[i + j] = Integer::from(&rem[i + j] - &q * c).rem_euc(p);
+                }
+            }
+            quo[i] = q;
+        }
+        rem.truncate(d);
+
+        let mut quo = Self { coeffs: quo };
+        let mut rem = Self { coeffs: rem };
+        quo.normalize();
+        rem.normalize();
+        (quo, rem)
+    }
+
+    /// Returns `self` modulo `other`.
+    pub fn rem(&self, other: &Self, p: &Integer) -> Self {
+        self.div_rem(other, p).1
+    }
+
+    /// Returns the polynomial divided by its leading coefficient.
+    pub fn monic(&self, p: &Integer) -> Self {
+        if self.is_zero() {
+            return Self::zero();
+        }
+        self.scale(&self.leading().invert(p).unwrap(), p)
+    }
+
+    /// Returns the monic greatest common divisor of `self` and `other`.
+    pub fn gcd(&self, other: &Self, p: &Integer) -> Self {
+        let mut a = self.clone();
+        let mut b = other.clone();
+        while !b.is_zero() {
+            let r = a.rem(&b, p);
+            a = b;
+            b = r;
+        }
+        a.monic(p)
+    }
+
+    /// Returns the inverse of `self` modulo `m`, `None` if they are not coprime.
+    pub fn invert(&self, m: &Self, p: &Integer) -> Option<Self> {
+        // Extended Euclidean algorithm, keeping only the coefficients of `self`
+        let (mut a, mut b) = (m.clone(), self.rem(m, p));
+        let (mut u, mut v) = (Self::zero(), Self::one());
+        while !b.is_zero() {
+            let (q, r) = a.div_rem(&b, p);
+            let w = u.sub(&q.mul(&v, p), p);
+            (a, b) = (b, r);
+            (u, v) = (v, w);
+        }
+        if a.degree() != Some(0) {
+            return None;
+        }
+        Some(u.scale(&a.leading().invert(p).unwrap(), p))
+    }
+
+    /// Returns a root of the polynomial in `F_p` (`p` being an odd prime), `None` if it has none.
+    ///
+    /// The product `gcd(self, x**p - x)` of the linear factors is split with random `gcd(g, (x + c)**((p-1)/2) - 1)`
+    /// (Cantor-Zassenhaus) until one of them is isolated.
+    pub fn root(&self, p: &Integer, rand_state: &mut RandState<'_>) -> Option<Integer> {
+        if self.degree()? == 0 {
+            return None;
+        }
+        let modulus = PolyModulus::new(self, p);
+        let frobenius = modulus.pow(&Self::x(), p);
+        let mut g = self.gcd(&frobenius.sub(&Self::x(), p), p);
+        if g.degree()? == 0 {
+            return None;
+        }
+
+        let exponent = Integer::from(p - 1) >> 1;
+        while g.degree() != Some(1) {
+            let c = Integer::from(p.random_below_ref(rand_state));
+            let modulus = PolyModulus::new(&g, p);
+            let power = modulus.pow(&Self::from_coeffs(vec![c, 1.into()], p), &exponent);
+            let h = g.gcd(&power.sub(&Self::one(), p), p);
+            let (deg_h, deg_g) = (h.degree().unwrap(), g.degree().unwrap());
+            if deg_h > 0 && deg_h < deg_g {
+                g = if 2 * deg_h <= deg_g {
+                    h
+                } else {
+                    g.div_rem(&h, p).0
+                };
+            }
+        }
+        Some(Integer::from(-&g.coeffs[0]).rem_euc(p))
+    }
+}
+
+/// Packs the coefficients into an integer, each of them in a slot of `limbs` 64-bit limbs.
+fn pack(coeffs: &[Integer], limbs: usize) -> Integer {
+    let mut digits = vec![0u64; coeffs.len() * limbs];
+    for (i, c) in coeffs.iter().enumerate() {
+        let len = c.significant_digits::<u64>();
+        c.write_digits(&mut digits[i * limbs..i * limbs + len], Order::Lsf);
+    }
+    Integer::from_digits(&digits, Order::Lsf)
+}
+
+/// Returns the 64-bit limbs of a non-negative integer, least significant first.
+fn to_digits(n: &Integer) -> Vec<u64> {
+    let mut digits = vec![0u64; n.significant_digits::<u64>()];
+    n.write_digits(&mut digits, Order::Lsf);
+    digits
+}
+
+/// Polynomial modulus with a precomputed inverse, for fast reductions (Barrett reduction).
+#[derive(Debug, Clone)]
+pub(crate) struct PolyModulus {
+    p: Integer,
+    m: Poly,
+    /// Inverse of the reversed modulus, modulo `x**deg(m)`.
+    inv: Poly,
+}
+
+impl PolyModulus {
+    /// Creates the modulus `m` over `F_p`. `m` must be of degree at least 1.
+    pub fn new(m: &Poly, p: &Integer) -> Self {
+        let m = m.monic(p);
+        let d = m.degree().expect("modulus must not be zero");
+
+        // Newton iteration: g <- g * (2 - rev(m) * g) mod x**k
+        let rev = m.reverse(d + 1);
+        let mut inv = Poly::one();
+        let mut k = 1;
+        while k < d {
+            k = (2 * k).min(d);
+            let e = Poly::constant(&2.into(), p).sub(&rev.truncate(k).mul(&inv, p).truncate(k), p);
+            inv = inv.mul(&e, p).truncate(k);
+        }
+
+        Self {
+            p: p.clone(),
+            m,
+            inv,
+        }
+    }
+
+    /// Returns the (monic) modulus polynomial.
+    pub fn modulus(&self) -> &Poly {
+        &self.m
+    }
+
+    /// Returns the degree of the modulus.
+    pub fn degree(&self) -> usize {
+        self.m.coeffs.len() - 1
+    }
+
+    /// Returns `a` reduced modulo the modulus.
+    pub fn reduce(&self, a: &Poly) -> Poly {
+        let d = self.degree();
+        let n = a.coeffs.len();
+        if n <= d {
+            return a.clone();
+        }
+        let k = n - d;
+        if k > d {
+            return a.rem(&self.m, &self.p);
+        }
+
+        let q = a
+            .reverse(n)
+            .truncate(k)
+            .mul(&self.inv.truncate(k), &self.p)
+            .truncate(k)
+            .reverse(k);
+        a.sub(&q.mul(&self.m, &self.p), &self.p)
+    }
+
+    /// Returns `a * b` modulo the modulus.
+    pub fn mul(&self, a: &Poly, b: &Poly) -> Poly {
+        self.reduce(&a.mul(b, &self.p))
+    }
+
+    /// Returns `a**2` modulo the modulus.
+    pub fn square(&self, a: &Poly) -> Poly {
+        self.reduce(&a.square(&self.p))
+    }
+
+    /// Returns `a**e` modulo the modulus, `e` being non-negative.
+    pub fn pow(&self, a: &Poly, e: &Integer) -> Poly {
+        let a = self.reduce(a);
+        let mut result = Poly::one();
+        for i in (0..e.significant_bits()).rev() {
+            result = self.square(&result);
+            if e.get_bit(i) {
+                result = self.mul(&result, &a);
+            }
+        }
+        self.reduce(&result)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn poly(coeffs: &[i64], p: &Integer) -> Poly {
+        Poly::from_coeffs(coeffs.iter().map(|&c| Integer::from(c)).collect(), p)
+    }
+
+    #[test]
+    fn arithmetic() {
+        let p = Integer::from(101);
+        let a = poly(&[1, 2, 3], &p);
+        let b = poly(&[100, 1], &p);
+        assert_eq!(a.add(&b, &p), poly(&[0, 3, 3], &p));
+        assert_eq!(a.sub(&a, &p), Poly::zero());
+        assert_eq!(a.mul(&b, &p), poly(&[-1, -1, -1, 3], &p));
+        assert_eq!(a.mul(&b, &p).div_rem(&b, &p), (a.clone(), Poly::zero()));
+        // (x - 1)(x + 2) and (x - 1)(x + 3)
+        assert_eq!(
+            poly(&[-2, 1, 1], &p).gcd(&poly(&[-3, 2, 1], &p), &p),
+            poly(&[-1, 1], &p)
+        );
+
+        let m = poly(&[3, 0, 1], &p);
+        let inv = a.invert(&m, &p).unwrap();
+        assert!(a.mul(&inv, &p).rem(&m, &p).is_one());
+        assert_eq!(poly(&[-1, 1], &p).invert(&poly(&[-1, 0, 1], &p), &p), None);
+    }
+
+    #[test]
+    fn root() {
+        let p = Integer::from(1_000_000_007);
+        let mut rand_state = RandState::new();
+        // (x - 3)(x - 5)(x - 7)(x**2 + 1), x**2 + 1 being irreducible modulo p = 3 (mod 4)
+        let f = poly(&[-105, 71, -15, 1], &p).mul(&poly(&[1, 0, 1], &p), &p);
+        for _ in 0..10 {
+            let r = f.root(&p, &mut rand_state).unwrap();
+            assert!([3, 5, 7].contains(&r.to_i32().unwrap()));
+        }
+        assert_eq!(poly(&[1, 0, 1], &p).root(&p, &mut rand_state), None);
+        assert_eq!(poly(&[4], &p).root(&p, &mut rand_state), None);
+    }
+
+    #[test]
+    fn kronecker_and_barrett() {
+        let p = Integer::from(1_000_000_007);
+        let a = Poly::from_coeffs((0..100).map(|i| Integer::from(i * i + 7)).collect(), &p);
+        let b = Poly::from_coeffs((0..80).map(|i| Integer::from(3 * i + 1)).collect(), &p);
+
+        let mut schoolbook = vec![Integer::ZERO; 179];
+        for (i, x) in a.coeffs.iter().enumerate() {
+            for (j, y) in b.coeffs.iter().enumerate() {
+                schoolbook[i + j] += x * y;
+            }
+        }
+        assert_eq!(a.mul(&b, &p), Poly::from_coeffs(schoolbook, &p));
+
+        let modulus = PolyModulus::new(&b, &p);
+        let c = a.mul(&a, &p);
+        assert_eq!(modulus.reduce(&c), c.rem(&b.monic(&p), &p));
+        assert_eq!(
+            modulus.pow(&Poly::x(), &1000.into()),
+            Poly::from_coeffs((0..=1000).map(|i| Integer::from(i / 1000)).collect(), &p)
+                .rem(&b, &p)
+        );
+    }
+}
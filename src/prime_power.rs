@@ -0,0 +1,199 @@
+use rug::{ops::Pow, ops::RemRounding, Integer};
+
+use crate::{
+    discrete_log_with_order_factors,
+    factorize::{factor, is_prime, perfect_power},
+    order_from_multiple, DlogSolution, Error, Factorization, ModularGroup, SolverOptions,
+};
+
+/// Returns `(p, k)` if `n = p**k` for an odd prime `p` and `k >= 1`.
+pub(crate) fn odd_prime_power(n: &Integer) -> Option<(Integer, u32)> {
+    let (mut p, mut k) = (n.clone(), 1);
+    while let Some((root, e)) = perfect_power(&p) {
+        p = root;
+        k *= e as u32;
+    }
+    (p.is_odd() && is_prime(&p)).then_some((p, k))
+}
+
+/// Returns the p-adic logarithm of `z = 1 (mod p)` divided by `p`, modulo `p**(k-1)`.
+///
+/// For `p` odd, `z**(p**(k-1)) = exp(p**(k-1) * log(z)) = 1 + p**(k-1) * log(z) (mod p**(2k))`.
+fn padic_log(z: &Integer, p: &Integer, k: u32) -> Integer {
+    let pk = p.clone().pow(k);
+    let p2k = Integer::from(pk.square_ref());
+    let power = z.clone().pow_mod(&p.clone().pow(k - 1), &p2k).unwrap();
+    (power - 1u32) / pk % p.clone().pow(k - 1)
+}
+
+/// Compute the discrete logarithm of `a` in base `b` modulo `n = p**k` for an odd prime `p` (smallest non-negative
+/// integer `x` where `b**x = a (mod n)`).
+///
+/// The logarithm is computed modulo `p` with `discrete_log`, which gives `x` modulo the order of `b` modulo `p`. The
+/// rest of `x`, modulo a power of `p`, comes from the p-adic logarithms of `a**(p-1)` and `b**(p-1)` (Bach's method),
+/// in polynomial time instead of one Pohlig-Hellman sub-problem per base `p` digit.
+///
+/// Returns `Error::InvalidModulus` if `n` is not a power of an odd prime, and `Error::NotRelativelyPrime` if `b` is not
+/// a unit modulo `n`. Powers of 2 are left to Pohlig-Hellman: the orders modulo `2**k` are powers of 2, which makes
+/// its sub-problems of order 2 trivial.
+pub fn discrete_log_prime_power(n: &Integer, a: &Integer, b: &Integer) -> Result<Integer, Error> {
+    let (p, k) = odd_prime_power(n).ok_or(Error::InvalidModulus)?;
+    prime_power_log(&p, k, a, b, &factor(&(p.clone() - 1))).map(|solution| solution.x0)
+}
+
+/// `discrete_log_prime_power` when a multiple `order` of the order of `b` modulo `n` is known: the order of `b` modulo
+/// `p` is then computed from the factorization of its part prime to `p`, instead of that of `p - 1`.
+pub(crate) fn discrete_log_prime_power_with_order(
+    n: &Integer,
+    a: &Integer,
+    b: &Integer,
+    order: &Integer,
+) -> Result<Integer, Error> {
+    let (p, k) = odd_prime_power(n).ok_or(Error::InvalidModulus)?;
+    let mut multiple = order.clone();
+    multiple.remove_factor_mut(&p);
+    prime_power_log(&p, k, a, b, &factor(&multiple)).map(|solution| solution.x0)
+}
+
+/// Compute all the discrete logarithms of `a` in base `b` modulo `p**k` for an odd prime `p` (see
+/// `discrete_log_prime_power`).
+///
+/// `multiple_factors` must be the prime factorization of a multiple of the order of `b` modulo `p`, such as `p - 1`:
+/// the exact order is computed from it, for the logarithm to be the smallest one, and it is used for the
+/// Pohlig-Hellman decomposition modulo `p`.
+pub(crate) fn prime_power_log(
+    p: &Integer,
+    k: u32,
+    a: &Integer,
+    b: &Integer,
+    multiple_factors: &Factorization,
+) -> Result<DlogSolution, Error> {
+    if b.is_divisible(p) {
+        return Err(Error::NotRelativelyPrime);
+    }
+    if a.is_divisible(p) {
+        return Err(Error::LogDoesNotExist);
+    }
+
+    let (a_p, b_p) = (a.clone().rem_euc(p), b.clone().rem_euc(p));
+    let (order_p, order_p_factors) = order_from_multiple(
+        &ModularGroup::new(p.clone()),
+        &b_p,
+        multiple_factors.clone(),
+        &SolverOptions::default(),
+    )?;
+    let modular = DlogSolution::new(
+        discrete_log_with_order_factors(p, &a_p, &b_p, &order_p, Some(&order_p_factors))?,
+        order_p,
+    );
+    if k == 1 {
+        return Ok(modular);
+    }
+
+    // a = b**x (mod p**k) if and only if a = b**x (mod p) and a**(p-1) = b**((p-1)*x) (mod p**k), which lie in the
+    // subgroup 1 + pZ, isomorphic to Z/p^(k-1)Z through the p-adic logarithm
+    let p2k = p.clone().pow(2 * k);
+    let log_of = |x: &Integer| {
+        let z = x.clone().pow_mod(&Integer::from(p - 1), &p2k).unwrap();
+        padic_log(&z, p, k)
+    };
+    let (mut log_a, mut log_b) = (log_of(a), log_of(b));
+    let mut modulus = p.clone().pow(k - 1);
+    // Solve x * log_b = log_a (mod p**(k-1))
+    while log_b.is_divisible(p) && modulus > 1 {
+        if !log_a.is_divisible(p) {
+            return Err(Error::LogDoesNotExist);
+        }
+        log_a /= p;
+        log_b /= p;
+        modulus /= p;
+    }
+    if modulus == 1 {
+        return Ok(modular);
+    }
+    let x = log_a * log_b.invert(&modulus).unwrap() % &modulus;
+    Ok(modular.intersect(&DlogSolution::new(x, modulus)).unwrap())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use super::*;
+    use crate::{discrete_log_pohlig_hellman, discrete_log_with_order};
+
+    #[test]
+    fn prime_power() {
+        assert_eq!(odd_prime_power(&81.into()), Some((3.into(), 4)));
+        assert_eq!(odd_prime_power(&7.into()), Some((7.into(), 1)));
+        assert_eq!(odd_prime_power(&64.into()), None);
+        assert_eq!(odd_prime_power(&45.into()), None);
+        assert_eq!(
+            discrete_log_prime_power(&45.into(), &2.into(), &2.into()),
+            Err(Error::InvalidModulus)
+        );
+        assert_eq!(
+            discrete_log_prime_power(&27.into(), &2.into(), &3.into()),
+            Err(Error::NotRelativelyPrime)
+        );
+
+        // Smallest exponent found by brute force
+        for n in [9u32, 25, 27, 49, 81, 125] {
+            for b in 1..n {
+                let b = Integer::from(b);
+                if Integer::from(b.gcd_ref(&n.into())) != 1 {
+                    continue;
+                }
+                let mut power = Integer::from(1);
+                let mut expected = std::collections::HashMap::new();
+                for x in 0..n {
+                    expected.entry(power.clone()).or_insert(x);
+                    power = power * &b % n;
+                }
+                for a in (1..n).filter(|a| Integer::from(*a).gcd(&n.into()) == 1) {
+                    let result = discrete_log_prime_power(&n.into(), &a.into(), &b);
+                    match expected.get(&Integer::from(a)) {
+                        Some(x) => assert_eq!(result, Ok(Integer::from(*x))),
+                        None => assert_eq!(result, Err(Error::LogDoesNotExist)),
+                    }
+                }
+            }
+        }
+
+        // 1000003^5, where the order of 2 is divisible by 1000003^4
+        let n = Integer::from(1000003).pow(5);
+        let x = Integer::from_str("123456789012345678901234567890").unwrap();
+        let a = Integer::from(2).pow_mod(&x, &n).unwrap();
+        let log = discrete_log_prime_power(&n, &a, &2.into()).unwrap();
+        assert_eq!(Integer::from(2).pow_mod(&log, &n).unwrap(), a);
+        assert_eq!(
+            log,
+            discrete_log_pohlig_hellman(&n, &a, &2.into(), None).unwrap()
+        );
+
+        // p - 1 = 2 * 73 * 101 * Q for a 256-bit semiprime Q, and b of order 101 modulo p: the order given to
+        // discrete_log_with_order is used instead of factoring p - 1
+        let p = Integer::from_str(
+            "1077259219233662626425903474859493873105039740494906394318656001063223427454383419",
+        )
+        .unwrap();
+        let n = Integer::from(p.square_ref());
+        let b = Integer::from_str(
+            "984642071236520457333815784128805836967237296296673193186651400872927952107172879",
+        )
+        .unwrap();
+        let order = Integer::from(&p * 101);
+        let x = Integer::from_str("12345678901234567890123456789012345678901234567890").unwrap();
+        let a = b.clone().pow_mod(&x, &n).unwrap();
+        assert_eq!(discrete_log_with_order(&n, &a, &b, &order), Ok(x));
+
+        // 2058 = 2 * 3 * 7^3 is a multiple of the order 1029 of 2 modulo 7^4
+        for x in [100, 1000] {
+            let a = Integer::from(2).pow_mod(&x.into(), &2401.into()).unwrap();
+            assert_eq!(
+                discrete_log_with_order(&2401.into(), &a, &2.into(), &2058.into()),
+                Ok(x.into())
+            );
+        }
+    }
+}
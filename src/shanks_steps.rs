@@ -2,7 +2,7 @@ use std::collections::HashMap;
 
 use rug::Integer;
 
-use crate::{n_order, Error};
+use crate::{n_order, Error, Group, ModularGroup};
 
 pub const MAX_ORDER: u64 = 1_000_000_000_000u64;
 
@@ -24,32 +24,43 @@ pub fn discrete_log_shanks_steps(
         None => n_order(&b, n)?,
     };
 
-    if order >= MAX_ORDER {
+    discrete_log_shanks_steps_group(&ModularGroup::new(n.clone()), &a, &b, &order)
+}
+
+/// Baby-step giant-step algorithm for computing the discrete logarithm of `a` in base `b` in any `group` (smallest non-negative integer `x` where `b**x = a`).
+///
+/// The algorithm uses `O(sqrt(order))` memory, where `order` is the order of `b`.
+pub fn discrete_log_shanks_steps_group<G: Group>(
+    group: &G,
+    a: &G::Element,
+    b: &G::Element,
+    order: &Integer,
+) -> Result<Integer, Error> {
+    if *order >= MAX_ORDER {
         return Err(Error::LogDoesNotExist);
     }
 
-    let m = order.sqrt() + 1;
+    let m = order.clone().sqrt() + 1;
     let mut t = HashMap::new();
-    let mut x = Integer::from(1);
+    let mut x = group.identity();
 
     // Build table: baby steps
     let mut i = Integer::ZERO;
     while i < m {
-        t.insert(x.clone(), i.clone());
-        x = x * &b % n;
+        t.entry(x.clone()).or_insert_with(|| i.clone());
+        x = group.op(&x, b);
         i += 1;
     }
 
     // Giant steps
-    let z = b.invert(n).unwrap();
-    let z = z.pow_mod(&m, n).unwrap();
-    let mut x = a;
+    let z = group.pow(&group.inverse(b), &m);
+    let mut x = a.clone();
     let mut i = Integer::ZERO;
     while i < m {
         if let Some(j) = t.get(&x) {
             return Ok(Integer::from(&i * &m + j));
         }
-        x = x * &z % n;
+        x = group.op(&x, &z);
         i += 1;
     }
 
@@ -0,0 +1,218 @@
+use rug::{ops::RemRounding, Integer};
+
+use crate::{
+    discrete_log, discrete_log_group, ec_discrete_log, element_order, factorize::factor,
+    utils::sqrt_mod, Curve, Error, Group, Point,
+};
+
+/// Norm one subgroup of `F_{p^2}* = F_p(c)*`, where `c**2 = d` is not a square in `F_p`.
+///
+/// The element `r + s*c` is represented as `(r, s)`. The group is cyclic of order `p + 1`.
+struct NormOneGroup {
+    p: Integer,
+    d: Integer,
+}
+
+impl Group for NormOneGroup {
+    type Element = (Integer, Integer);
+
+    fn identity(&self) -> (Integer, Integer) {
+        (Integer::from(1), Integer::ZERO)
+    }
+
+    fn op(
+        &self,
+        (r1, s1): &(Integer, Integer),
+        (r2, s2): &(Integer, Integer),
+    ) -> (Integer, Integer) {
+        let r = (Integer::from(r1 * r2) + Integer::from(s1 * s2) * &self.d) % &self.p;
+        let s = (Integer::from(r1 * s2) + Integer::from(r2 * s1)) % &self.p;
+        (r, s)
+    }
+
+    /// Returns the conjugate of `(r, s)`, which is its inverse since its norm is one.
+    fn inverse(&self, (r, s): &(Integer, Integer)) -> (Integer, Integer) {
+        (r.clone(), (-s.clone()).rem_euc(&self.p))
+    }
+
+    fn encode(&self, (r, s): &(Integer, Integer)) -> Integer {
+        Integer::from(s * &self.p) + r
+    }
+}
+
+/// Group of the nonsingular points of a singular cubic `y**2 = (x - alpha)**2 * (x - beta)`.
+enum SingularGroup {
+    /// Cusp (`alpha = beta`): the points are mapped to `F_p+` by `(x, y) -> (x - alpha)/y`.
+    Cusp { alpha: Integer },
+    /// Split node (`alpha - beta = c**2` is a square): the points are mapped to `F_p*` by
+    /// `(x, y) -> (y + c*(x - alpha))/(y - c*(x - alpha))`.
+    SplitNode { alpha: Integer, c: Integer },
+    /// Non-split node (`alpha - beta = d` is not a square): same map as the split node, with values in the norm one
+    /// subgroup of `F_p(sqrt(d))*`.
+    NonSplitNode { alpha: Integer, d: Integer },
+}
+
+impl SingularGroup {
+    /// Finds the singular point of `curve` and the group its nonsingular points are isomorphic to.
+    fn new(curve: &Curve) -> Self {
+        let p = curve.p();
+        if *curve.a() == 0 {
+            // The discriminant is zero, so b is zero too: y**2 = x**3
+            return Self::Cusp {
+                alpha: Integer::ZERO,
+            };
+        }
+
+        // With 2*alpha + beta = 0: a = -3*alpha**2 and b = 2*alpha**3, so alpha = -3b/(2a) and alpha - beta = 3*alpha
+        let alpha = (Integer::from(curve.b() * -3)
+            * Integer::from(curve.a() * 2).invert(p).unwrap())
+        .rem_euc(p);
+        let d = Integer::from(&alpha * 3) % p;
+        match sqrt_mod(&d, p) {
+            Some(c) => Self::SplitNode { alpha, c },
+            None => Self::NonSplitNode { alpha, d },
+        }
+    }
+
+    /// Maps a nonsingular point to the isomorphic group, returning the two coordinates of the image (the second one
+    /// is only used by the non-split node).
+    fn map(&self, curve: &Curve, point: &Point) -> Result<(Integer, Integer), Error> {
+        let p = curve.p();
+        let (x, y) = match point {
+            Point::Infinity => {
+                return Ok(match self {
+                    Self::Cusp { .. } => (Integer::ZERO, Integer::ZERO),
+                    _ => (Integer::from(1), Integer::ZERO),
+                })
+            }
+            Point::Affine(x, y) => (x, y),
+        };
+        let alpha = match self {
+            Self::Cusp { alpha }
+            | Self::SplitNode { alpha, .. }
+            | Self::NonSplitNode { alpha, .. } => alpha,
+        };
+        let u = Integer::from(x - alpha).rem_euc(p);
+        if u == 0 && *y == 0 {
+            // The singular point is not part of the group
+            return Err(Error::PointNotOnCurve);
+        }
+
+        Ok(match self {
+            Self::Cusp { .. } => (
+                u * Integer::from(y.invert_ref(p).unwrap()) % p,
+                Integer::ZERO,
+            ),
+            Self::SplitNode { c, .. } => {
+                let cu = u * c;
+                let num = Integer::from(y + &cu);
+                let den = Integer::from(y - &cu).rem_euc(p);
+                (num * den.invert(p).unwrap() % p, Integer::ZERO)
+            }
+            Self::NonSplitNode { d, .. } => {
+                // (y + u*c)/(y - u*c) = (y**2 + d*u**2 + 2*y*u*c)/(y**2 - d*u**2)
+                let y2 = Integer::from(y.square_ref());
+                let du2 = Integer::from(u.square_ref()) * d;
+                let norm_inv = Integer::from(&y2 - &du2).rem_euc(p).invert(p).unwrap();
+                let r = (y2 + du2) * &norm_inv % p;
+                let s = Integer::from(y * 2) * u * norm_inv % p;
+                (r, s)
+            }
+        })
+    }
+}
+
+/// Compute the discrete logarithm of `q` in base `p` on a singular `curve` (smallest non-negative integer `k` where
+/// `k*p = q`).
+///
+/// The nonsingular points of a singular cubic form a group isomorphic to the additive group `F_p+` (cusp), to the
+/// multiplicative group `F_p*` (split node) or to the norm one subgroup of `F_{p^2}*` (non-split node). The points
+/// are mapped to this group, where the discrete logarithm is computed (with a modular inversion for the cusp, with
+/// `discrete_log` for the split node and with `discrete_log_group` for the non-split node).
+///
+/// If the curve is not singular, `ec_discrete_log` is used instead.
+pub fn ec_discrete_log_singular(curve: &Curve, p: &Point, q: &Point) -> Result<Integer, Error> {
+    if !curve.is_singular() {
+        return ec_discrete_log(curve, p, q, None);
+    }
+    if !curve.contains(p) || !curve.contains(q) {
+        return Err(Error::PointNotOnCurve);
+    }
+
+    let modulus = curve.p();
+    let group = SingularGroup::new(curve);
+    let (p_image, p_image2) = group.map(curve, p)?;
+    let (q_image, q_image2) = group.map(curve, q)?;
+
+    let k = match &group {
+        SingularGroup::Cusp { .. } => {
+            if p_image == 0 {
+                Integer::ZERO
+            } else {
+                q_image * p_image.invert(modulus).unwrap() % modulus
+            }
+        }
+        SingularGroup::SplitNode { .. } => discrete_log(modulus, &q_image, &p_image)?,
+        SingularGroup::NonSplitNode { d, .. } => {
+            let group = NormOneGroup {
+                p: modulus.clone(),
+                d: d.clone(),
+            };
+            let p_image = (p_image, p_image2);
+            let order_factors = factor(&(modulus.clone() + 1));
+            if !order_factors.is_complete() {
+                return Err(Error::IncompleteFactorization);
+            }
+            let order = element_order(&group, &p_image, &order_factors);
+            discrete_log_group(&group, &(q_image, q_image2), &p_image, &order)?
+        }
+    };
+
+    if curve.mul(p, &k) == *q {
+        Ok(k)
+    } else {
+        Err(Error::LogDoesNotExist)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn cusp() {
+        // y^2 = x^3
+        let curve = Curve::new(1000000007.into(), 0.into(), 0.into());
+        let p = curve.point(4.into(), 8.into()).unwrap();
+        let q = curve.mul(&p, &123456789.into());
+        assert_eq!(ec_discrete_log_singular(&curve, &p, &q).unwrap(), 123456789);
+        assert_eq!(
+            ec_discrete_log_singular(&curve, &p, &Point::Affine(0.into(), 0.into())),
+            Err(Error::PointNotOnCurve)
+        );
+    }
+
+    #[test]
+    fn split_node() {
+        // y^2 = (x - 1)^2 (x + 2), 3 is a square modulo p
+        let curve = Curve::new(1000000007.into(), (-3).into(), 2.into());
+        let p = curve.lift_x(&5.into()).unwrap();
+        let q = curve.mul(&p, &987654.into());
+        assert_eq!(ec_discrete_log(&curve, &p, &q, None).unwrap(), 987654);
+    }
+
+    #[test]
+    fn non_split_node() {
+        // y^2 = (x - 1)^2 (x + 2), 3 is not a square modulo p
+        let curve = Curve::new(1000000087.into(), (-3).into(), 2.into());
+        let p = curve.lift_x(&6.into()).unwrap();
+        let q = curve.mul(&p, &555555555.into());
+        // The nonsingular points form a cyclic group of order p + 1, generated by p
+        let order = element_order(&curve, &p, &factor(&1000000088.into()));
+        assert_eq!(order, 1000000088);
+        assert_eq!(
+            ec_discrete_log_singular(&curve, &p, &q).unwrap(),
+            555555555 % order
+        );
+    }
+}
@@ -0,0 +1,508 @@
+use std::collections::{HashMap, HashSet};
+
+use primal::Primes;
+use rug::{rand::RandState, Integer};
+
+use crate::{factorize::pollard_rho_brent, utils::sqrt_mod};
+
+/// Parameters by size of `n`: largest number of decimal digits, size of the factor base and sieve half-width `M`.
+const PARAMETERS: [(usize, usize, u32); 9] = [
+    (20, 60, 8192),
+    (30, 150, 32768),
+    (40, 300, 65536),
+    (50, 1400, 32768),
+    (60, 3000, 32768),
+    (70, 5000, 65536),
+    (80, 8000, 65536),
+    (90, 14000, 98304),
+    (100, 24000, 131072),
+];
+/// Smallest sieved prime: the smaller primes of the factor base cost more to sieve than they contribute, they are only
+/// used in the trial division of the candidates.
+const SIEVE_START: u32 = 40;
+/// The sieve threshold allows a cofactor of about `pmax**THRESHOLD_EXPONENT` for the largest factor base prime `pmax`.
+const THRESHOLD_EXPONENT: f64 = 2.5;
+/// Largest prime of a partial relation, as a multiple of the largest factor base prime.
+const LARGE_PRIME_MULTIPLIER: u64 = 64;
+/// Number of relations collected beyond the number of columns of the matrix.
+const EXTRA_RELATIONS: usize = 16;
+/// Number of sieve cells checked at once when looking for candidates (the sieve length is a multiple of it).
+const CHUNK: usize = 32;
+/// Multipliers tried by the Knuth-Schroeppel function.
+const MULTIPLIERS: [u32; 31] = [
+    1, 3, 5, 7, 11, 13, 15, 17, 19, 21, 23, 29, 31, 33, 35, 37, 39, 41, 43, 47, 51, 53, 55, 57, 59,
+    61, 65, 67, 69, 71, 73,
+];
+
+/// Prime of the factor base, with a square root of `k*n` modulo it and its rounded base 2 logarithm.
+struct FactorBasePrime {
+    p: u32,
+    sqrt: u32,
+    log: u8,
+}
+
+/// Relation `y**2 = (-1)**e0 * prod(p_i**e_i) * large**2 (mod n)`, the exponents being indexed by `0` for `-1` and
+/// `i + 1` for the `i`-th prime of the factor base.
+struct Relation {
+    y: Integer,
+    exponents: Vec<(usize, u32)>,
+    large: Integer,
+}
+
+impl Relation {
+    /// Combines two partial relations with the same large prime into a full relation.
+    fn combine(self, other: Relation, large_prime: u64, n: &Integer) -> Relation {
+        let mut exponents = self.exponents;
+        for (i, e) in other.exponents {
+            match exponents.iter_mut().find(|(j, _)| *j == i) {
+                Some((_, f)) => *f += e,
+                None => exponents.push((i, e)),
+            }
+        }
+        Relation {
+            y: self.y * other.y % n,
+            exponents,
+            large: self.large * other.large * large_prime % n,
+        }
+    }
+}
+
+/// Returns the inverse of `a` modulo the prime `p`, `a` being non-zero modulo `p`.
+fn invert_mod(a: u64, p: u64) -> u64 {
+    let (mut r0, mut r1) = (p as i64, (a % p) as i64);
+    let (mut t0, mut t1) = (0i64, 1i64);
+    while r1 != 0 {
+        let q = r0 / r1;
+        (r0, r1) = (r1, r0 - q * r1);
+        (t0, t1) = (t1, t0 - q * t1);
+    }
+    t0.rem_euclid(p as i64) as u64
+}
+
+/// Returns the multiplier `k` maximizing the Knuth-Schroeppel function, the expected contribution of the small primes
+/// to the smoothness of the values of the polynomials for `k*n`.
+fn multiplier(n: &Integer) -> u32 {
+    let score = |k: u32| {
+        let kn8 = (n.mod_u(8) * k) % 8;
+        let mut score = -0.5 * (k as f64).ln()
+            + match kn8 {
+                1 => 2.0,
+                5 => 1.0,
+                _ => 0.5,
+            } * 2f64.ln();
+        for p in Primes::all().skip(1).take_while(|p| *p < 1000) {
+            let p = p as u32;
+            let knp = Integer::from(n.mod_u(p) as u64 * k as u64 % p as u64);
+            let ln_p = (p as f64).ln();
+            if k.is_multiple_of(p) {
+                score += ln_p / p as f64;
+            } else if knp.legendre(&Integer::from(p)) == 1 {
+                score += 2.0 * ln_p / (p - 1) as f64;
+            }
+        }
+        score
+    };
+    MULTIPLIERS
+        .into_iter()
+        .max_by(|k1, k2| score(*k1).total_cmp(&score(*k2)))
+        .unwrap()
+}
+
+/// Chooses the primes of the factor base whose product `A` is close to `target`, avoiding the products already used.
+fn choose_a(
+    fb: &[FactorBasePrime],
+    target: &Integer,
+    rand_state: &mut RandState<'_>,
+    used: &mut HashSet<Integer>,
+) -> Vec<usize> {
+    let log_target = target.to_f64().log2();
+    let usable = (0..fb.len())
+        .filter(|&i| fb[i].p >= SIEVE_START && fb[i].sqrt != 0)
+        .collect::<Vec<_>>();
+    let log_max = (fb[*usable.last().unwrap()].p as f64).log2().min(12.0);
+    let s = ((log_target / log_max).ceil() as usize).clamp(1, usable.len() / 2);
+    let log_q = log_target / s as f64;
+
+    // Pool of the primes closest to the ideal size of the factors of `A`
+    let mut pool = usable.clone();
+    pool.sort_by(|&i, &j| {
+        let di = ((fb[i].p as f64).log2() - log_q).abs();
+        let dj = ((fb[j].p as f64).log2() - log_q).abs();
+        di.total_cmp(&dj)
+    });
+    pool.truncate((4 * s + 8).min(usable.len()));
+
+    loop {
+        let mut chosen: Vec<usize> = Vec::with_capacity(s);
+        while chosen.len() < s - 1 {
+            let i = pool[rand_state.below(pool.len() as u32) as usize];
+            if !chosen.contains(&i) {
+                chosen.push(i);
+            }
+        }
+        // The last prime brings the product as close as possible to the target
+        let partial = chosen.iter().map(|&i| fb[i].p).product::<Integer>();
+        let rest = (log_target - partial.to_f64().log2()).exp2();
+        let last = usable
+            .iter()
+            .filter(|i| !chosen.contains(i))
+            .min_by(|&&i, &&j| {
+                let di = (fb[i].p as f64 - rest).abs();
+                let dj = (fb[j].p as f64 - rest).abs();
+                di.total_cmp(&dj)
+            })
+            .copied()
+            .unwrap();
+        chosen.push(last);
+        if used.insert(partial * fb[last].p) {
+            return chosen;
+        }
+    }
+}
+
+/// Returns sets of relations whose product is a square (Gaussian elimination modulo 2 on bit vectors).
+///
+/// The relations with a prime appearing in no other relation are removed first, then the columns are eliminated from
+/// the sparsest to the densest to limit the fill-in.
+fn dependencies(relations: &[Relation], columns: usize) -> Vec<Vec<usize>> {
+    let odd = |r: usize| {
+        relations[r]
+            .exponents
+            .iter()
+            .filter(|(_, e)| e % 2 == 1)
+            .map(|(i, _)| *i)
+    };
+
+    let mut active = vec![true; relations.len()];
+    let mut weights = vec![0usize; columns];
+    loop {
+        weights.fill(0);
+        for r in (0..relations.len()).filter(|&r| active[r]) {
+            odd(r).for_each(|i| weights[i] += 1);
+        }
+        let mut removed = false;
+        for (r, active) in active.iter_mut().enumerate() {
+            if *active && odd(r).any(|i| weights[i] == 1) {
+                *active = false;
+                removed = true;
+            }
+        }
+        if !removed {
+            break;
+        }
+    }
+    let kept = (0..relations.len())
+        .filter(|&r| active[r])
+        .collect::<Vec<_>>();
+
+    let words = columns.div_ceil(64);
+    let history_words = kept.len().div_ceil(64);
+    let mut rows = kept
+        .iter()
+        .map(|&r| {
+            let mut row = vec![0u64; words];
+            odd(r).for_each(|i| row[i / 64] ^= 1 << (i % 64));
+            row
+        })
+        .collect::<Vec<_>>();
+    let mut history = (0..kept.len())
+        .map(|k| {
+            let mut row = vec![0u64; history_words];
+            row[k / 64] = 1 << (k % 64);
+            row
+        })
+        .collect::<Vec<_>>();
+
+    let mut order = (0..columns).filter(|&i| weights[i] > 0).collect::<Vec<_>>();
+    order.sort_by_key(|&i| weights[i]);
+    let mut used = vec![false; kept.len()];
+    for column in order {
+        let (word, bit) = (column / 64, 1 << (column % 64));
+        let Some(pivot) = (0..rows.len()).find(|&k| !used[k] && rows[k][word] & bit != 0) else {
+            continue;
+        };
+        used[pivot] = true;
+        let (pivot_row, pivot_history) = (rows[pivot].clone(), history[pivot].clone());
+        for k in 0..rows.len() {
+            if k != pivot && rows[k][word] & bit != 0 {
+                rows[k]
+                    .iter_mut()
+                    .zip(&pivot_row)
+                    .for_each(|(a, b)| *a ^= b);
+                history[k]
+                    .iter_mut()
+                    .zip(&pivot_history)
+                    .for_each(|(a, b)| *a ^= b);
+            }
+        }
+    }
+
+    (0..rows.len())
+        .filter(|&k| !used[k])
+        .map(|k| {
+            (0..kept.len())
+                .filter(|&j| history[k][j / 64] >> (j % 64) & 1 == 1)
+                .map(|j| kept[j])
+                .collect()
+        })
+        .collect()
+}
+
+/// Finds a non-trivial factor of the odd composite `n` with the self-initializing quadratic sieve (SIQS), `None` if
+/// none is found.
+///
+/// Relations `y**2 = A * g(x) (mod n)` are collected by sieving the values of the polynomials
+/// `g(x) = A*x**2 + 2*B*x + C` (with `B**2 - C*A = k*n` for a Knuth-Schroeppel multiplier `k`) over `[-M, M)`, with
+/// one large prime allowed in their factorization. The `2**(s-1)` polynomials sharing the same product `A` of `s`
+/// factor base primes are switched with a Gray code. A combination of the relations whose product is a square is then
+/// found with Gaussian elimination modulo 2, giving `x**2 = y**2 (mod n)`.
+///
+/// `n` must not be a perfect power, and it is meant to have at least 20 digits: smaller numbers are left to Pollard's
+/// rho algorithm.
+pub fn siqs(n: &Integer) -> Option<Integer> {
+    if n.is_even() {
+        return (*n > 2).then(|| Integer::from(2));
+    }
+    if n.significant_bits() < 64 {
+        return pollard_rho_brent(n, u64::MAX);
+    }
+    let digits = (n.significant_bits() as f64 * std::f64::consts::LOG10_2) as usize + 1;
+    let (_, fb_size, m) = *PARAMETERS
+        .iter()
+        .find(|(d, _, _)| digits <= *d)
+        .unwrap_or(PARAMETERS.last().unwrap());
+
+    let k = multiplier(n);
+    let kn = Integer::from(n * k);
+    let mut fb = Vec::with_capacity(fb_size);
+    for p in Primes::all() {
+        if fb.len() == fb_size {
+            break;
+        }
+        let p = p as u32;
+        let log = (p as f64).log2().round() as u8;
+        let r = kn.mod_u(p);
+        if p == 2 || r == 0 {
+            if p != 2 && n.is_divisible_u(p) {
+                return Some(p.into());
+            }
+            fb.push(FactorBasePrime { p, sqrt: r, log });
+        } else if let Some(sqrt) = sqrt_mod(&r.into(), &p.into()) {
+            fb.push(FactorBasePrime {
+                p,
+                sqrt: sqrt.to_u32().unwrap(),
+                log,
+            });
+        }
+    }
+
+    let pmax = fb.last().unwrap().p;
+    let large_prime_bound = pmax as u64 * LARGE_PRIME_MULTIPLIER;
+    let columns = fb.len() + 1;
+    let len = 2 * m as usize;
+    let target_a = Integer::from(&kn * 2u32).sqrt() / m;
+    let log_g = (m as f64).log2() + kn.significant_bits() as f64 / 2.0;
+    let threshold = (log_g - THRESHOLD_EXPONENT * (pmax as f64).log2()).max(0.0) as u8;
+    let sieve_start = fb
+        .iter()
+        .position(|fp| fp.p >= SIEVE_START)
+        .unwrap_or(fb.len());
+
+    let mut rand_state = RandState::new();
+    rand_state.seed(n);
+    let mut used_a = HashSet::new();
+    let mut relations: Vec<Relation> = Vec::new();
+    let mut partials: HashMap<u64, Relation> = HashMap::new();
+    let mut sieve = vec![0u8; len];
+    let mut soln1 = vec![0u32; fb.len()];
+    let mut soln2 = vec![0u32; fb.len()];
+
+    while relations.len() < columns + EXTRA_RELATIONS {
+        // Initialization of a new `A = prod(q_l)` and of the `B_l` with `B_l**2 = k*n (mod q_l)`, `B_l = 0 (mod q_j)`
+        let a_factors = choose_a(&fb, &target_a, &mut rand_state, &mut used_a);
+        let a = a_factors.iter().map(|&i| fb[i].p).product::<Integer>();
+        let b_terms = a_factors
+            .iter()
+            .map(|&i| {
+                let q = fb[i].p;
+                let a_q = Integer::from(&a / q);
+                let inv = Integer::from(a_q.mod_u(q)).invert(&q.into()).unwrap();
+                let mut gamma = (inv * fb[i].sqrt % q).to_u32().unwrap();
+                if gamma > q / 2 {
+                    gamma = q - gamma;
+                }
+                a_q * gamma
+            })
+            .collect::<Vec<_>>();
+        let mut b = b_terms.iter().sum::<Integer>();
+        let mut in_a = vec![false; fb.len()];
+        a_factors.iter().for_each(|&i| in_a[i] = true);
+
+        // Roots of g modulo the factor base primes, and their shifts when switching polynomials
+        let mut b_ainv = vec![vec![0u32; fb.len()]; b_terms.len()];
+        for (i, fp) in fb.iter().enumerate().skip(sieve_start) {
+            if in_a[i] {
+                continue;
+            }
+            let p = fp.p as u64;
+            let ainv = invert_mod(a.mod_u(fp.p) as u64, p);
+            let b_mod = b.mod_u(fp.p) as u64;
+            let offset = (m % fp.p) as u64;
+            soln1[i] = (((fp.sqrt as u64 + p - b_mod) * ainv + offset) % p) as u32;
+            soln2[i] = (((2 * p - fp.sqrt as u64 - b_mod) * ainv + offset) % p) as u32;
+            for (l, b_l) in b_terms.iter().enumerate() {
+                b_ainv[l][i] = (2 * b_l.mod_u(fp.p) as u64 * ainv % p) as u32;
+            }
+        }
+
+        for j in 0u32..1 << (b_terms.len() - 1) {
+            if j > 0 {
+                // Gray code: the sign of B_l flips, with l the lowest set bit of j plus one
+                let v = j.trailing_zeros() as usize;
+                let negative = ((j ^ (j >> 1)) >> v) & 1 == 1;
+                let l = v + 1;
+                if negative {
+                    b -= Integer::from(&b_terms[l] * 2u32);
+                } else {
+                    b += Integer::from(&b_terms[l] * 2u32);
+                }
+                for (i, fp) in fb.iter().enumerate().skip(sieve_start) {
+                    if in_a[i] {
+                        continue;
+                    }
+                    let shift = if negative {
+                        b_ainv[l][i]
+                    } else {
+                        fp.p - b_ainv[l][i]
+                    };
+                    soln1[i] = ((soln1[i] as u64 + shift as u64) % fp.p as u64) as u32;
+                    soln2[i] = ((soln2[i] as u64 + shift as u64) % fp.p as u64) as u32;
+                }
+            }
+
+            sieve.fill(0);
+            for (i, fp) in fb.iter().enumerate().skip(sieve_start) {
+                if in_a[i] {
+                    continue;
+                }
+                let p = fp.p as usize;
+                for start in [soln1[i], soln2[i]]
+                    .into_iter()
+                    .take(1 + (soln1[i] != soln2[i]) as usize)
+                {
+                    let mut k = start as usize;
+                    while k < len {
+                        sieve[k] = sieve[k].saturating_add(fp.log);
+                        k += p;
+                    }
+                }
+            }
+
+            let candidates = sieve
+                .chunks_exact(CHUNK)
+                .enumerate()
+                .filter(|(_, chunk)| chunk.iter().fold(0, |max, &c| max.max(c)) >= threshold)
+                .flat_map(|(k, chunk)| {
+                    (0..CHUNK)
+                        .filter(move |&c| chunk[c] >= threshold)
+                        .map(move |c| k * CHUNK + c)
+                })
+                .collect::<Vec<_>>();
+            for index in candidates {
+                let x = index as i64 - m as i64;
+                let y = Integer::from(&a * x) + &b;
+                let g = (Integer::from(y.square_ref()) - &kn) / &a;
+                if g == 0 {
+                    continue;
+                }
+
+                let mut v = Integer::from(g.abs_ref());
+                let mut exponents = Vec::new();
+                if g < 0 {
+                    exponents.push((0, 1));
+                }
+                for (i, fp) in fb.iter().enumerate() {
+                    let divisible = if i < sieve_start || in_a[i] {
+                        v.is_divisible_u(fp.p)
+                    } else {
+                        let r = index as u32 % fp.p;
+                        r == soln1[i] || r == soln2[i]
+                    };
+                    let mut e = in_a[i] as u32;
+                    if divisible {
+                        while v.is_divisible_u(fp.p) {
+                            v.div_exact_u_mut(fp.p);
+                            e += 1;
+                        }
+                    }
+                    if e > 0 {
+                        exponents.push((i + 1, e));
+                    }
+                }
+
+                let relation = Relation {
+                    y: y % n,
+                    exponents,
+                    large: Integer::from(1),
+                };
+                if v == 1 {
+                    relations.push(relation);
+                } else if let Some(large_prime) = v.to_u64().filter(|l| *l <= large_prime_bound) {
+                    match partials.remove(&large_prime) {
+                        Some(other) => relations.push(relation.combine(other, large_prime, n)),
+                        None => {
+                            partials.insert(large_prime, relation);
+                        }
+                    }
+                }
+            }
+        }
+    }
+    // Square root of each dependency: x**2 = y**2 (mod n)
+    for dependency in dependencies(&relations, columns) {
+        let mut x = Integer::from(1);
+        let mut y = Integer::from(1);
+        let mut exponents = vec![0u64; columns];
+        for &r in &dependency {
+            x = x * &relations[r].y % n;
+            y = y * &relations[r].large % n;
+            for (i, e) in &relations[r].exponents {
+                exponents[*i] += *e as u64;
+            }
+        }
+        for (i, e) in exponents
+            .iter()
+            .enumerate()
+            .skip(1)
+            .filter(|(_, e)| **e > 0)
+        {
+            let p = Integer::from(fb[i - 1].p);
+            y = y * p.pow_mod(&Integer::from(e / 2), n).unwrap() % n;
+        }
+        let g = (x - y).gcd(n);
+        if g != 1 && g != *n {
+            return Some(g);
+        }
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use super::*;
+
+    #[test]
+    fn quadratic_sieve() {
+        for (p, q) in [
+            ("30000000000000012347", "70000000000000006819"),
+            ("4000000000000000000000027", "20000000000000000000000009"),
+        ] {
+            let p = Integer::from_str(p).unwrap();
+            let q = Integer::from_str(q).unwrap();
+            let d = siqs(&Integer::from(&p * &q)).unwrap();
+            assert!(d == p || d == q);
+        }
+    }
+}
@@ -0,0 +1,102 @@
+use rug::{ops::RemRounding, Integer};
+
+use crate::crt::crt_pair;
+
+/// Set of solutions of a discrete logarithm problem: all the integers `x0 + k * period`.
+///
+/// `x0` is the smallest non-negative solution and `period` is the order of the base, so that `x0` is the value returned
+/// by `discrete_log`.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct DlogSolution {
+    /// Smallest non-negative solution.
+    pub x0: Integer,
+    /// Period of the solutions (order of the base).
+    pub period: Integer,
+}
+
+impl DlogSolution {
+    /// Creates the solution set `x0 + k * period`, with `x0` reduced modulo `period`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `period` is not positive.
+    pub fn new(x0: Integer, period: Integer) -> Self {
+        assert!(period > 0, "the period must be positive");
+        Self {
+            x0: x0.rem_euc(&period),
+            period,
+        }
+    }
+
+    /// Returns `true` if `x` is a solution.
+    pub fn contains(&self, x: &Integer) -> bool {
+        Integer::from(x - &self.x0).is_divisible(&self.period)
+    }
+
+    /// Returns the solutions in `[lo, hi)`, in increasing order.
+    pub fn solutions_in(&self, lo: &Integer, hi: &Integer) -> impl Iterator<Item = Integer> + '_ {
+        // Smallest solution >= lo
+        let mut x = Integer::from(lo - &self.x0).rem_euc(&self.period);
+        if x != 0 {
+            x = &self.period - x;
+        }
+        let mut x = x + lo;
+        let hi = hi.clone();
+        std::iter::from_fn(move || {
+            (x < hi).then(|| {
+                let solution = x.clone();
+                x += &self.period;
+                solution
+            })
+        })
+    }
+
+    /// Returns the solutions which are also solutions of `other` (for instance a congruence `x = r (mod m)` created with
+    /// `DlogSolution::new(r, m)`), `None` if there is none.
+    ///
+    /// The periods need not be coprime: the period of the intersection is their least common multiple.
+    pub fn intersect(&self, other: &DlogSolution) -> Option<DlogSolution> {
+        let (x0, period) = crt_pair(&self.x0, &self.period, &other.x0, &other.period).ok()?;
+        Some(DlogSolution { x0, period })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn dlog_solution() {
+        let solution = DlogSolution::new(23.into(), 10.into());
+        assert_eq!(solution, DlogSolution::new(3.into(), 10.into()));
+        assert!(solution.contains(&(-7).into()));
+        assert!(!solution.contains(&4.into()));
+        assert_eq!(
+            solution
+                .solutions_in(&(-10).into(), &30.into())
+                .collect::<Vec<_>>(),
+            [-7, 3, 13, 23]
+        );
+        assert_eq!(
+            solution
+                .solutions_in(&3.into(), &4.into())
+                .collect::<Vec<_>>(),
+            [3]
+        );
+        assert_eq!(solution.solutions_in(&4.into(), &13.into()).count(), 0);
+
+        // x = 3 (mod 10) and x = 7 (mod 12) gives x = 43 (mod 60)
+        assert_eq!(
+            solution.intersect(&DlogSolution::new(7.into(), 12.into())),
+            Some(DlogSolution::new(43.into(), 60.into()))
+        );
+        assert_eq!(
+            solution.intersect(&DlogSolution::new(6.into(), 12.into())),
+            None
+        );
+        assert_eq!(
+            solution.intersect(&DlogSolution::new(2.into(), 7.into())),
+            Some(DlogSolution::new(23.into(), 70.into()))
+        );
+    }
+}
@@ -1,6 +1,6 @@
 use rug::Integer;
 
-use crate::Error;
+use crate::{Error, Group, ModularGroup};
 
 /// Trial multiplication algorithm for computing the discrete logarithm of `a` in base `b` modulo `n` (smallest non-negative integer `x` where `b**x = a (mod n)`).
 ///
@@ -21,13 +21,25 @@ pub fn discrete_log_trial_mul(
         None => n,
     };
 
-    let mut x = Integer::from(1);
+    discrete_log_trial_mul_group(&ModularGroup::new(n.clone()), &a, &b, order)
+}
+
+/// Trial multiplication algorithm for computing the discrete logarithm of `a` in base `b` in any `group` (smallest non-negative integer `x` where `b**x = a`).
+///
+/// At most `order` multiplications are performed, where `order` is the order of `b` (or any multiple of it).
+pub fn discrete_log_trial_mul_group<G: Group>(
+    group: &G,
+    a: &G::Element,
+    b: &G::Element,
+    order: &Integer,
+) -> Result<Integer, Error> {
+    let mut x = group.identity();
     let mut i = 0;
     loop {
-        if x == a {
+        if x == *a {
             return Ok(Integer::from(i));
         }
-        x = x * &b % n;
+        x = group.op(&x, b);
 
         i += 1;
         if i == *order {
@@ -0,0 +1,272 @@
+use std::collections::BTreeMap;
+
+use rug::{ops::Pow, Integer};
+
+use crate::{factorize::factor, Error, Factorization};
+
+/// Returns the factorization of Euler's totient `phi(n)`, the order of `(Z/nZ)*`, from the prime factorization of `n`.
+pub(crate) fn totient_factors(n_factors: &Factorization) -> Factorization {
+    let mut factors = Factorization::new();
+    for (p, e, primality) in n_factors.iter_with_primality() {
+        factors.insert(p.clone(), e - 1, primality);
+        factors *= &factor(&(p.clone() - 1));
+    }
+    factors
+}
+
+/// Checks that `n` is positive and that `n_factors` is a complete factorization of it.
+fn check_factors(n: &Integer, n_factors: &Factorization) -> Result<(), Error> {
+    if *n < 1 {
+        Err(Error::InvalidModulus)
+    } else if n_factors.value() != *n {
+        Err(Error::InvalidFactorization)
+    } else if !n_factors.is_complete() {
+        Err(Error::IncompleteFactorization)
+    } else {
+        Ok(())
+    }
+}
+
+/// Returns Euler's totient `phi(n)`, the order of `(Z/nZ)*` (`n` must be positive).
+pub fn euler_phi(n: &Integer) -> Result<Integer, Error> {
+    if *n < 1 {
+        return Err(Error::InvalidModulus);
+    }
+    euler_phi_with_factors(n, &factor(n))
+}
+
+/// Returns Euler's totient `phi(n)`, the order of `(Z/nZ)*` (`n` must be positive).
+///
+/// `n_factors` must be the prime factorization of `n`.
+pub fn euler_phi_with_factors(n: &Integer, n_factors: &Factorization) -> Result<Integer, Error> {
+    check_factors(n, n_factors)?;
+    n_factors.euler_phi()
+}
+
+/// Returns Carmichael's function `lambda(n)`, the exponent of `(Z/nZ)*` (largest order of its elements, `n` must be
+/// positive).
+pub fn carmichael_lambda(n: &Integer) -> Result<Integer, Error> {
+    if *n < 1 {
+        return Err(Error::InvalidModulus);
+    }
+    carmichael_lambda_with_factors(n, &factor(n))
+}
+
+/// Returns Carmichael's function `lambda(n)`, the exponent of `(Z/nZ)*` (largest order of its elements, `n` must be
+/// positive).
+///
+/// `n_factors` must be the prime factorization of `n`.
+pub fn carmichael_lambda_with_factors(
+    n: &Integer,
+    n_factors: &Factorization,
+) -> Result<Integer, Error> {
+    check_factors(n, n_factors)?;
+    n_factors.carmichael_lambda()
+}
+
+/// Returns the invariant factors `d_1 | d_2 | ... | d_k` of `(Z/nZ)*`, in increasing order (`n` must be positive).
+///
+/// The group is isomorphic to `Z/d_1 x ... x Z/d_k`: the product of the invariant factors is `phi(n)`, the last one is
+/// `lambda(n)`, and the group is cyclic if there is at most one. The trivial groups (`n = 1, 2`) have none.
+pub fn unit_group_structure(n: &Integer) -> Result<Vec<Integer>, Error> {
+    if *n < 1 {
+        return Err(Error::InvalidModulus);
+    }
+    unit_group_structure_with_factors(n, &factor(n))
+}
+
+/// Returns the invariant factors `d_1 | d_2 | ... | d_k` of `(Z/nZ)*`, in increasing order (`n` must be positive).
+///
+/// `n_factors` must be the prime factorization of `n`.
+pub fn unit_group_structure_with_factors(
+    n: &Integer,
+    n_factors: &Factorization,
+) -> Result<Vec<Integer>, Error> {
+    check_factors(n, n_factors)?;
+
+    // Orders of the cyclic factors of (Z/p^eZ)*
+    let mut cyclic = Vec::new();
+    for (p, e, primality) in n_factors.iter_with_primality() {
+        if *p == 2 {
+            if e >= 2 {
+                cyclic.push(Factorization::from_iter([(Integer::from(2), 1)]));
+            }
+            if e >= 3 {
+                cyclic.push(Factorization::from_iter([(Integer::from(2), e - 2)]));
+            }
+        } else {
+            let mut order = factor(&(p.clone() - 1));
+            if !order.is_complete() {
+                return Err(Error::IncompleteFactorization);
+            }
+            order.insert(p.clone(), e - 1, primality);
+            cyclic.push(order);
+        }
+    }
+
+    // Exponents of the primary components, grouped by prime in decreasing order
+    let mut primary = BTreeMap::<Integer, Vec<usize>>::new();
+    for order in &cyclic {
+        for (q, e) in order.iter() {
+            primary.entry(q.clone()).or_default().push(e);
+        }
+    }
+    let length = primary.values().map(Vec::len).max().unwrap_or(0);
+    let mut invariants = vec![Integer::from(1); length];
+    for (q, mut exponents) in primary {
+        exponents.sort_unstable_by(|a, b| b.cmp(a));
+        for (d, e) in invariants.iter_mut().rev().zip(exponents) {
+            *d *= q.clone().pow(e as u32);
+        }
+    }
+    Ok(invariants)
+}
+
+/// Returns the factorization of `phi(n)` if `(Z/nZ)*` is cyclic (`n` is 1, 2, 4, `p**k` or `2*p**k` for an odd prime
+/// `p`), `Error::NotCyclic` otherwise (`Error::IncompleteFactorization` if `n` or `phi(n)` cannot be completely
+/// factored).
+fn cyclic_order_factors(n: &Integer) -> Result<Factorization, Error> {
+    if *n < 1 {
+        return Err(Error::InvalidModulus);
+    }
+    let n_factors = factor(n);
+    let odd_primes = n_factors.iter().filter(|(p, _)| **p != 2).count();
+    let two = n_factors.exponent(&Integer::from(2));
+    if !matches!((odd_primes, two), (0, 0..=2) | (1, 0..=1)) {
+        return Err(Error::NotCyclic);
+    }
+    let order_factors = totient_factors(&n_factors);
+    if !order_factors.is_complete() {
+        return Err(Error::IncompleteFactorization);
+    }
+    Ok(order_factors)
+}
+
+/// Returns `true` if the order of `g` modulo `n` is `phi(n)`, given the factorization of `phi(n)`.
+fn has_full_order(g: &Integer, n: &Integer, order_factors: &Factorization) -> bool {
+    let g = Integer::from(g % n);
+    if Integer::from(g.gcd_ref(n)) != 1 {
+        return false;
+    }
+    let order = order_factors.value();
+    order_factors
+        .iter()
+        .all(|(q, _)| Integer::from(g.pow_mod_ref(&Integer::from(&order / q), n).unwrap()) != 1)
+}
+
+/// Returns `true` if `g` is a primitive root modulo `n` (a generator of `(Z/nZ)*`).
+///
+/// Returns `Error::NotCyclic` if `(Z/nZ)*` is not cyclic.
+pub fn is_primitive_root(g: &Integer, n: &Integer) -> Result<bool, Error> {
+    let order_factors = cyclic_order_factors(n)?;
+    Ok(has_full_order(g, n, &order_factors))
+}
+
+/// Returns the smallest primitive root modulo `n` (0 for `n = 1`).
+///
+/// Returns `Error::NotCyclic` if `(Z/nZ)*` is not cyclic.
+pub fn primitive_root(n: &Integer) -> Result<Integer, Error> {
+    primitive_roots(n)?.next().ok_or(Error::NotCyclic)
+}
+
+/// Returns an iterator over the primitive roots modulo `n`, in increasing order (there are `phi(phi(n))` of them).
+///
+/// Returns `Error::NotCyclic` if `(Z/nZ)*` is not cyclic.
+pub fn primitive_roots(n: &Integer) -> Result<impl Iterator<Item = Integer>, Error> {
+    let order_factors = cyclic_order_factors(n)?;
+    let n = n.clone();
+    let mut g = Integer::new();
+    Ok(std::iter::from_fn(move || {
+        while g < n {
+            let candidate = g.clone();
+            g += 1;
+            if has_full_order(&candidate, &n, &order_factors) {
+                return Some(candidate);
+            }
+        }
+        None
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Primality;
+
+    #[test]
+    fn euler_phi_() {
+        assert_eq!(euler_phi(&1.into()).unwrap(), 1);
+        assert_eq!(euler_phi(&360.into()).unwrap(), 96);
+        assert_eq!(carmichael_lambda(&360.into()).unwrap(), 12);
+        assert_eq!(carmichael_lambda(&561.into()).unwrap(), 80);
+        assert_eq!(euler_phi(&0.into()), Err(Error::InvalidModulus));
+        assert_eq!(
+            euler_phi_with_factors(&12.into(), &factor(&24.into())),
+            Err(Error::InvalidFactorization)
+        );
+        let mut incomplete = Factorization::new();
+        incomplete.insert(15.into(), 1, Primality::Composite);
+        assert_eq!(
+            carmichael_lambda_with_factors(&15.into(), &incomplete),
+            Err(Error::IncompleteFactorization)
+        );
+    }
+
+    #[test]
+    fn primitive_root_() {
+        assert_eq!(primitive_root(&1.into()).unwrap(), 0);
+        assert_eq!(primitive_root(&2.into()).unwrap(), 1);
+        assert_eq!(primitive_root(&4.into()).unwrap(), 3);
+        assert_eq!(primitive_root(&41.into()).unwrap(), 6);
+        assert_eq!(primitive_root(&(2 * 25).into()).unwrap(), 3);
+        assert_eq!(primitive_root(&8.into()), Err(Error::NotCyclic));
+        assert_eq!(primitive_root(&15.into()), Err(Error::NotCyclic));
+        assert_eq!(primitive_root(&0.into()), Err(Error::InvalidModulus));
+        assert_eq!(
+            is_primitive_root(&2.into(), &15.into()),
+            Err(Error::NotCyclic)
+        );
+
+        assert!(is_primitive_root(&2.into(), &11.into()).unwrap());
+        assert!(!is_primitive_root(&3.into(), &11.into()).unwrap());
+        assert!(!is_primitive_root(&22.into(), &11.into()).unwrap());
+        assert_eq!(
+            primitive_roots(&11.into()).unwrap().collect::<Vec<_>>(),
+            [2, 6, 7, 8]
+        );
+        for n in [9, 25, 27, 49, 50, 54] {
+            let phi = euler_phi(&n.into()).unwrap();
+            let roots = primitive_roots(&n.into()).unwrap().collect::<Vec<_>>();
+            assert_eq!(roots.len(), euler_phi(&phi).unwrap());
+            assert!(roots
+                .iter()
+                .all(|g| crate::n_order(g, &n.into()).unwrap() == phi));
+        }
+    }
+
+    #[test]
+    fn unit_group_structure_() {
+        let structure = |n: u32| {
+            unit_group_structure(&n.into())
+                .unwrap()
+                .iter()
+                .map(|d| d.to_u32().unwrap())
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(structure(1), Vec::<u32>::new());
+        assert_eq!(structure(2), Vec::<u32>::new());
+        assert_eq!(structure(7), [6]);
+        assert_eq!(structure(8), [2, 2]);
+        assert_eq!(structure(15), [2, 4]);
+        assert_eq!(structure(360), [2, 2, 2, 12]);
+        assert_eq!(structure(2 * 27), [18]);
+        for n in 1..200u32 {
+            let invariants = unit_group_structure(&n.into()).unwrap();
+            assert!(invariants.windows(2).all(|w| w[1].is_divisible(&w[0])));
+            let product = invariants.iter().product::<Integer>();
+            assert_eq!(product, euler_phi(&n.into()).unwrap());
+            let lambda = invariants.last().cloned().unwrap_or(Integer::from(1));
+            assert_eq!(lambda, carmichael_lambda(&n.into()).unwrap());
+        }
+    }
+}
@@ -1,73 +1,107 @@
-use std::collections::HashMap;
+use rug::{ops::Pow, ops::RemRounding, Integer};
 
-use primal::Primes;
-use rug::Integer;
+use crate::{factorize::factor, Factorization};
 
-pub fn fast_factor(n: &Integer) -> HashMap<Integer, usize> {
-    let mut factors: HashMap<Integer, usize> = HashMap::new();
-    let mut n: Integer = n.clone();
-    for prime in Primes::all().take(1_000_000) {
-        let prime = Integer::from(prime);
-        if n.clone().div_rem(prime.clone()).1 == 0 {
-            while n.clone().div_rem(prime.clone()).1 == 0 {
-                n /= &prime;
-                *factors.entry(prime.clone()).or_insert(0) += 1;
-            }
-        }
+/// Returns the prime factorization of `base**k - 1`.
+///
+/// `base**k - 1` is first split into the cyclotomic values `Phi_d(base)` for the divisors `d` of `k`, which are then
+/// factored separately.
+pub fn factor_power_minus_one(base: &Integer, k: usize) -> Factorization {
+    let mut cyclotomic: Vec<(usize, Integer)> = Vec::new();
+    let mut factors = Factorization::new();
+    for d in (1..=k).filter(|d| k.is_multiple_of(*d)) {
+        // base**d - 1 is the product of Phi_e(base) over the divisors e of d
+        let value = cyclotomic
+            .iter()
+            .filter(|(e, _)| d.is_multiple_of(*e))
+            .fold(base.clone().pow(d as u32) - 1u32, |acc, (_, phi)| acc / phi);
+        factors *= &factor(&value);
+        cyclotomic.push((d, value));
     }
+    factors
+}
 
-    if n != 1 {
-        *factors.entry(n).or_insert(0) += 1;
+/// Square root of `a` modulo the odd prime `p` (Tonelli-Shanks algorithm), `None` if `a` is not a square.
+pub fn sqrt_mod(a: &Integer, p: &Integer) -> Option<Integer> {
+    let a = a.clone().rem_euc(p);
+    if a == 0 {
+        return Some(a);
+    }
+    if a.legendre(p) != 1 {
+        return None;
     }
 
-    factors
-}
+    // p - 1 = q * 2^s with q odd
+    let s = Integer::from(p - 1).find_one(0).unwrap();
+    let q = Integer::from(p - 1) >> s;
 
-pub fn crt(residues: &[Integer], modulli: &[Integer]) -> Option<Integer> {
-    let prod = modulli.iter().product::<Integer>();
-    let mut sum = Integer::ZERO;
+    // Find a quadratic non-residue
+    let mut z = Integer::from(2);
+    while z.legendre(p) != -1 {
+        z += 1;
+    }
 
-    for (residue, modulus) in residues.iter().zip(modulli) {
-        let p = prod.clone() / modulus;
-        sum += residue * Integer::from(p.invert_ref(modulus)?) * p
+    let mut m = s;
+    let mut c = z.pow_mod(&q, p).unwrap();
+    let mut t = a.clone().pow_mod(&q, p).unwrap();
+    let mut r = a.pow_mod(&((q + 1) >> 1), p).unwrap();
+    while t != 1 {
+        let mut i = 0;
+        let mut t2 = t.clone();
+        while t2 != 1 {
+            t2 = t2.square() % p;
+            i += 1;
+        }
+        let b = c.pow_mod(&(Integer::from(1) << (m - i - 1)), p).unwrap();
+        r = r * &b % p;
+        c = b.square() % p;
+        t = t * &c % p;
+        m = i;
     }
 
-    Some(sum % prod)
+    Some(r)
+}
+
+/// Returns the number of threads to use for a requested count of `threads` (`0` for all the available cores).
+pub(crate) fn thread_count(threads: usize) -> usize {
+    if threads == 0 {
+        std::thread::available_parallelism().map_or(1, usize::from)
+    } else {
+        threads
+    }
 }
 
 #[cfg(test)]
 mod tests {
+    use std::collections::HashMap;
+
     use super::*;
 
     #[test]
-    fn chinese_remainder_theorem() {
-        assert_eq!(
-            crt(
-                &[3.into(), 5.into(), 7.into()],
-                &[2.into(), 3.into(), 1.into()]
-            ),
-            Some(Integer::from(5))
-        );
-        assert_eq!(
-            crt(
-                &[1.into(), 4.into(), 6.into()],
-                &[3.into(), 5.into(), 7.into()]
-            ),
-            Some(Integer::from(34))
-        );
-        assert_eq!(
-            crt(
-                &[1.into(), 4.into(), 6.into()],
-                &[1.into(), 2.into(), 0.into()]
-            ),
-            None
-        );
+    fn modular_square_root() {
+        for p in [3, 5, 7, 13, 17, 41, 97, 257, 65537] {
+            let p = Integer::from(p);
+            for a in 0..p.to_u32().unwrap().min(300) {
+                let a = Integer::from(a);
+                match sqrt_mod(&a, &p) {
+                    Some(r) => assert_eq!(r.square() % &p, a),
+                    None => assert_eq!(a.legendre(&p), -1),
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn power_minus_one() {
+        // 2^12 - 1 = 3^2 * 5 * 7 * 13
         assert_eq!(
-            crt(
-                &[2.into(), 5.into(), 7.into()],
-                &[6.into(), 9.into(), 15.into()]
-            ),
-            None
+            factor_power_minus_one(&2.into(), 12),
+            Factorization::from(HashMap::from([
+                (3.into(), 2),
+                (5.into(), 1),
+                (7.into(), 1),
+                (13.into(), 1)
+            ]))
         );
     }
 }
@@ -0,0 +1,525 @@
+use rug::{
+    ops::{Pow, RemRounding},
+    Integer,
+};
+
+use crate::{
+    crt::crt, discrete_log_with_order, factorize::factor, primitive_root, Error, Factorization,
+};
+
+/// Cyclic factor `(Z/p^eZ)*` of `(Z/nZ)*` (or one of the two cyclic factors of `(Z/2^eZ)*`), with its generator modulo
+/// `p**e` and its order.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct CyclicComponent {
+    modulus: Integer,
+    generator: Integer,
+    order: Integer,
+}
+
+/// Generator of prime power order `q**k` of `(Z/nZ)*`, the `q`-part of the generator of a cyclic component.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct PrimaryGenerator {
+    element: Integer,
+    q: Integer,
+    k: u32,
+    component: usize,
+    /// `order/q**k`, `order` being the order of the cyclic component.
+    cofactor: Integer,
+}
+
+/// Change of coordinates from the primary generators of `(Z/nZ)*` of order a power of `q` to the basis generators of
+/// order a power of `q` (Smith normal form over `Z/q^EZ`, the coordinates being scaled to `Z/q^EZ`).
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Transform {
+    q: Integer,
+    exponent: u32,
+    /// Indices of the primary generators of order a power of `q`.
+    rows: Vec<usize>,
+    /// Invertible matrix `U` such that `U * A * V` is diagonal.
+    u: Vec<Vec<Integer>>,
+    /// `q`-valuations of the non-zero diagonal entries.
+    valuations: Vec<u32>,
+}
+
+/// Basis of a subgroup `H` of `(Z/nZ)*`: generators `g_i` of prime power orders `o_i` such that every element of `H` is
+/// written uniquely as `g_1**x_1 * ... * g_k**x_k` with `0 <= x_i < o_i`.
+///
+/// Bases are built by `unit_group_basis` (`H = (Z/nZ)*`) and `subgroup_basis` (`H` generated by given units), and the
+/// exponents `x_i` of an element are computed by `discrete_log_vector`. The generators are grouped by prime, in
+/// increasing order, and by decreasing order for each prime.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UnitGroupBasis {
+    n: Integer,
+    generators: Vec<Integer>,
+    orders: Vec<Integer>,
+    components: Vec<CyclicComponent>,
+    primary: Vec<PrimaryGenerator>,
+    transforms: Vec<Transform>,
+}
+
+impl UnitGroupBasis {
+    /// Returns the modulus `n`.
+    pub fn modulus(&self) -> &Integer {
+        &self.n
+    }
+
+    /// Returns the generators `g_i` of the basis.
+    pub fn generators(&self) -> &[Integer] {
+        &self.generators
+    }
+
+    /// Returns the orders `o_i` of the generators, which are prime powers.
+    pub fn orders(&self) -> &[Integer] {
+        &self.orders
+    }
+
+    /// Returns the order of the subgroup, the product of the orders of the generators.
+    pub fn order(&self) -> Integer {
+        self.orders.iter().product()
+    }
+
+    /// Returns `g_1**x_1 * ... * g_k**x_k` modulo `n` for the given exponents (negative exponents are allowed).
+    ///
+    /// # Panics
+    ///
+    /// Panics if the number of exponents is not the number of generators.
+    pub fn element(&self, exponents: &[Integer]) -> Integer {
+        assert_eq!(exponents.len(), self.generators.len());
+        self.generators
+            .iter()
+            .zip(exponents)
+            .fold(Integer::from(1) % &self.n, |acc, (g, x)| {
+                acc * g.clone().pow_mod(x, &self.n).unwrap() % &self.n
+            })
+    }
+
+    /// Returns the coordinates of `a` on the primary generators of `(Z/nZ)*`.
+    fn primary_log(&self, a: &Integer) -> Result<Vec<Integer>, Error> {
+        let logs = self
+            .components
+            .iter()
+            .map(|c| {
+                let mut a = Integer::from(a % &c.modulus);
+                if c.modulus.is_even() {
+                    // (Z/2^eZ)* = <-1> x <5>: the sign is given by a mod 4
+                    let negative = a.mod_u(4) == 3;
+                    if c.generator == Integer::from(&c.modulus - 1) {
+                        return Ok(Integer::from(negative as u32));
+                    }
+                    if negative {
+                        a = Integer::from(&c.modulus - &a);
+                    }
+                }
+                discrete_log_with_order(&c.modulus, &a, &c.generator, &c.order)
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(self
+            .primary
+            .iter()
+            .map(|g| {
+                let qk = g.q.clone().pow(g.k);
+                let inverse = g.cofactor.clone().invert(&qk).unwrap();
+                (&logs[g.component] * inverse) % qk
+            })
+            .collect())
+    }
+}
+
+/// Returns the cyclic components of `(Z/nZ)*`, from the prime factorization of `n`.
+fn cyclic_components(n_factors: &Factorization) -> Vec<CyclicComponent> {
+    let mut components = Vec::new();
+    for (p, e) in n_factors.iter() {
+        let modulus = p.clone().pow(e as u32);
+        if *p == 2 {
+            // (Z/2^eZ)* = <-1> x <5>
+            if e >= 2 {
+                let generator = Integer::from(&modulus - 1);
+                components.push(CyclicComponent {
+                    modulus: modulus.clone(),
+                    generator,
+                    order: 2.into(),
+                });
+            }
+            if e >= 3 {
+                components.push(CyclicComponent {
+                    modulus,
+                    generator: 5.into(),
+                    order: Integer::from(1) << (e as u32 - 2),
+                });
+            }
+        } else {
+            let order = p.clone().pow(e as u32 - 1) * Integer::from(p - 1);
+            let generator = primitive_root(&modulus).unwrap();
+            components.push(CyclicComponent {
+                modulus,
+                generator,
+                order,
+            });
+        }
+    }
+    components
+}
+
+/// Returns the primary generators of `(Z/nZ)*`, the prime power order parts of the generators of its cyclic
+/// components, lifted to `Z/nZ`.
+///
+/// Returns `Error::IncompleteFactorization` if the order of a component cannot be completely factored.
+fn primary_generators(
+    n: &Integer,
+    components: &[CyclicComponent],
+) -> Result<Vec<PrimaryGenerator>, Error> {
+    let mut primary = Vec::new();
+    for (i, c) in components.iter().enumerate() {
+        let cofactor_modulus = Integer::from(n / &c.modulus);
+        let order_factors = factor(&c.order);
+        if !order_factors.is_complete() {
+            return Err(Error::IncompleteFactorization);
+        }
+        for (q, k) in order_factors.iter() {
+            let cofactor = &c.order / q.clone().pow(k as u32);
+            let local = c.generator.clone().pow_mod(&cofactor, &c.modulus).unwrap();
+            let (element, _) = crt(
+                &[local, 1.into()],
+                &[c.modulus.clone(), cofactor_modulus.clone()],
+            )
+            .unwrap();
+            primary.push(PrimaryGenerator {
+                element,
+                q: q.clone(),
+                k: k as u32,
+                component: i,
+                cofactor,
+            });
+        }
+    }
+    primary.sort_by(|a, b| a.q.cmp(&b.q));
+    Ok(primary)
+}
+
+/// Computes the Smith normal form of the `r x s` matrix `a` over `Z/q^EZ`, `modulus` being `q**E`.
+///
+/// Returns `(U, V, valuations)` such that `U * a * V` is diagonal, its non-zero diagonal entries being
+/// `q**valuations[i]`.
+fn smith_form(
+    mut a: Vec<Vec<Integer>>,
+    s: usize,
+    q: &Integer,
+    modulus: &Integer,
+) -> (Vec<Vec<Integer>>, Vec<Vec<Integer>>, Vec<u32>) {
+    let r = a.len();
+    let identity = |size: usize| {
+        (0..size)
+            .map(|i| (0..size).map(|j| Integer::from(i == j)).collect::<Vec<_>>())
+            .collect::<Vec<_>>()
+    };
+    let (mut u, mut v) = (identity(r), identity(s));
+    let valuation = |x: &Integer| {
+        let mut x = x.clone();
+        x.remove_factor_mut(q)
+    };
+
+    let mut valuations = Vec::new();
+    for t in 0..r.min(s) {
+        // Pivot of smallest valuation
+        let pivot = (t..r)
+            .flat_map(|i| (t..s).map(move |j| (i, j)))
+            .filter(|&(i, j)| a[i][j] != 0)
+            .min_by_key(|&(i, j)| valuation(&a[i][j]));
+        let Some((pi, pj)) = pivot else {
+            break;
+        };
+        a.swap(t, pi);
+        u.swap(t, pi);
+        for row in a.iter_mut() {
+            row.swap(t, pj);
+        }
+        for row in v.iter_mut() {
+            row.swap(t, pj);
+        }
+
+        // Make the pivot a power of q
+        let mut unit = a[t][t].clone();
+        let e = unit.remove_factor_mut(q);
+        let inverse = unit.invert(modulus).unwrap();
+        for x in a[t].iter_mut().chain(u[t].iter_mut()) {
+            *x = Integer::from(&*x * &inverse) % modulus;
+        }
+        let pivot = q.clone().pow(e);
+
+        // Clear the column, then the row of the pivot
+        let (pivot_row, pivot_u) = (a[t].clone(), u[t].clone());
+        for (i, (row, u_row)) in a.iter_mut().zip(u.iter_mut()).enumerate() {
+            if i != t && row[t] != 0 {
+                let c = Integer::from(&row[t] / &pivot);
+                for (x, p) in row.iter_mut().zip(&pivot_row) {
+                    *x = (&*x - Integer::from(&c * p)).rem_euc(modulus);
+                }
+                for (x, p) in u_row.iter_mut().zip(&pivot_u) {
+                    *x = (&*x - Integer::from(&c * p)).rem_euc(modulus);
+                }
+            }
+        }
+        for j in 0..s {
+            if j != t && a[t][j] != 0 {
+                let c = Integer::from(&a[t][j] / &pivot);
+                for row in a.iter_mut().chain(v.iter_mut()) {
+                    let x = Integer::from(&c * &row[t]);
+                    row[j] = (&row[j] - x).rem_euc(modulus);
+                }
+            }
+        }
+        valuations.push(e);
+    }
+    (u, v, valuations)
+}
+
+/// Returns a basis of the subgroup of `(Z/nZ)*` generated by `elements`, given the cyclic components of `(Z/nZ)*`.
+fn basis(
+    n: &Integer,
+    components: Vec<CyclicComponent>,
+    elements: &[Integer],
+) -> Result<UnitGroupBasis, Error> {
+    let primary = primary_generators(n, &components)?;
+    let mut basis = UnitGroupBasis {
+        n: n.clone(),
+        generators: Vec::new(),
+        orders: Vec::new(),
+        components,
+        primary,
+        transforms: Vec::new(),
+    };
+    let logs = elements
+        .iter()
+        .map(|h| basis.primary_log(h))
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let mut start = 0;
+    while start < basis.primary.len() {
+        let q = basis.primary[start].q.clone();
+        let rows = (start..basis.primary.len())
+            .take_while(|&i| basis.primary[i].q == q)
+            .collect::<Vec<_>>();
+        start += rows.len();
+        let exponent = rows.iter().map(|&i| basis.primary[i].k).max().unwrap();
+        let modulus = q.clone().pow(exponent);
+
+        // Coordinates of the elements, scaled from Z/q^kZ to Z/q^EZ
+        let scale = |i: usize| q.clone().pow(exponent - basis.primary[i].k);
+        let a = rows
+            .iter()
+            .map(|&i| logs.iter().map(|x| &x[i] * scale(i)).collect())
+            .collect::<Vec<_>>();
+        let (u, v, valuations) = smith_form(a.clone(), elements.len(), &q, &modulus);
+
+        for (t, e) in valuations.iter().enumerate() {
+            // Generator of coordinates a * v[.][t], unscaled
+            let mut generator = Integer::from(1);
+            for (row, &i) in rows.iter().enumerate() {
+                let x = a[row]
+                    .iter()
+                    .zip(&v)
+                    .map(|(x, v_row)| Integer::from(x * &v_row[t]))
+                    .sum::<Integer>()
+                    % &modulus;
+                let y = x / scale(i);
+                let power = basis.primary[i].element.clone().pow_mod(&y, n).unwrap();
+                generator = generator * power % n;
+            }
+            basis.generators.push(generator);
+            basis.orders.push(q.clone().pow(exponent - e));
+        }
+        basis.transforms.push(Transform {
+            q,
+            exponent,
+            rows,
+            u,
+            valuations,
+        });
+    }
+    Ok(basis)
+}
+
+/// Returns a basis of `(Z/nZ)*` made of generators of prime power order (`n` must be positive).
+///
+/// The generators are the prime power order parts of primitive roots modulo the prime powers `p**e` dividing `n` (and
+/// of `-1` and `5` modulo `2**e`), lifted to `Z/nZ` with the Chinese remainder theorem.
+pub fn unit_group_basis(n: &Integer) -> Result<UnitGroupBasis, Error> {
+    if *n < 1 {
+        return Err(Error::InvalidModulus);
+    }
+    unit_group_basis_with_factors(n, &factor(n))
+}
+
+/// Returns a basis of `(Z/nZ)*` made of generators of prime power order (`n` must be positive).
+///
+/// `n_factors` must be the prime factorization of `n`.
+pub fn unit_group_basis_with_factors(
+    n: &Integer,
+    n_factors: &Factorization,
+) -> Result<UnitGroupBasis, Error> {
+    if *n < 1 {
+        return Err(Error::InvalidModulus);
+    }
+    if !n_factors.is_complete() {
+        return Err(Error::IncompleteFactorization);
+    }
+    if n_factors.value() != *n {
+        return Err(Error::InvalidFactorization);
+    }
+    let components = cyclic_components(n_factors);
+    let elements = primary_generators(n, &components)?
+        .into_iter()
+        .map(|g| g.element)
+        .collect::<Vec<_>>();
+    basis(n, components, &elements)
+}
+
+/// Returns a basis of the subgroup of `(Z/nZ)*` generated by `elements`, made of generators of prime power order.
+///
+/// Returns `Error::NotRelativelyPrime` if an element is not a unit modulo `n`, and `Error::IncompleteFactorization` if
+/// `n` cannot be completely factored.
+pub fn subgroup_basis(n: &Integer, elements: &[Integer]) -> Result<UnitGroupBasis, Error> {
+    if *n < 1 {
+        return Err(Error::InvalidModulus);
+    }
+    if elements.iter().any(|h| Integer::from(h.gcd_ref(n)) != 1) {
+        return Err(Error::NotRelativelyPrime);
+    }
+    let n_factors = factor(n);
+    if !n_factors.is_complete() {
+        return Err(Error::IncompleteFactorization);
+    }
+    basis(n, cyclic_components(&n_factors), elements)
+}
+
+/// Compute the vector discrete logarithm of `a` on `basis` (exponents `x_i` with `0 <= x_i < o_i` where
+/// `a = g_1**x_1 * ... * g_k**x_k (mod n)`).
+///
+/// The logarithms in the cyclic components `(Z/p^eZ)*` are computed with `discrete_log_with_order` (Pohlig-Hellman for
+/// composite orders), then mapped to the basis with a Smith normal form of the subgroup for each prime dividing its
+/// order. Returns `Error::LogDoesNotExist` if `a` does not lie in the subgroup spanned by the basis.
+pub fn discrete_log_vector(basis: &UnitGroupBasis, a: &Integer) -> Result<Vec<Integer>, Error> {
+    if Integer::from(a.gcd_ref(&basis.n)) != 1 {
+        return Err(Error::NotRelativelyPrime);
+    }
+    let logs = basis.primary_log(a)?;
+    let mut exponents = Vec::new();
+    for transform in &basis.transforms {
+        let q = &transform.q;
+        let modulus = q.clone().pow(transform.exponent);
+        let y = transform
+            .rows
+            .iter()
+            .map(|&i| &logs[i] * q.clone().pow(transform.exponent - basis.primary[i].k))
+            .collect::<Vec<_>>();
+        for (t, u_row) in transform.u.iter().enumerate() {
+            let z = u_row
+                .iter()
+                .zip(&y)
+                .map(|(u, y)| Integer::from(u * y))
+                .sum::<Integer>()
+                % &modulus;
+            match transform.valuations.get(t) {
+                Some(&e) => {
+                    let pivot = q.clone().pow(e);
+                    if !z.is_divisible(&pivot) {
+                        return Err(Error::LogDoesNotExist);
+                    }
+                    exponents.push(z / pivot);
+                }
+                None if z != 0 => return Err(Error::LogDoesNotExist),
+                None => {}
+            }
+        }
+    }
+    Ok(exponents)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+
+    use super::*;
+    use crate::n_order;
+
+    /// Elements of the subgroup of (Z/nZ)* generated by `elements`, by brute force.
+    fn span(n: u32, elements: &[u32]) -> HashSet<u32> {
+        let mut span = HashSet::from([1 % n]);
+        let mut frontier = vec![1 % n];
+        while let Some(x) = frontier.pop() {
+            for h in elements {
+                let y = (x as u64 * *h as u64 % n as u64) as u32;
+                if span.insert(y) {
+                    frontier.push(y);
+                }
+            }
+        }
+        span
+    }
+
+    #[test]
+    fn unit_group_basis_() {
+        for n in [1u32, 2, 4, 8, 15, 16, 45, 360, 1001] {
+            let basis = unit_group_basis(&n.into()).unwrap();
+            assert_eq!(basis.order(), crate::euler_phi(&n.into()).unwrap());
+            for (g, o) in basis.generators().iter().zip(basis.orders()) {
+                assert_eq!(n_order(g, &n.into()).unwrap(), *o);
+            }
+            for a in (1..n).filter(|a| Integer::from(*a).gcd(&n.into()) == 1) {
+                let log = discrete_log_vector(&basis, &a.into()).unwrap();
+                assert!(log
+                    .iter()
+                    .zip(basis.orders())
+                    .all(|(x, o)| *x >= 0 && x < o));
+                assert_eq!(basis.element(&log), a);
+            }
+        }
+        let basis = unit_group_basis(&360.into()).unwrap();
+        assert_eq!(basis.orders(), [4, 2, 2, 2, 3]);
+        assert_eq!(
+            discrete_log_vector(&basis, &6.into()),
+            Err(Error::NotRelativelyPrime)
+        );
+
+        let n = Integer::from(1000003) * 1000033;
+        let basis = unit_group_basis(&n).unwrap();
+        let a = Integer::from(123456789);
+        assert_eq!(basis.element(&discrete_log_vector(&basis, &a).unwrap()), a);
+    }
+
+    #[test]
+    fn subgroup_basis_() {
+        for (n, elements) in [
+            (15u32, vec![2u32]),
+            (105, vec![2, 11]),
+            (105, vec![4, 16, 64]),
+            (1001, vec![10, 100, 34]),
+            (720, vec![7, 11, 13]),
+            (91, vec![]),
+        ] {
+            let elements_int = elements
+                .iter()
+                .map(|h| Integer::from(*h))
+                .collect::<Vec<_>>();
+            let basis = subgroup_basis(&n.into(), &elements_int).unwrap();
+            let span = span(n, &elements);
+            assert_eq!(basis.order(), span.len());
+            for a in (1..n).filter(|a| Integer::from(*a).gcd(&n.into()) == 1) {
+                match discrete_log_vector(&basis, &a.into()) {
+                    Ok(log) => {
+                        assert!(span.contains(&a));
+                        assert_eq!(basis.element(&log), a);
+                    }
+                    Err(e) => {
+                        assert_eq!(e, Error::LogDoesNotExist);
+                        assert!(!span.contains(&a));
+                    }
+                }
+            }
+        }
+        assert_eq!(
+            subgroup_basis(&15.into(), &[3.into()]),
+            Err(Error::NotRelativelyPrime)
+        );
+    }
+}